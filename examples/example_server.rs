@@ -1,5 +1,6 @@
 use tiny_http::error::{Error, InvalidUri, Result};
 use tiny_http::http::{Request, Response};
+use tiny_http::server::extract::Path;
 use tiny_http::server::HttpServer;
 
 use bytes::Bytes;
@@ -10,12 +11,16 @@ fn main() {
         .workers(4)
         .route("/", "GET", Box::new(route_home))
         .route(
-            "/files",
+            "/files/:filename",
             "GET",
-            Box::new(move |request: &Request<Bytes>| {
-                let filename = request.uri().inner.chars().skip(7).collect::<String>();
+            Box::new(move |Path(filename): Path<String>| {
+                // The parameter is percent-decoded, so `..%2F` must not
+                // reach outside of the directory.
+                if filename.contains(['/', '\\']) || filename.contains("..") {
+                    return Err(InvalidUri::with_context(filename).into());
+                }
                 let file_content = read_to_string(format!("examples/files/{filename}"))
-                    .map_err(|_| Error::from(InvalidUri))?;
+                    .map_err(|_| InvalidUri::with_context(filename))?;
                 Response::builder()
                     .with_status(200)
                     .with_header("Content-Type", b"text/html")
@@ -39,10 +44,10 @@ fn route_home(_: &Request<Bytes>) -> Result<Response<Bytes>> {
         .with_body(Bytes::from(home))
 }
 
-fn route_error(_: &Request<Bytes>) -> Result<Response<Bytes>> {
+fn route_error(_: &Request<Bytes>, error: &Error) -> Result<Response<Bytes>> {
     let err = read_to_string("examples/files/error.html").expect("Failed to read file.");
     Response::builder()
-        .with_status(404)
+        .with_status(error.status())
         .with_header("Content-Type", b"text/html")
         .with_body(Bytes::from(err))
 }
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use super::pool::Pool;
+use super::Client;
+use crate::error::Result;
+
+/// An HTTP client builder.
+pub struct Builder {
+    inner: Result<Parts>,
+}
+
+impl Default for Builder {
+    #[inline]
+    fn default() -> Self {
+        Builder {
+            inner: Ok(Parts::default()),
+        }
+    }
+}
+
+impl Builder {
+    /// Creates a new default instance of `Builder` to construct a `Client`.
+    #[inline]
+    pub fn new() -> Self {
+        Builder::default()
+    }
+
+    /// Sets the time that connecting to a server may take, or no limit if
+    /// `None` or zero. The default is 30 seconds.
+    #[inline]
+    pub fn connect_timeout(self, timeout: Option<Duration>) -> Self {
+        let inner = self.inner.map(move |mut parts| {
+            parts.connect_timeout = timeout.filter(|timeout| !timeout.is_zero());
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Sets the time that every read and every write of a connection may
+    /// take, or no limit if `None` or zero. The default is 30 seconds.
+    ///
+    /// A request that times out fails with an I/O error of kind `TimedOut`.
+    #[inline]
+    pub fn timeout(self, timeout: Option<Duration>) -> Self {
+        let inner = self.inner.map(move |mut parts| {
+            parts.timeout = timeout.filter(|timeout| !timeout.is_zero());
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Sets the maximum number of redirects that are followed for a request,
+    /// after which it fails with `TooManyRedirects`. With 0, redirects are
+    /// returned as they are. The default is 10.
+    #[inline]
+    pub fn max_redirects(self, max: usize) -> Self {
+        let inner = self.inner.map(move |mut parts| {
+            parts.max_redirects = max;
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Sets the maximum number of idle connections that are kept for reuse
+    /// per host. With 0, every request opens a connection. The default is 8.
+    #[inline]
+    pub fn max_idle_per_host(self, max: usize) -> Self {
+        let inner = self.inner.map(move |mut parts| {
+            parts.max_idle_per_host = max;
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Builds the `Client`.
+    pub fn build(self) -> Result<Client> {
+        self.inner.map(|parts| Client {
+            pool: Pool::new(parts.max_idle_per_host),
+            connect_timeout: parts.connect_timeout,
+            timeout: parts.timeout,
+            max_redirects: parts.max_redirects,
+        })
+    }
+}
+
+struct Parts {
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    max_redirects: usize,
+    max_idle_per_host: usize,
+}
+
+impl Default for Parts {
+    #[inline]
+    fn default() -> Self {
+        Parts {
+            connect_timeout: Some(Duration::from_secs(30)),
+            timeout: Some(Duration::from_secs(30)),
+            max_redirects: 10,
+            max_idle_per_host: 8,
+        }
+    }
+}
@@ -0,0 +1,331 @@
+pub mod build;
+pub mod pool;
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use bytes::Bytes;
+
+use build::Builder;
+use pool::{Connection, Origin, Pool};
+
+use crate::error::{Error, ErrorKind, FailedConnection, InvalidUri, Result, TooManyRedirects};
+use crate::http::header::typed::Host;
+use crate::http::header::TypedHeader;
+use crate::http::request::parts::Parts;
+use crate::http::{
+    Body, Encode, HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode, Uri,
+    Version,
+};
+
+/// A blocking HTTP/1.1 client.
+///
+/// A request is sent to the host of its absolute URI, e.g.
+/// `http://localhost:8080/path`, or to the host of its `Host` header if its
+/// URI is only a path. Only `http` is supported.
+///
+/// The connections are kept open for the next requests to the same host,
+/// so a `Client` is meant to be shared, e.g. between threads.
+pub struct Client {
+    pool: Pool,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    max_redirects: usize,
+}
+
+impl Default for Client {
+    #[inline]
+    fn default() -> Self {
+        Client::builder()
+            .build()
+            .expect("Guaranteed by the default options.")
+    }
+}
+
+impl Client {
+    /// Creates a `Client` with the default options.
+    #[inline]
+    pub fn new() -> Self {
+        Client::default()
+    }
+
+    /// Returns a `Builder` that constructs a `Client`.
+    #[inline]
+    pub fn builder() -> Builder {
+        Builder::new()
+    }
+
+    /// Sends the request and returns the response, whose body is read
+    /// entirely, following the redirects of the response.
+    ///
+    /// A `303 See Other` redirect, and a `301` or `302` one of a `POST`
+    /// request, are followed with a `GET` request without a body. The
+    /// credentials of the request are not sent to another host. A redirect
+    /// to another scheme than `http`, e.g. `https`, is returned as it is.
+    ///
+    /// A request that times out fails with an I/O error of kind `TimedOut`,
+    /// and is not sent again.
+    pub fn send<T: Body>(&self, request: Request<T>) -> Result<Response<Bytes>> {
+        let (mut head, body) = request.into_parts();
+        let mut bytes = Vec::new();
+        body.write_to(&mut bytes)?;
+        let mut body = Bytes::from(bytes);
+        if head.version.major() != 1 {
+            head.version = Version::HTTP_11;
+        }
+
+        let mut redirects = 0;
+        loop {
+            let (host, path) = target(&head)?;
+            head.uri = Uri::new(&path);
+            head.headers.typed_insert(host.clone());
+            let request = Request::from_parts(head, body);
+            let response = self.exchange(&host, &request).map_err(timed_out)?;
+            (head, body) = request.into_parts();
+
+            let Some(location) = redirect(&response, &host, &path)? else {
+                return Ok(response);
+            };
+            if self.max_redirects == 0 {
+                return Ok(response);
+            }
+            redirects += 1;
+            if redirects > self.max_redirects {
+                return Err(TooManyRedirects::with_context(location).into());
+            }
+            let status = response.status().code().get();
+            if status == 303 && head.method != Method::HEAD
+                || matches!(status, 301 | 302) && head.method == Method::POST
+            {
+                head.method = Method::GET;
+                head.headers.remove_body_len();
+                head.headers.remove(&HeaderName::CONTENT_TYPE);
+                body = Bytes::new();
+            }
+            head.uri = Uri { inner: location };
+            let (next, _) = target(&head)?;
+            if origin(&next) != origin(&host) {
+                remove_credentials(&mut head.headers);
+            }
+        }
+    }
+
+    /// Sends the request to the host and reads its response, on a connection
+    /// of the pool if there is one.
+    ///
+    /// A pooled connection that the server has closed in the meantime fails
+    /// before the response starts, in which case the request is sent again on
+    /// another connection, if sending it twice is safe, i.e. if its method is
+    /// idempotent.
+    fn exchange(&self, host: &Host, request: &Request<Bytes>) -> Result<Response<Bytes>> {
+        let origin = origin(host);
+        let bytes = request.to_bytes()?;
+        let retryable = matches!(*request.method(), Method::GET | Method::HEAD);
+        loop {
+            let (mut connection, reused) = match self.pool.take(&origin) {
+                Some(connection) => (connection, true),
+                None => (self.connect(&origin)?, false),
+            };
+            let retry = reused && retryable;
+            let stream = connection.get_mut();
+            match stream.write_all(&bytes).and_then(|()| stream.flush()) {
+                Err(err) if retry && is_disconnect(&err) => continue,
+                result => result?,
+            }
+            match connection.fill_buf() {
+                Ok([]) if retry => continue,
+                Err(err) if retry && is_disconnect(&err) => continue,
+                Ok([]) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                result => result.map(|_| ())?,
+            }
+
+            let method = request.method();
+            let mut head = Response::read_head(&mut connection)?;
+            // Interim responses precede the response to the request
+            while (100..200).contains(&head.status().code().get())
+                && *head.status() != StatusCode::SWITCHING_PROTOCOLS
+            {
+                head = Response::read_head(&mut connection)?;
+            }
+            let persistent = is_persistent(request, &head);
+            let response = head.read_body(&mut connection, method)?;
+            if persistent {
+                self.pool.put(origin, connection);
+            }
+            return Ok(response);
+        }
+    }
+
+    /// Opens a connection to the first address of the server that accepts it.
+    fn connect(&self, origin: &Origin) -> Result<Connection> {
+        let (hostname, port) = origin;
+        let failed =
+            |err| FailedConnection::with_context(format!("{hostname}:{port}")).with_source(err);
+        let mut last_err = io::Error::from(io::ErrorKind::AddrNotAvailable);
+        for addr in (hostname.as_str(), *port)
+            .to_socket_addrs()
+            .map_err(failed)?
+        {
+            let stream = match self.connect_timeout {
+                Some(timeout) => TcpStream::connect_timeout(&addr, timeout),
+                None => TcpStream::connect(addr),
+            };
+            match stream {
+                Ok(stream) => {
+                    stream.set_nodelay(true)?;
+                    stream.set_read_timeout(self.timeout)?;
+                    stream.set_write_timeout(self.timeout)?;
+                    return Ok(BufReader::new(stream));
+                }
+                Err(err) => last_err = err,
+            }
+        }
+        Err(failed(last_err).into())
+    }
+}
+
+/// Returns the host that the request is sent to, and its path and query,
+/// from its absolute URI or from its path and its `Host` header.
+fn target(head: &Parts) -> Result<(Host, String)> {
+    let uri = head.uri.inner.as_str();
+    let uri = uri.split_once('#').map_or(uri, |(uri, _)| uri);
+    if let Some(rest) = strip_scheme(uri) {
+        let end = rest.find(['/', '?']).unwrap_or(rest.len());
+        let (authority, path) = rest.split_at(end);
+        let host = (!authority.contains('@'))
+            .then(|| HeaderValue::try_from(authority).ok())
+            .flatten()
+            .and_then(|authority| Host::decode(&authority))
+            .filter(|host| !host.hostname().is_empty())
+            .ok_or_else(|| InvalidUri::with_context(uri))?;
+        return Ok((host, format!("/{}", path.trim_start_matches('/'))));
+    }
+    if !uri.starts_with('/') {
+        return Err(InvalidUri::with_context(uri).into());
+    }
+    match head.headers.typed_get::<Host>()? {
+        Some(host) => Ok((host, uri.to_owned())),
+        None => Err(InvalidUri::with_context(format!("{uri} without a Host header")).into()),
+    }
+}
+
+/// Returns the URI without its `http` scheme, or `None` for other URIs.
+fn strip_scheme(uri: &str) -> Option<&str> {
+    uri.get(..7)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("http://"))
+        .map(|_| &uri[7..])
+}
+
+/// Returns the host, in lowercase, and the port that the client connects to.
+fn origin(host: &Host) -> Origin {
+    (
+        host.hostname().to_ascii_lowercase(),
+        host.port().unwrap_or(80),
+    )
+}
+
+/// Returns the location of the response, resolved against the host and the
+/// path of the request, if it is a redirect that can be followed, i.e. to
+/// another `http` URI.
+fn redirect(response: &Response<Bytes>, host: &Host, path: &str) -> Result<Option<String>> {
+    if !matches!(response.status().code().get(), 301 | 302 | 303 | 307 | 308) {
+        return Ok(None);
+    }
+    let Some(location) = response.headers().get(&HeaderName::LOCATION) else {
+        return Ok(None);
+    };
+    let location = resolve(location.to_str()?.trim(), host, path);
+    Ok(strip_scheme(&location).is_some().then_some(location))
+}
+
+/// Resolves the location of a redirect against the host and the path of the
+/// request that was redirected, into an absolute URI.
+fn resolve(location: &str, host: &Host, path: &str) -> String {
+    let location = location
+        .split_once('#')
+        .map_or(location, |(location, _)| location);
+    if has_scheme(location) {
+        return location.to_owned();
+    }
+    if let Some(rest) = location.strip_prefix("//") {
+        return format!("http://{rest}");
+    }
+    let authority = host.encode();
+    let base = format!("http://{}", String::from_utf8_lossy(authority.as_bytes()));
+    let path = path.split_once('?').map_or(path, |(path, _)| path);
+    match location.as_bytes().first() {
+        Some(b'/') => format!("{base}{location}"),
+        Some(b'?') | None => format!("{base}{path}{location}"),
+        Some(_) => {
+            let directory = path.rsplit_once('/').map_or("", |(directory, _)| directory);
+            format!("{base}{directory}/{location}")
+        }
+    }
+}
+
+/// Checks whether the URI reference is absolute, i.e. starts with a scheme,
+/// a letter then letters, digits, `+`, `-` or `.`, followed by a colon before
+/// any `/`, `?` or `#`.
+fn has_scheme(location: &str) -> bool {
+    let Some(end) = location.find([':', '/', '?', '#']) else {
+        return false;
+    };
+    let (scheme, rest) = location.split_at(end);
+    rest.starts_with(':')
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+/// Removes the headers of the request that carry credentials, before it is
+/// redirected to another host.
+fn remove_credentials(headers: &mut HeaderMap) {
+    for name in [
+        HeaderName::AUTHORIZATION,
+        HeaderName::PROXY_AUTHORIZATION,
+        HeaderName::COOKIE,
+    ] {
+        headers.remove(&name);
+    }
+}
+
+/// Checks whether the connection can send further requests once the body
+/// of the response has been read.
+fn is_persistent<T>(request: &Request<T>, response: &Response<()>) -> bool {
+    let headers = response.headers();
+    let keep_alive = match *response.version() {
+        Version::HTTP_11 => !headers.has_token(&HeaderName::CONNECTION, "close"),
+        Version::HTTP_10 => headers.has_token(&HeaderName::CONNECTION, "keep-alive"),
+        _ => false,
+    };
+    keep_alive
+        && !request
+            .headers()
+            .has_token(&HeaderName::CONNECTION, "close")
+        && *response.status() != StatusCode::SWITCHING_PROTOCOLS
+        && (!response.has_body(request.method()) || response.is_delimited())
+}
+
+/// Returns whether the error means that the server has closed the connection.
+fn is_disconnect(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+    )
+}
+
+/// Reports a read or a write that timed out, which some platforms report as
+/// `WouldBlock`, with an I/O error of kind `TimedOut`.
+fn timed_out(err: Error) -> Error {
+    match err.kind() {
+        ErrorKind::Io(io) if io.kind() == io::ErrorKind::WouldBlock => {
+            io::Error::new(io::ErrorKind::TimedOut, "the request timed out").into()
+        }
+        _ => err,
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::io::{self, BufReader};
+use std::net::TcpStream;
+use std::sync::{Mutex, PoisonError};
+
+/// A connection to a server, with the bytes that have been read from it.
+pub(crate) type Connection = BufReader<TcpStream>;
+
+/// The host, in lowercase, and the port of a server.
+pub(crate) type Origin = (String, u16);
+
+/// The idle connections of a client, that are reused for the next requests
+/// to the same server.
+pub(crate) struct Pool {
+    idle: Mutex<HashMap<Origin, Vec<Connection>>>,
+    max_idle_per_host: usize,
+}
+
+impl Pool {
+    /// Creates a pool that keeps at most `max_idle_per_host` connections to
+    /// every server.
+    pub(crate) fn new(max_idle_per_host: usize) -> Self {
+        Pool {
+            idle: Mutex::new(HashMap::new()),
+            max_idle_per_host,
+        }
+    }
+
+    /// Takes the connection to the server that was used last, if there is one,
+    /// skipping the connections that the server has closed in the meantime.
+    pub(crate) fn take(&self, origin: &Origin) -> Option<Connection> {
+        let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+        let connections = idle.get_mut(origin)?;
+        let mut connection = None;
+        while let Some(idle) = connections.pop() {
+            if is_idle(idle.get_ref()) {
+                connection = Some(idle);
+                break;
+            }
+        }
+        if connections.is_empty() {
+            idle.remove(origin);
+        }
+        connection
+    }
+
+    /// Keeps the connection for the next request to the server, unless the
+    /// server has sent bytes that no request asked for or the pool is full.
+    pub(crate) fn put(&self, origin: Origin, connection: Connection) {
+        if !connection.buffer().is_empty() {
+            return;
+        }
+        let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+        let connections = idle.entry(origin).or_default();
+        if connections.len() < self.max_idle_per_host {
+            connections.push(connection);
+        }
+    }
+}
+
+/// Checks whether the server has neither closed the connection nor sent
+/// bytes that no request asked for, without blocking.
+fn is_idle(stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
+    let idle = matches!(
+        stream.peek(&mut [0]),
+        Err(err) if err.kind() == io::ErrorKind::WouldBlock
+    );
+    stream.set_nonblocking(false).is_ok() && idle
+}
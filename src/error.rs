@@ -1,21 +1,115 @@
+use std::convert::Infallible;
+use std::error;
+use std::fmt;
+use std::io;
 use std::result;
 
+use crate::http::StatusCode;
+
 #[derive(Debug)]
 pub struct Error {
-    #[allow(dead_code)]
     inner: ErrorKind,
 }
 
+impl Error {
+    /// Returns the kind of the error.
+    #[inline]
+    pub fn kind(&self) -> &ErrorKind {
+        &self.inner
+    }
+
+    /// Returns the status code of the response that reports the error.
+    #[inline]
+    pub fn status(&self) -> StatusCode {
+        self.inner.status()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.inner, f)
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.inner.source()
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error {
+            inner: ErrorKind::Io(err),
+        }
+    }
+}
+
+impl From<Infallible> for Error {
+    fn from(err: Infallible) -> Error {
+        match err {}
+    }
+}
+
 macro_rules! create_errors {
     (
         $(
-            ($error_kind:ident, $error:ident);
+            ($error_kind:ident, $error:ident, $status:ident, $description:expr);
         )+
     ) => {
 
         $(
-        #[derive(Debug)]
-        pub struct $error;
+        #[derive(Debug, Default)]
+        pub struct $error {
+            context: Option<String>,
+            source: Option<Box<dyn error::Error + Send + Sync>>,
+        }
+
+        impl $error {
+            /// Creates a new error without context.
+            #[inline]
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            /// Creates a new error with the offending value as context.
+            #[inline]
+            pub fn with_context<C: Into<String>>(context: C) -> Self {
+                Self { context: Some(context.into()), source: None }
+            }
+
+            /// Sets the underlying error that caused this one.
+            #[inline]
+            pub fn with_source<E>(mut self, source: E) -> Self
+            where
+                E: Into<Box<dyn error::Error + Send + Sync>>,
+            {
+                self.source = Some(source.into());
+                self
+            }
+
+            /// Returns the context of the error, if there is one.
+            #[inline]
+            pub fn context(&self) -> Option<&str> {
+                self.context.as_deref()
+            }
+        }
+
+        impl fmt::Display for $error {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str($description)?;
+                match &self.context {
+                    Some(context) => write!(f, ": `{}`", context),
+                    None => Ok(()),
+                }
+            }
+        }
+
+        impl error::Error for $error {
+            fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+                self.source.as_deref().map(|err| err as &(dyn error::Error + 'static))
+            }
+        }
 
         impl From<$error> for Error {
             fn from(err: $error) -> Error {
@@ -27,20 +121,74 @@ macro_rules! create_errors {
         #[derive(Debug)]
         pub enum ErrorKind {
             $($error_kind($error),)+
+            Io(io::Error),
+        }
+
+        impl ErrorKind {
+            /// Returns the status code of the response that reports an error
+            /// of this kind.
+            pub fn status(&self) -> StatusCode {
+                match self {
+                    $( ErrorKind::$error_kind(_) => StatusCode::$status, )+
+                    ErrorKind::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                }
+            }
+
+            /// Returns the underlying error that caused the error, if there is one.
+            fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+                match self {
+                    $( ErrorKind::$error_kind(err) => error::Error::source(err), )+
+                    ErrorKind::Io(err) => Some(err),
+                }
+            }
+        }
+
+        impl fmt::Display for ErrorKind {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self {
+                    $( ErrorKind::$error_kind(err) => fmt::Display::fmt(err, f), )+
+                    ErrorKind::Io(err) => write!(f, "I/O error: {}", err),
+                }
+            }
         }
 
     }
 }
 
 create_errors! {
-    (Method, InvalidMethod);
-    (Uri, InvalidUri);
-    (Version, InvalidVersion);
-    (StatusCode, InvalidStatusCode);
-    (Header, InvalidHeaderName);
-    (Body, InvalidBody);
-    (Connection, FailedConnection);
-    (ErrorHandler, NoErrorHandler);
+    (Method, InvalidMethod, NOT_IMPLEMENTED, "invalid HTTP method");
+    (Uri, InvalidUri, BAD_REQUEST, "invalid URI");
+    (Version, InvalidVersion, BAD_REQUEST, "invalid HTTP version");
+    (VersionSupport, UnsupportedVersion, HTTP_VERSION_NOT_SUPPORTED, "unsupported HTTP version");
+    (StatusCode, InvalidStatusCode, INTERNAL_SERVER_ERROR, "invalid status code");
+    (Header, InvalidHeaderName, BAD_REQUEST, "invalid header");
+    (HeaderValue, InvalidHeaderValue, BAD_REQUEST, "invalid header value");
+    (Body, InvalidBody, BAD_REQUEST, "invalid body");
+    (Connection, FailedConnection, INTERNAL_SERVER_ERROR, "failed connection");
+    (ErrorHandler, NoErrorHandler, INTERNAL_SERVER_ERROR, "no error handler");
+    (Route, NoMatchingRoute, NOT_FOUND, "no route matches the request path");
+    (RouteMethod, MethodNotAllowed, METHOD_NOT_ALLOWED, "route does not accept the request method");
+    (RouteConflict, AmbiguousRoute, INTERNAL_SERVER_ERROR, "more than one route matches the request path");
+    (Tls, InvalidCertificate, INTERNAL_SERVER_ERROR, "invalid TLS certificate or private key");
+    (WebSocket, WebSocketError, BAD_REQUEST, "WebSocket protocol error");
+    (WebSocketVersion, UnsupportedWebSocketVersion, UPGRADE_REQUIRED, "unsupported WebSocket version");
+    (Http2, Http2Error, BAD_REQUEST, "malformed HTTP/2 request");
+    (Expectation, ExpectationFailed, EXPECTATION_FAILED, "expectation cannot be met");
+    (PayloadSize, PayloadTooLarge, PAYLOAD_TOO_LARGE, "payload is too large");
+    (HeadSize, HeadersTooLarge, REQUEST_HEADER_FIELDS_TOO_LARGE, "header section is too large");
+    (Timeout, RequestTimeout, REQUEST_TIMEOUT, "request timed out");
+    (Date, InvalidDate, BAD_REQUEST, "invalid HTTP date");
+    (Redirect, TooManyRedirects, BAD_GATEWAY, "too many redirects");
+}
+
+impl FailedConnection {
+    /// Returns the I/O error that caused the failure, if there is one.
+    ///
+    /// Its kind distinguishes, for example, an address that is in use from
+    /// a permission that was denied.
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.source.as_deref().and_then(|err| err.downcast_ref())
+    }
 }
 
 pub type Result<T> = result::Result<T, Error>;
@@ -1,27 +1,171 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
 use bytes::Bytes;
 
-// TODO: Create a macro that implements the trait trivially to
-// types that have a `len` method.
+use crate::error::{InvalidBody, Result};
 
+/// Types that can be sent as the body of an HTTP message.
 pub trait Body {
-    /// Returns the number of bytes of the body.
-    fn content_len(&self) -> usize;
+    /// Returns the number of bytes of the body, if it is known before the
+    /// body is written.
+    ///
+    /// A response whose body has no known length is sent with chunked
+    /// transfer-coding.
+    fn content_len(&self) -> Option<usize>;
+
+    /// Writes the body to the writer.
+    fn write_to(&self, writer: &mut dyn Write) -> io::Result<()>;
+}
+
+/// Types that can be created from the body of a received HTTP message.
+pub trait FromBody: Sized {
+    /// Creates the body from the received bytes.
+    fn from_bytes(bytes: Bytes) -> Result<Self>;
+}
+
+macro_rules! byte_bodies {
+    ($($ty:ty),+ $(,)?) => {
+        $(
+            impl Body for $ty {
+                #[inline]
+                fn content_len(&self) -> Option<usize> {
+                    Some(self.len())
+                }
+
+                #[inline]
+                fn write_to(&self, writer: &mut dyn Write) -> io::Result<()> {
+                    writer.write_all(self.as_ref())
+                }
+            }
+        )+
+    }
+}
+
+byte_bodies!(String, &str, Vec<u8>, &[u8], Bytes);
+
+impl Body for () {
+    #[inline]
+    fn content_len(&self) -> Option<usize> {
+        Some(0)
+    }
+
+    #[inline]
+    fn write_to(&self, _: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<B: Body + ?Sized> Body for Box<B> {
+    #[inline]
+    fn content_len(&self) -> Option<usize> {
+        (**self).content_len()
+    }
+
+    #[inline]
+    fn write_to(&self, writer: &mut dyn Write) -> io::Result<()> {
+        (**self).write_to(writer)
+    }
 }
 
-impl Body for String {
-    fn content_len(&self) -> usize {
-        self.len()
+impl FromBody for Bytes {
+    #[inline]
+    fn from_bytes(bytes: Bytes) -> Result<Self> {
+        Ok(bytes)
     }
 }
 
-impl Body for &[u8] {
-    fn content_len(&self) -> usize {
-        self.len()
+impl FromBody for Vec<u8> {
+    #[inline]
+    fn from_bytes(bytes: Bytes) -> Result<Self> {
+        Ok(bytes.into())
     }
 }
 
-impl Body for Bytes {
-    fn content_len(&self) -> usize {
-        self.len()
+impl FromBody for String {
+    fn from_bytes(bytes: Bytes) -> Result<Self> {
+        String::from_utf8(bytes.into())
+            .map_err(|_| InvalidBody::with_context("body is not valid UTF-8").into())
+    }
+}
+
+/// A body that is read from a file while it is written.
+///
+/// The file is not kept open; it is opened every time the body is written.
+#[derive(Debug, Clone)]
+pub struct FileBody {
+    path: PathBuf,
+    len: usize,
+}
+
+impl FileBody {
+    /// Creates a body from the file at the specified path.
+    ///
+    /// Fails if the metadata of the file cannot be read.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let len = fs::metadata(&path)?.len() as usize;
+        Ok(FileBody { path, len })
+    }
+
+    /// Returns the path of the file.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Body for FileBody {
+    #[inline]
+    fn content_len(&self) -> Option<usize> {
+        Some(self.len)
+    }
+
+    /// Writes the bytes of the file up to the length that it had when the
+    /// body was created.
+    ///
+    /// Fails with `UnexpectedEof` if the file has shrunk since, since the
+    /// length has already been sent, so that the connection is closed rather
+    /// than left waiting for the missing bytes.
+    fn write_to(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let file = fs::File::open(&self.path)?;
+        let written = io::copy(&mut file.take(self.len as u64), writer)?;
+        if written < self.len as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "{} has {written} bytes instead of {}",
+                    self.path.display(),
+                    self.len
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_the_length_of_the_file_when_it_was_opened() {
+        let path = std::env::temp_dir().join(format!("tiny-http-body-{}", std::process::id()));
+        fs::write(&path, b"hello").unwrap();
+        let body = FileBody::open(&path).unwrap();
+        assert_eq!(body.content_len(), Some(5));
+
+        // Bytes that are appended later are not sent.
+        fs::write(&path, b"hello world").unwrap();
+        let mut written = Vec::new();
+        body.write_to(&mut written).unwrap();
+        assert_eq!(written, b"hello");
+
+        // Nor can the body be sent short.
+        fs::write(&path, b"hell").unwrap();
+        let err = body.write_to(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        fs::remove_file(&path).unwrap();
     }
 }
@@ -0,0 +1,397 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::InvalidDate;
+use crate::http::HeaderValue;
+
+const WEEKDAYS: [&[u8]; 7] = [b"Thu", b"Fri", b"Sat", b"Sun", b"Mon", b"Tue", b"Wed"];
+
+const LONG_WEEKDAYS: [&[u8]; 7] = [
+    b"Thursday",
+    b"Friday",
+    b"Saturday",
+    b"Sunday",
+    b"Monday",
+    b"Tuesday",
+    b"Wednesday",
+];
+
+const MONTHS: [&[u8]; 12] = [
+    b"Jan", b"Feb", b"Mar", b"Apr", b"May", b"Jun", b"Jul", b"Aug", b"Sep", b"Oct", b"Nov", b"Dec",
+];
+
+/// The last second of the year 9999, the last that IMF-fixdate can express.
+const MAX_SECS: u64 = 253_402_300_799;
+
+thread_local! {
+    /// The `Date` header of the current second, with the date it was formatted for.
+    static CACHED: RefCell<Option<(HttpDate, HeaderValue)>> = const { RefCell::new(None) };
+}
+
+/// A point in time with a resolution of one second, as found in headers such
+/// as `Date`, `Last-Modified`, `If-Modified-Since` or `Expires`.
+///
+/// It is parsed from any of the three formats that HTTP allows, i.e.
+/// IMF-fixdate (`Sun, 06 Nov 1994 08:49:37 GMT`), the obsolete RFC 850 format
+/// (`Sunday, 06-Nov-94 08:49:37 GMT`) and the format of C's `asctime`
+/// (`Sun Nov  6 08:49:37 1994`), and it is always formatted as IMF-fixdate.
+/// Dates before the Unix epoch are not supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HttpDate {
+    /// The seconds since the Unix epoch.
+    secs: u64,
+}
+
+impl HttpDate {
+    /// Returns the current date.
+    #[inline]
+    pub fn now() -> Self {
+        HttpDate::from(SystemTime::now())
+    }
+
+    /// Creates a date from the seconds since the Unix epoch, up to the end
+    /// of the year 9999.
+    fn from_secs(secs: u64) -> Self {
+        HttpDate {
+            secs: secs.min(MAX_SECS),
+        }
+    }
+
+    /// Creates a date from its fields, which are checked to be in range.
+    fn from_fields(
+        year: u64,
+        month: u64,
+        day: u64,
+        (hour, min, sec): (u64, u64, u64),
+    ) -> Option<Self> {
+        if !(1970..=9999).contains(&year) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        let days = days_from_civil(year, month, day);
+        Some(HttpDate {
+            secs: days * 86400 + hour * 3600 + min * 60 + sec,
+        })
+    }
+}
+
+impl From<SystemTime> for HttpDate {
+    /// Converts the time, truncated to the second. Times before the Unix
+    /// epoch become the epoch.
+    fn from(time: SystemTime) -> Self {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        HttpDate::from_secs(secs)
+    }
+}
+
+impl From<HttpDate> for SystemTime {
+    #[inline]
+    fn from(date: HttpDate) -> Self {
+        UNIX_EPOCH + Duration::from_secs(date.secs)
+    }
+}
+
+impl From<HttpDate> for HeaderValue {
+    #[inline]
+    fn from(date: HttpDate) -> Self {
+        HeaderValue::from_lossy(date.to_string())
+    }
+}
+
+impl<'a> TryFrom<&'a str> for HttpDate {
+    type Error = InvalidDate;
+
+    /// Parses a date in any of the formats of HTTP.
+    fn try_from(s: &'a str) -> Result<Self, Self::Error> {
+        let b = s.as_bytes();
+        parse_imf_fixdate(b)
+            .or_else(|| parse_rfc850(b))
+            .or_else(|| parse_asctime(b))
+            .ok_or_else(|| InvalidDate::with_context(s))
+    }
+}
+
+impl<'a> TryFrom<&'a HeaderValue> for HttpDate {
+    type Error = InvalidDate;
+
+    /// Parses the value of a header, such as `If-Modified-Since`.
+    fn try_from(value: &'a HeaderValue) -> Result<Self, Self::Error> {
+        match std::str::from_utf8(value.as_bytes()) {
+            Ok(s) => HttpDate::try_from(s.trim()),
+            Err(_) => Err(InvalidDate::with_context(value.to_string())),
+        }
+    }
+}
+
+impl FromStr for HttpDate {
+    type Err = InvalidDate;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        HttpDate::try_from(s)
+    }
+}
+
+impl fmt::Display for HttpDate {
+    /// Formats the date as an IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = self.secs / 86400;
+        let time = self.secs % 86400;
+        let (year, month, day) = civil_from_days(days);
+        write!(
+            f,
+            "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+            name(WEEKDAYS[(days % 7) as usize]),
+            day,
+            name(MONTHS[month as usize - 1]),
+            year,
+            time / 3600,
+            time % 3600 / 60,
+            time % 60
+        )
+    }
+}
+
+/// Returns the current date as the value of a `Date` header.
+///
+/// The value only changes once per second, so it is formatted once per
+/// second and thread.
+pub(crate) fn date_header() -> HeaderValue {
+    let now = HttpDate::now();
+    CACHED.with(|cached| {
+        let mut cached = cached.borrow_mut();
+        match &*cached {
+            Some((date, value)) if *date == now => value.clone(),
+            _ => {
+                let value = HeaderValue::from(now);
+                *cached = Some((now, value.clone()));
+                value
+            }
+        }
+    })
+}
+
+/// Parses an IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+fn parse_imf_fixdate(b: &[u8]) -> Option<HttpDate> {
+    if b.len() != 29 || !WEEKDAYS.contains(&&b[..3]) || &b[3..5] != b", " || &b[25..] != b" GMT" {
+        return None;
+    }
+    let day = digits(&b[5..7])?;
+    let month = month(&b[8..11])?;
+    let year = digits(&b[12..16])?;
+    if b[7] != b' ' || b[11] != b' ' || b[16] != b' ' {
+        return None;
+    }
+    HttpDate::from_fields(year, month, day, time(&b[17..25])?)
+}
+
+/// Parses a date of the obsolete RFC 850 format, e.g.
+/// `Sunday, 06-Nov-94 08:49:37 GMT`.
+///
+/// A two-digit year that would be more than 50 years in the future is taken
+/// to be in the past century, as RFC 9110 requires.
+fn parse_rfc850(b: &[u8]) -> Option<HttpDate> {
+    let comma = b.iter().position(|&c| c == b',')?;
+    let (weekday, rest) = (&b[..comma], &b[comma..]);
+    if !LONG_WEEKDAYS.contains(&weekday) || rest.len() != 24 || &rest[..2] != b", " {
+        return None;
+    }
+    let rest = &rest[2..];
+    let day = digits(&rest[..2])?;
+    let month = month(&rest[3..6])?;
+    let year = digits(&rest[7..9])?;
+    if rest[2] != b'-' || rest[6] != b'-' || rest[9] != b' ' || &rest[18..] != b" GMT" {
+        return None;
+    }
+    let (this_year, _, _) = civil_from_days(HttpDate::now().secs / 86400);
+    let mut year = this_year / 100 * 100 + year;
+    if year > this_year + 50 {
+        year -= 100;
+    }
+    HttpDate::from_fields(year, month, day, time(&rest[10..18])?)
+}
+
+/// Parses a date of the format of C's `asctime`, e.g. `Sun Nov  6 08:49:37 1994`.
+fn parse_asctime(b: &[u8]) -> Option<HttpDate> {
+    if b.len() != 24 || !WEEKDAYS.contains(&&b[..3]) {
+        return None;
+    }
+    let month = month(&b[4..7])?;
+    let day = match b[8] {
+        b' ' => digits(&b[9..10])?,
+        _ => digits(&b[8..10])?,
+    };
+    let year = digits(&b[20..24])?;
+    if b[3] != b' ' || b[7] != b' ' || b[10] != b' ' || b[19] != b' ' {
+        return None;
+    }
+    HttpDate::from_fields(year, month, day, time(&b[11..19])?)
+}
+
+/// Parses a time of the form `hh:mm:ss`. A leap second is taken as the
+/// second before it.
+fn time(b: &[u8]) -> Option<(u64, u64, u64)> {
+    if b[2] != b':' || b[5] != b':' {
+        return None;
+    }
+    let (hour, min, sec) = (digits(&b[..2])?, digits(&b[3..5])?, digits(&b[6..8])?);
+    if hour > 23 || min > 59 || sec > 60 {
+        return None;
+    }
+    Some((hour, min, sec.min(59)))
+}
+
+/// Returns the number of the month of the abbreviated name, from 1 to 12.
+fn month(b: &[u8]) -> Option<u64> {
+    MONTHS
+        .iter()
+        .position(|&name| name == b)
+        .map(|index| index as u64 + 1)
+}
+
+/// Parses a number of ASCII digits only.
+fn digits(b: &[u8]) -> Option<u64> {
+    b.iter().try_fold(0, |n, &c| {
+        c.is_ascii_digit().then(|| n * 10 + u64::from(c - b'0'))
+    })
+}
+
+/// Returns a name of the tables as a string.
+fn name(b: &[u8]) -> &str {
+    std::str::from_utf8(b).expect("The names are ASCII.")
+}
+
+/// Returns the number of days of the month of the year.
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Converts the year, month and day of the proleptic Gregorian calendar,
+/// from 1970 on, to the days since the Unix epoch.
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    // Shift the epoch to 0000-03-01, so that leap days end the year.
+    let year = year - u64::from(month <= 2);
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Converts the days since the Unix epoch to the year, month and day of the
+/// proleptic Gregorian calendar.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Option<u64> {
+        s.parse::<HttpDate>().ok().map(|date| date.secs)
+    }
+
+    #[test]
+    fn parses_the_three_formats() {
+        assert_eq!(parse("Sun, 06 Nov 1994 08:49:37 GMT"), Some(784_111_777));
+        assert_eq!(parse("Sunday, 06-Nov-94 08:49:37 GMT"), Some(784_111_777));
+        assert_eq!(parse("Sun Nov  6 08:49:37 1994"), Some(784_111_777));
+        assert_eq!(parse("Sun Nov 16 08:49:37 1994"), Some(784_975_777));
+
+        assert_eq!(parse("Sun, 06 Nov 1994 08:49:37 UTC"), None);
+        assert_eq!(parse("Sun, 6 Nov 1994 08:49:37 GMT"), None);
+        assert_eq!(parse("Sunday, 06-Nov-1994 08:49:37 GMT"), None);
+        assert_eq!(parse("Sun Nov 06 08:49:37 94"), None);
+        assert_eq!(parse("Sun, 06 Nov 1994 24:00:00 GMT"), None);
+        assert_eq!(parse("Sun, 06 Nov 1994 08:49:3a GMT"), None);
+    }
+
+    #[test]
+    fn formats_as_imf_fixdate() {
+        for s in [
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+            "Thu, 01 Jan 1970 00:00:00 GMT",
+            "Tue, 29 Feb 2000 12:00:00 GMT",
+        ] {
+            assert_eq!(s.parse::<HttpDate>().unwrap().to_string(), s);
+        }
+        let date: HttpDate = "Sun Nov  6 08:49:37 1994".parse().unwrap();
+        assert_eq!(date.to_string(), "Sun, 06 Nov 1994 08:49:37 GMT");
+        // A leap second is the second before it.
+        let date: HttpDate = "Sat, 31 Dec 2016 23:59:60 GMT".parse().unwrap();
+        assert_eq!(date.to_string(), "Sat, 31 Dec 2016 23:59:59 GMT");
+    }
+
+    #[test]
+    fn takes_two_digit_years_within_50_years_from_now() {
+        let (this_year, _, _) = civil_from_days(HttpDate::now().secs / 86400);
+        let future = this_year + 50;
+        let past = future + 1 - 100;
+        let date = |year: u64| format!("Monday, 01-Jan-{:02} 00:00:00 GMT", year % 100);
+        assert_eq!(
+            parse(&date(future)),
+            Some(days_from_civil(future, 1, 1) * 86400)
+        );
+        assert_eq!(
+            parse(&date(past)),
+            Some(days_from_civil(past, 1, 1) * 86400)
+        );
+    }
+
+    #[test]
+    fn accepts_february_29_in_leap_years_only() {
+        for year in [1972, 2000, 2024] {
+            let date = format!("Thu, 29 Feb {year} 00:00:00 GMT");
+            let secs = parse(&date).unwrap();
+            assert_eq!(civil_from_days(secs / 86400), (year, 2, 29));
+            assert_eq!(parse(&format!("Thu Feb 29 00:00:00 {year}")), Some(secs));
+        }
+        for year in [1970, 2023, 2100] {
+            assert_eq!(parse(&format!("Thu, 29 Feb {year} 00:00:00 GMT")), None);
+            assert_eq!(parse(&format!("Thu Feb 29 00:00:00 {year}")), None);
+        }
+        assert_eq!(parse("Thu, 30 Feb 2024 00:00:00 GMT"), None);
+        assert_eq!(parse("Thu, 31 Apr 2024 00:00:00 GMT"), None);
+        assert_eq!(parse("Thu, 00 Jan 2024 00:00:00 GMT"), None);
+    }
+
+    #[test]
+    fn supports_the_years_1970_to_9999() {
+        assert_eq!(parse("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        assert_eq!(parse("Wed, 31 Dec 1969 23:59:59 GMT"), None);
+        assert_eq!(parse("Wed Dec 31 23:59:59 1969"), None);
+        assert_eq!(parse("Fri, 31 Dec 9999 23:59:59 GMT"), Some(MAX_SECS));
+
+        let date = HttpDate::from(UNIX_EPOCH - Duration::from_secs(1));
+        assert_eq!(date.to_string(), "Thu, 01 Jan 1970 00:00:00 GMT");
+        let date = HttpDate::from(UNIX_EPOCH + Duration::from_secs(MAX_SECS + 86400));
+        assert_eq!(date.to_string(), "Fri, 31 Dec 9999 23:59:59 GMT");
+        assert_eq!(
+            SystemTime::from(date),
+            UNIX_EPOCH + Duration::from_secs(MAX_SECS)
+        );
+    }
+}
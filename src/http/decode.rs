@@ -0,0 +1,233 @@
+use std::collections::hash_map::Entry;
+use std::io::{self, BufRead, Read};
+
+use bytes::Bytes;
+
+use crate::error::{
+    Error, HeadersTooLarge, InvalidBody, InvalidHeaderName, InvalidHeaderValue, PayloadTooLarge,
+    Result,
+};
+use crate::http::{HeaderMap, HeaderName, HeaderValue};
+
+/// The default size of the largest head of a message, i.e. its start line and
+/// its header lines with their line endings.
+pub(crate) const MAX_HEAD_SIZE: usize = 64 << 10;
+
+/// The default size of the largest body of a request, which is buffered
+/// whole.
+pub(crate) const MAX_BODY_SIZE: usize = 16 << 20;
+
+/// Reads a line, without its line ending, and fails if the stream ends
+/// before the line does.
+///
+/// The line, with its line ending, is deducted from the budget, and fails
+/// with the error of `too_large` if it does not fit.
+pub(crate) fn read_line<R: BufRead>(
+    bufreader: &mut R,
+    line: &mut Vec<u8>,
+    budget: &mut usize,
+    too_large: fn() -> Error,
+) -> Result<()> {
+    line.clear();
+    let limit = budget.saturating_add(1) as u64;
+    if bufreader.take(limit).read_until(b'\n', line)? == 0 || line.last() != Some(&b'\n') {
+        return Err(match line.len() > *budget {
+            true => too_large(),
+            false => io::Error::from(io::ErrorKind::UnexpectedEof).into(),
+        });
+    }
+    *budget -= line.len();
+    line.pop();
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    Ok(())
+}
+
+/// Reads the header lines of a message, up to the empty line that ends them.
+///
+/// Headers whose names are not known fail with `InvalidHeaderName`, unless
+/// `skip_unknown` is set, e.g. for the headers that a server chose to send.
+/// A repeated header replaces the previous value, except `Transfer-Encoding`,
+/// whose codings are joined, and `Content-Length`, whose values must be equal
+/// or fail with `InvalidHeaderValue`.
+///
+/// The lines are deducted from the budget of the head, and fail with
+/// `HeadersTooLarge` once they exceed it.
+pub(crate) fn read_headers<R: BufRead>(
+    bufreader: &mut R,
+    skip_unknown: bool,
+    budget: &mut usize,
+) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    let mut line = Vec::new();
+    loop {
+        read_line(bufreader, &mut line, budget, head_too_large)?;
+        if line.is_empty() {
+            return Ok(headers);
+        }
+        let Some(colon) = line.iter().position(|&b| b == b':') else {
+            let line = String::from_utf8_lossy(&line).into_owned();
+            return Err(InvalidHeaderName::with_context(line).into());
+        };
+        let name = match HeaderName::from_bytes(&line[..colon]) {
+            Some(name) => name,
+            None if skip_unknown => continue,
+            None => {
+                let name = String::from_utf8_lossy(&line[..colon]).into_owned();
+                return Err(InvalidHeaderName::with_context(name).into());
+            }
+        };
+        let mut value = HeaderValue::from_bytes(line[colon + 1..].trim_ascii())?;
+        value.set_sensitive(name.is_sensitive());
+        match headers.inner.entry(name) {
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+            }
+            // A length that is given twice, differently, cannot frame the body
+            // safely, since another server may have taken the other one.
+            Entry::Occupied(entry) if *entry.key() == HeaderName::CONTENT_LENGTH => {
+                if entry.get().as_bytes() != value.as_bytes() {
+                    let context = format!("Content-Length: {}, {}", entry.get(), value);
+                    return Err(InvalidHeaderValue::with_context(context).into());
+                }
+            }
+            // The codings of repeated lines are applied in order.
+            Entry::Occupied(mut entry) if *entry.key() == HeaderName::TRANSFER_ENCODING => {
+                let mut codings = entry.get().as_bytes().to_vec();
+                codings.extend_from_slice(b", ");
+                codings.extend_from_slice(value.as_bytes());
+                entry.insert(HeaderValue::from_bytes(&codings)?);
+            }
+            Entry::Occupied(mut entry) => {
+                entry.insert(value);
+            }
+        }
+    }
+}
+
+/// Reads the body of a message, which the headers delimit with chunked
+/// transfer-coding or with `Content-Length`.
+///
+/// A message with neither header has an empty body, unless `until_close` is
+/// set, as for a response, whose body then lasts until the connection closes.
+///
+/// A body longer than `max_len` fails with `PayloadTooLarge`, before it is
+/// read if its length is known. The lines of a chunked body count towards
+/// its length.
+pub(crate) fn read_body<R: BufRead>(
+    bufreader: &mut R,
+    headers: &HeaderMap,
+    until_close: bool,
+    max_len: usize,
+) -> Result<Bytes> {
+    if let Some(codings) = headers.get(&HeaderName::TRANSFER_ENCODING) {
+        let last = codings.as_bytes().rsplit(|&b| b == b',').next();
+        return match last.map(<[u8]>::trim_ascii) {
+            Some(coding) if coding.eq_ignore_ascii_case(b"chunked") => {
+                read_chunked(bufreader, max_len)
+            }
+            _ if until_close => read_to_end(bufreader, max_len),
+            _ => Err(InvalidBody::with_context(codings.to_string()).into()),
+        };
+    }
+    match content_length(headers)? {
+        Some(len) if len > max_len as u64 => {
+            Err(PayloadTooLarge::with_context(len.to_string()).into())
+        }
+        Some(len) => read_len(bufreader, len),
+        None if until_close => read_to_end(bufreader, max_len),
+        None => Ok(Bytes::new()),
+    }
+}
+
+/// Returns the value of the `Content-Length` header, which only has digits,
+/// or fails with `InvalidHeaderValue`.
+pub(crate) fn content_length(headers: &HeaderMap) -> Result<Option<u64>> {
+    let Some(value) = headers.get(&HeaderName::CONTENT_LENGTH) else {
+        return Ok(None);
+    };
+    let digits = value.as_bytes();
+    std::str::from_utf8(digits)
+        .ok()
+        .filter(|len| !len.is_empty() && digits.iter().all(u8::is_ascii_digit))
+        .and_then(|len| len.parse().ok())
+        .map(Some)
+        .ok_or_else(|| InvalidHeaderValue::with_context(format!("Content-Length: {value}")).into())
+}
+
+/// Reads a body of the length, and fails if the stream ends before it.
+fn read_len<R: BufRead>(bufreader: &mut R, len: u64) -> Result<Bytes> {
+    let mut body = Vec::new();
+    bufreader.take(len).read_to_end(&mut body)?;
+    if (body.len() as u64) < len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(Bytes::from(body))
+}
+
+/// Reads a body that lasts until the stream ends.
+fn read_to_end<R: BufRead>(bufreader: &mut R, max_len: usize) -> Result<Bytes> {
+    let mut body = Vec::new();
+    let limit = max_len.saturating_add(1) as u64;
+    bufreader.take(limit).read_to_end(&mut body)?;
+    if body.len() > max_len {
+        return Err(PayloadTooLarge::new().into());
+    }
+    Ok(Bytes::from(body))
+}
+
+/// Reads a body sent with chunked transfer-coding, up to its last chunk and
+/// the trailer section after it, whose fields are discarded.
+fn read_chunked<R: BufRead>(bufreader: &mut R, max_len: usize) -> Result<Bytes> {
+    let mut body = Vec::new();
+    let mut line = Vec::new();
+    let mut budget = max_len;
+    loop {
+        read_line(bufreader, &mut line, &mut budget, body_too_large)?;
+        // Chunk extensions, after a semicolon, are ignored
+        let size = line.split(|&b| b == b';').next().unwrap_or_default();
+        let size = std::str::from_utf8(size.trim_ascii())
+            .ok()
+            .filter(|size| !size.is_empty())
+            .and_then(|size| u64::from_str_radix(size, 16).ok())
+            .ok_or_else(|| invalid_chunk(&line))?;
+        if size == 0 {
+            break;
+        }
+        if size > budget as u64 {
+            return Err(body_too_large());
+        }
+        budget -= size as usize;
+        let start = body.len();
+        bufreader.take(size).read_to_end(&mut body)?;
+        if ((body.len() - start) as u64) < size {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        read_line(bufreader, &mut line, &mut budget, body_too_large)?;
+        if !line.is_empty() {
+            return Err(invalid_chunk(&line));
+        }
+    }
+    loop {
+        read_line(bufreader, &mut line, &mut budget, body_too_large)?;
+        if line.is_empty() {
+            return Ok(Bytes::from(body));
+        }
+    }
+}
+
+/// Returns the error of a malformed line of a chunked body.
+fn invalid_chunk(line: &[u8]) -> Error {
+    InvalidBody::with_context(format!("chunk: {}", line.escape_ascii())).into()
+}
+
+/// Returns the error of a head that exceeds its budget.
+pub(crate) fn head_too_large() -> Error {
+    HeadersTooLarge::new().into()
+}
+
+/// Returns the error of a chunked body that exceeds its budget.
+fn body_too_large() -> Error {
+    PayloadTooLarge::new().into()
+}
@@ -0,0 +1,155 @@
+use std::io::{self, Write};
+
+use bytes::Bytes;
+
+use crate::http::{Body, HeaderMap, HeaderName, Request, Response, Version};
+
+/// HTTP messages that can be encoded into their wire format.
+pub trait Encode {
+    /// Appends the wire format of the message to the buffer.
+    ///
+    /// Fails only if the body of the message fails to be written.
+    fn encode(&self, buf: &mut Vec<u8>) -> io::Result<()>;
+
+    /// Returns the wire format of the message.
+    fn to_bytes(&self) -> io::Result<Bytes> {
+        let mut buf = Vec::new();
+        self.encode(&mut buf)?;
+        Ok(Bytes::from(buf))
+    }
+
+    /// Writes the message to the writer.
+    ///
+    /// The message is encoded in a buffer first, so that it is written
+    /// with a single write.
+    fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        let mut buf = Vec::new();
+        self.encode(&mut buf)?;
+        writer.write_all(&buf)?;
+        writer.flush()
+    }
+}
+
+impl<T: Body> Encode for Response<T> {
+    /// Encodes the status line, the headers and the body of the response,
+    /// or only the body for an HTTP/0.9 response.
+    fn encode(&self, buf: &mut Vec<u8>) -> io::Result<()> {
+        if *self.version() == Version::HTTP_09 {
+            return self.body().write_to(buf);
+        }
+        self.encode_head(buf)?;
+        encode_body(self.body(), self.headers(), buf)
+    }
+}
+
+impl<T: Body> Encode for Request<T> {
+    /// Encodes the request line, the headers and the body of the request,
+    /// or only the request line of a simple request of HTTP/0.9.
+    fn encode(&self, buf: &mut Vec<u8>) -> io::Result<()> {
+        if *self.version() == Version::HTTP_09 {
+            return write!(buf, "{} {}\r\n", self.method(), self.uri());
+        }
+        write!(
+            buf,
+            "{} {} {}\r\n",
+            self.method(),
+            self.uri(),
+            self.version()
+        )?;
+        encode_headers(self.headers(), buf);
+        encode_body(self.body(), self.headers(), buf)
+    }
+}
+
+impl<T> Response<T> {
+    /// Encodes the status line and the headers of the response, without its
+    /// body, e.g. for the response to a `HEAD` request. An HTTP/0.9 response
+    /// has no head, so nothing is encoded.
+    pub(crate) fn encode_head(&self, buf: &mut Vec<u8>) -> io::Result<()> {
+        if *self.version() == Version::HTTP_09 {
+            return Ok(());
+        }
+        let status = self.status();
+        write!(
+            buf,
+            "{} {} {}\r\n",
+            self.version(),
+            status.code(),
+            status.msg().unwrap_or_default()
+        )?;
+        encode_headers(self.headers(), buf);
+        Ok(())
+    }
+}
+
+/// Encodes the header lines of a message, and the empty line that ends them.
+fn encode_headers(headers: &HeaderMap, buf: &mut Vec<u8>) {
+    for (name, val) in headers {
+        buf.extend_from_slice(name.as_str().as_bytes());
+        buf.extend_from_slice(b": ");
+        buf.extend_from_slice(val.as_bytes());
+        buf.extend_from_slice(b"\r\n");
+    }
+    buf.extend_from_slice(b"\r\n");
+}
+
+/// Encodes the body of a message, in chunks if the headers say so.
+fn encode_body<T: Body>(body: &T, headers: &HeaderMap, buf: &mut Vec<u8>) -> io::Result<()> {
+    if is_chunked(headers) {
+        let mut chunked = ChunkedWriter::new(buf);
+        body.write_to(&mut chunked)?;
+        chunked.finish()
+    } else {
+        body.write_to(buf)
+    }
+}
+
+/// Checks whether the body of a message is sent with chunked transfer-coding.
+fn is_chunked(headers: &HeaderMap) -> bool {
+    headers
+        .get(&HeaderName::TRANSFER_ENCODING)
+        .map(|value| {
+            value
+                .as_bytes()
+                .split(|&b| b == b',')
+                .any(|coding| coding.trim_ascii().eq_ignore_ascii_case(b"chunked"))
+        })
+        .unwrap_or(false)
+}
+
+/// A writer that encodes the bytes written to it in chunks.
+///
+/// Every write becomes a chunk, and the last chunk is written by `finish`.
+pub struct ChunkedWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> ChunkedWriter<W> {
+    /// Creates a new chunked writer over the writer.
+    #[inline]
+    pub fn new(inner: W) -> Self {
+        ChunkedWriter { inner }
+    }
+
+    /// Writes the last, empty chunk, that terminates the body.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.inner.write_all(b"0\r\n\r\n")?;
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Write for ChunkedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        write!(self.inner, "{:x}\r\n", buf.len())?;
+        self.inner.write_all(buf)?;
+        self.inner.write_all(b"\r\n")?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
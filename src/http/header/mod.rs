@@ -1,13 +1,15 @@
 pub mod name;
+pub mod typed;
 pub mod value;
 
-use crate::error::{Error, Result};
+use crate::error::{Error, InvalidHeaderValue, Result};
 pub use name::HeaderName;
+pub use typed::TypedHeader;
 pub use value::HeaderValue;
 
 use std::collections::{hash_map, HashMap};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct HeaderMap {
     pub inner: HashMap<HeaderName, HeaderValue>,
 }
@@ -20,15 +22,32 @@ impl HeaderMap {
 
     /// Inserts a header in the `HeaderMap`, from name and value expressed
     /// in slices of bytes.
+    ///
+    /// The values of `Authorization`, `Proxy-Authorization`, `Cookie` and
+    /// `Set-Cookie` are marked as sensitive, so that `Debug` redacts them.
     pub fn insert<N, V>(&mut self, name: N, value: V) -> Result<Option<HeaderValue>>
     where
-        HeaderValue: From<V>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<Error>,
         HeaderName: TryFrom<N>,
         <HeaderName as TryFrom<N>>::Error: Into<Error>,
     {
         let name = TryFrom::try_from(name).map_err(Into::into)?;
-        let value = From::from(value);
-        Ok(self.inner.insert(name, value))
+        let value = TryFrom::try_from(value).map_err(Into::into)?;
+        Ok(self.insert_value(name, value))
+    }
+
+    /// Inserts a header value, which is marked as sensitive if the header
+    /// carries credentials, and returns the value that it replaces.
+    pub(crate) fn insert_value(
+        &mut self,
+        name: HeaderName,
+        mut value: HeaderValue,
+    ) -> Option<HeaderValue> {
+        if name.is_sensitive() {
+            value.set_sensitive(true);
+        }
+        self.inner.insert(name, value)
     }
 
     /// Returns the header-value in the `Request` given a header-name.
@@ -38,6 +57,68 @@ impl HeaderMap {
     pub fn get(&self, name: &HeaderName) -> Option<&HeaderValue> {
         self.inner.get(name)
     }
+
+    /// Returns the value of the typed header `H`, or `None` if the header is
+    /// missing.
+    ///
+    /// A malformed header fails with `InvalidHeaderValue`, which is answered
+    /// with `400 Bad Request`.
+    pub fn typed_get<H: TypedHeader>(&self) -> Result<Option<H>> {
+        self.get(&H::NAME)
+            .map(|value| {
+                H::decode(value).ok_or_else(|| {
+                    let context = format!("{}: {}", H::NAME, value.context());
+                    InvalidHeaderValue::with_context(context).into()
+                })
+            })
+            .transpose()
+    }
+
+    /// Inserts a typed header in the `HeaderMap`, and returns the value of
+    /// the header that it replaces.
+    pub fn typed_insert<H: TypedHeader>(&mut self, header: H) -> Option<HeaderValue> {
+        self.insert_value(H::NAME, header.encode())
+    }
+
+    /// Removes a header from the `HeaderMap`, and returns its value.
+    #[inline]
+    pub fn remove(&mut self, name: &HeaderName) -> Option<HeaderValue> {
+        self.inner.remove(name)
+    }
+
+    /// Sets the headers that delimit a body of the length, i.e. `Content-Length`,
+    /// or chunked `Transfer-Encoding` if the length is not known.
+    pub(crate) fn set_body_len(&mut self, len: Option<usize>) {
+        match len {
+            Some(len) => {
+                self.inner.remove(&HeaderName::TRANSFER_ENCODING);
+                self.inner
+                    .insert(HeaderName::CONTENT_LENGTH, HeaderValue::from(len));
+            }
+            None => {
+                self.inner.remove(&HeaderName::CONTENT_LENGTH);
+                self.inner.insert(
+                    HeaderName::TRANSFER_ENCODING,
+                    HeaderValue::from_static("chunked"),
+                );
+            }
+        }
+    }
+
+    /// Removes the headers that delimit a body, for a message that has none.
+    pub(crate) fn remove_body_len(&mut self) {
+        self.inner.remove(&HeaderName::CONTENT_LENGTH);
+        self.inner.remove(&HeaderName::TRANSFER_ENCODING);
+    }
+
+    /// Returns whether the comma-separated header contains the token, ignoring case.
+    pub(crate) fn has_token(&self, name: &HeaderName, token: &str) -> bool {
+        self.get(name).is_some_and(|value| {
+            String::from_utf8_lossy(value.as_bytes())
+                .split(',')
+                .any(|t| t.trim().eq_ignore_ascii_case(token))
+        })
+    }
 }
 
 impl<'a> IntoIterator for &'a HeaderMap {
@@ -66,3 +147,56 @@ impl IntoIterator for HeaderMap {
         self.inner.into_iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::typed::Authorization;
+    use super::*;
+    use crate::http::{Request, Response};
+    use bytes::Bytes;
+
+    #[test]
+    fn marks_credentials_as_sensitive() {
+        let mut headers = HeaderMap::new();
+        headers.insert(b"Authorization", b"Bearer secret").unwrap();
+        headers
+            .insert(b"proxy-authorization", b"Basic secret")
+            .unwrap();
+        headers.insert(b"Cookie", b"session=secret").unwrap();
+        headers.insert(b"Set-Cookie", b"session=secret").unwrap();
+        headers.insert(b"Accept", b"text/html").unwrap();
+        for (name, value) in &headers {
+            assert_eq!(value.is_sensitive(), *name != HeaderName::ACCEPT, "{name}");
+        }
+        let debug = format!("{headers:?}");
+        assert!(
+            !debug.contains("secret") && debug.contains("text/html"),
+            "{debug}"
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.typed_insert(Authorization::bearer("secret"));
+        assert!(headers
+            .get(&HeaderName::AUTHORIZATION)
+            .unwrap()
+            .is_sensitive());
+    }
+
+    #[test]
+    fn marks_parsed_credentials_as_sensitive() {
+        let request: Request<Bytes> = Request::from_reader(
+            &mut &b"GET / HTTP/1.1\r\nAuthorization: Bearer secret\r\nCookie: a=secret\r\n\r\n"[..],
+        )
+        .unwrap();
+        let debug = format!("{:?}", request.headers());
+        assert!(!debug.contains("secret"), "{debug}");
+
+        let response: Response<Bytes> = Response::from_reader(
+            &mut &b"HTTP/1.1 200 OK\r\nSet-Cookie: a=secret\r\nContent-Length: 0\r\n\r\n"[..],
+        )
+        .unwrap();
+        let value = response.headers().get(&HeaderName::SET_COOKIE).unwrap();
+        assert!(value.is_sensitive());
+        assert_eq!(value.to_str().unwrap(), "a=secret");
+    }
+}
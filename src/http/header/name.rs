@@ -17,6 +17,7 @@ macro_rules! standard_headers {
     ) => {
 
         #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+        #[allow(clippy::upper_case_acronyms)]
         enum StandardHeader {
             $($konst,)+
         }
@@ -39,11 +40,16 @@ macro_rules! standard_headers {
 
 
             /// Creates a `HeaderName` from a string reference.
+            ///
+            /// Header names are case-insensitive, so e.g. the lowercase names
+            /// of HTTP/2 match as well.
             pub fn from_bytes(bytes: &[u8]) -> Option<HeaderName> {
-                match bytes {
-                    $( $name_bytes => Some(HeaderName { inner: StandardHeader::$konst }),)+
-                    _ => None,
-                }
+                $(
+                    if bytes.eq_ignore_ascii_case($name_bytes) {
+                        return Some(HeaderName { inner: StandardHeader::$konst });
+                    }
+                )+
+                None
             }
         }
     }
@@ -53,7 +59,7 @@ impl<const N: usize> TryFrom<&[u8; N]> for HeaderName {
     type Error = InvalidHeaderName;
 
     fn try_from(bytes: &[u8; N]) -> Result<Self, Self::Error> {
-        HeaderName::from_bytes(bytes).ok_or(InvalidHeaderName)
+        TryFrom::try_from(&bytes[..])
     }
 }
 
@@ -61,7 +67,8 @@ impl TryFrom<&[u8]> for HeaderName {
     type Error = InvalidHeaderName;
 
     fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
-        HeaderName::from_bytes(bytes).ok_or(InvalidHeaderName)
+        HeaderName::from_bytes(bytes)
+            .ok_or_else(|| InvalidHeaderName::with_context(String::from_utf8_lossy(bytes)))
     }
 }
 
@@ -73,6 +80,21 @@ impl TryFrom<&str> for HeaderName {
     }
 }
 
+impl HeaderName {
+    /// Checks whether the values of the header are credentials, which are
+    /// marked as sensitive when they are inserted in a `HeaderMap`.
+    #[inline]
+    pub fn is_sensitive(&self) -> bool {
+        matches!(
+            *self,
+            HeaderName::AUTHORIZATION
+                | HeaderName::PROXY_AUTHORIZATION
+                | HeaderName::COOKIE
+                | HeaderName::SET_COOKIE
+        )
+    }
+}
+
 impl fmt::Display for HeaderName {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_str(self.as_str())
@@ -86,14 +108,16 @@ standard_headers! {
     (AcceptDatetime, ACCEPT_DATETIME, b"Accept-Datetime");
     (AcceptEncoding, ACCEPT_ENCODING, b"Accept-Encoding");
     (AcceptLanguage, ACCEPT_LANGUAGE, b"Accept-Language");
+    (AcceptRanges, ACCEPT_RANGES, b"Accept-Ranges");
     (AcceptCtrlSeqMethod, ACCEPT_CTRL_SEQ_METHOD, b"Accept-Control-Sequence-Method");
     (AcceptCtrlSeqHeaders, ACCEPT_CTRL_SEQ_HEADERS, b"Accept-Control-Sequence-Headers");
-    (Authorization, AUTHORIZATION, b"Authorezation");
+    (Authorization, AUTHORIZATION, b"Authorization");
     (CacheControl, CACHE_CONTROL, b"Cache-Control");
     (Connection, CONNECTION, b"Connection");
     (ContentEncoding, CONTENT_ENCODING, b"Content-Encoding");
     (ContentLength, CONTENT_LENGTH, b"Content-Length");
     (ContentMD5, CONTENT_MD5, b"Content-MD5");
+    (ContentRange, CONTENT_RANGE, b"Content-Range");
     (ContentType, CONTENT_TYPE, b"Content-Type");
     (Cookie, COOKIE, b"Cookie");
     (CfVisitor, CF_VISITOR, b"Cf-Visitor");
@@ -101,7 +125,9 @@ standard_headers! {
     (CfIpcountry, CF_IPCOUNTRY, b"Cf-Ipcountry");
     (CfRay, CF_RAY, b"Cf-Ray");
     (Date, DATE, b"Date");
+    (ETag, ETAG, b"ETag");
     (Expect, EXPECT, b"Expect");
+    (Expires, EXPIRES, b"Expires");
     (Forwarded, FORWARDED, b"Forwarded");
     (From, FROM, b"From");
     (Host, HOST, b"Host");
@@ -111,6 +137,9 @@ standard_headers! {
     (IfNoneMatch, IF_NONE_MATCH, b"If-None-Match");
     (IfRange, IF_RANGE, b"If-Range");
     (IfUnmodifiedSince, IF_UNMODIFIED_SINCE, b"If-Unmodified-Since");
+    (LastEventId, LAST_EVENT_ID, b"Last-Event-ID");
+    (LastModified, LAST_MODIFIED, b"Last-Modified");
+    (Location, LOCATION, b"Location");
     (MaxForwards, MAX_FORWARDS, b"Max-Forwards");
     (Origin, ORIGIN, b"Origin");
     (Pragma, PRAGMA, b"Pragma");
@@ -119,6 +148,9 @@ standard_headers! {
     (Priority, PRIORITY, b"Priority");
     (Range, RANGE, b"Range");
     (Referer, REFERER, b"Referer");
+    (RetryAfter, RETRY_AFTER, b"Retry-After");
+    (Server, SERVER, b"Server");
+    (SetCookie, SET_COOKIE, b"Set-Cookie");
     (TE, TE, b"TE");
     (Trailer, TRAILER, b"Trailer");
     (TransferEncoding, TRANSFER_ENCODING, b"Transfer-Encoding");
@@ -133,6 +165,11 @@ standard_headers! {
     (SecFetchSite, SEC_FETCH_SITE, b"Sec-Fetch-Site");
     (SecFetchUser, SEC_FETCH_USER, b"Sec-Fetch-User");
     (SecGpc, SEC_GPC, b"Sec-GPC");
+    (SecWebSocketAccept, SEC_WEBSOCKET_ACCEPT, b"Sec-WebSocket-Accept");
+    (SecWebSocketExtensions, SEC_WEBSOCKET_EXTENSIONS, b"Sec-WebSocket-Extensions");
+    (SecWebSocketKey, SEC_WEBSOCKET_KEY, b"Sec-WebSocket-Key");
+    (SecWebSocketProtocol, SEC_WEBSOCKET_PROTOCOL, b"Sec-WebSocket-Protocol");
+    (SecWebSocketVersion, SEC_WEBSOCKET_VERSION, b"Sec-WebSocket-Version");
     (UpgradeInsecureRequests, UPGRADE_INSECURE_REQUESTS, b"Upgrade-Insecure-Requests");
     (XForwardedProto, X_FORWARDED_PROTO, b"X-Forwarded-Proto");
     (XHTTPS, X_HTTPS, b"X-HTTPS");
@@ -0,0 +1,96 @@
+use std::fmt;
+
+use super::{is_token, text, TypedHeader};
+use crate::http::{base64, HeaderName, HeaderValue};
+
+/// The `Authorization` header, i.e. the credentials of the client for an
+/// authentication scheme, such as `Basic` or `Bearer`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Authorization {
+    scheme: String,
+    credentials: String,
+}
+
+impl Authorization {
+    /// Creates the credentials of the `Basic` scheme from a user-id and a password.
+    pub fn basic(user_id: &str, password: &str) -> Self {
+        Authorization {
+            scheme: "Basic".to_owned(),
+            credentials: base64::encode(format!("{user_id}:{password}").as_bytes()),
+        }
+    }
+
+    /// Creates the credentials of the `Bearer` scheme from a token.
+    pub fn bearer(token: &str) -> Self {
+        Authorization {
+            scheme: "Bearer".to_owned(),
+            credentials: token.to_owned(),
+        }
+    }
+
+    /// Returns the authentication scheme, e.g. `Basic`.
+    #[inline]
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// Returns the credentials as they are sent, after the scheme.
+    #[inline]
+    pub fn credentials(&self) -> &str {
+        &self.credentials
+    }
+
+    /// Returns the user-id and the password of the `Basic` scheme, or `None`
+    /// for other schemes and malformed credentials.
+    pub fn as_basic(&self) -> Option<(String, String)> {
+        if !self.scheme.eq_ignore_ascii_case("Basic") {
+            return None;
+        }
+        let decoded = String::from_utf8(base64::decode(self.credentials.as_bytes())?).ok()?;
+        let (user_id, password) = decoded.split_once(':')?;
+        Some((user_id.to_owned(), password.to_owned()))
+    }
+
+    /// Returns the token of the `Bearer` scheme, or `None` for other schemes.
+    pub fn as_bearer(&self) -> Option<&str> {
+        self.scheme
+            .eq_ignore_ascii_case("Bearer")
+            .then_some(self.credentials.as_str())
+    }
+}
+
+impl TypedHeader for Authorization {
+    const NAME: HeaderName = HeaderName::AUTHORIZATION;
+
+    fn decode(value: &HeaderValue) -> Option<Self> {
+        let value = text(value)?;
+        let (scheme, credentials) = value.split_once(' ').unwrap_or((value, ""));
+        if !is_token(scheme) {
+            return None;
+        }
+        Some(Authorization {
+            scheme: scheme.to_owned(),
+            credentials: credentials.trim_start_matches(' ').to_owned(),
+        })
+    }
+
+    fn encode(&self) -> HeaderValue {
+        let value = match self.credentials.is_empty() {
+            true => self.scheme.clone(),
+            false => format!("{} {}", self.scheme, self.credentials),
+        };
+        // The credentials are secret, so they are redacted from logs.
+        let mut value = HeaderValue::from_lossy(value);
+        value.set_sensitive(true);
+        value
+    }
+}
+
+impl fmt::Debug for Authorization {
+    /// Hides the credentials, which are secret.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Authorization")
+            .field("scheme", &self.scheme)
+            .finish_non_exhaustive()
+    }
+}
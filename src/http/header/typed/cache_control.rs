@@ -0,0 +1,172 @@
+use std::fmt;
+use std::time::Duration;
+
+use super::{is_token, parse_param_value, quote, split_list, text, TypedHeader};
+use crate::http::{HeaderName, HeaderValue};
+
+/// The `Cache-Control` header, i.e. the directives of caches for requests
+/// and responses, such as `no-cache` or `max-age=3600`.
+///
+/// The names of the directives are case-insensitive, so they are kept in
+/// lowercase. Directives that are not known are kept as they are.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheControl {
+    directives: Vec<(String, Option<String>)>,
+}
+
+impl CacheControl {
+    /// Creates a `Cache-Control` header without directives.
+    #[inline]
+    pub fn new() -> Self {
+        CacheControl::default()
+    }
+
+    /// Adds a directive without a value, such as `no-store`.
+    pub fn with(self, name: &str) -> Self {
+        self.with_directive(name, None)
+    }
+
+    /// Adds a directive with a value, such as `max-age=60`.
+    pub fn with_value(self, name: &str, value: &str) -> Self {
+        self.with_directive(name, Some(value.to_owned()))
+    }
+
+    /// Adds the `max-age` directive.
+    pub fn with_max_age(self, max_age: Duration) -> Self {
+        self.with_value("max-age", &max_age.as_secs().to_string())
+    }
+
+    /// Adds a directive, which replaces any directive of the same name.
+    fn with_directive(mut self, name: &str, value: Option<String>) -> Self {
+        let name = name.to_ascii_lowercase();
+        self.directives.retain(|(n, _)| *n != name);
+        self.directives.push((name, value));
+        self
+    }
+
+    /// Checks whether the header has the directive, whose name is case-insensitive.
+    pub fn has(&self, name: &str) -> bool {
+        self.directive(name).is_some()
+    }
+
+    /// Returns the value of the directive, which is `None` if the header has
+    /// not the directive or if it has no value.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.directive(name).flatten()
+    }
+
+    /// Returns the directives, with their values, in the order of the header.
+    pub fn directives(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.directives
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_deref()))
+    }
+
+    /// Returns the `max-age` directive, or `None` if it is missing or malformed.
+    pub fn max_age(&self) -> Option<Duration> {
+        self.seconds("max-age")
+    }
+
+    /// Returns the `s-maxage` directive, or `None` if it is missing or malformed.
+    pub fn s_maxage(&self) -> Option<Duration> {
+        self.seconds("s-maxage")
+    }
+
+    /// Checks whether the header has the `no-cache` directive.
+    #[inline]
+    pub fn no_cache(&self) -> bool {
+        self.has("no-cache")
+    }
+
+    /// Checks whether the header has the `no-store` directive.
+    #[inline]
+    pub fn no_store(&self) -> bool {
+        self.has("no-store")
+    }
+
+    /// Checks whether the header has the `no-transform` directive.
+    #[inline]
+    pub fn no_transform(&self) -> bool {
+        self.has("no-transform")
+    }
+
+    /// Checks whether the header has the `must-revalidate` directive.
+    #[inline]
+    pub fn must_revalidate(&self) -> bool {
+        self.has("must-revalidate")
+    }
+
+    /// Checks whether the header has the `public` directive.
+    #[inline]
+    pub fn public(&self) -> bool {
+        self.has("public")
+    }
+
+    /// Checks whether the header has the `private` directive.
+    #[inline]
+    pub fn private(&self) -> bool {
+        self.has("private")
+    }
+
+    /// Checks whether the header has the `immutable` directive.
+    #[inline]
+    pub fn immutable(&self) -> bool {
+        self.has("immutable")
+    }
+
+    /// Returns the directive, whose value may be missing.
+    fn directive(&self, name: &str) -> Option<Option<&str>> {
+        self.directives
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_deref())
+    }
+
+    /// Returns the value of the directive as a number of seconds.
+    fn seconds(&self, name: &str) -> Option<Duration> {
+        let value = self.get(name)?;
+        if !value.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        // Ages too large to be represented are the largest one, as RFC 9111 requires.
+        let secs = value.parse().unwrap_or(u64::MAX);
+        Some(Duration::from_secs(secs))
+    }
+}
+
+impl TypedHeader for CacheControl {
+    const NAME: HeaderName = HeaderName::CACHE_CONTROL;
+
+    fn decode(value: &HeaderValue) -> Option<Self> {
+        let directives = split_list(text(value)?, b',')
+            .into_iter()
+            .map(|directive| {
+                let (name, value) = match directive.split_once('=') {
+                    Some((name, value)) => (name.trim(), Some(parse_param_value(value.trim())?)),
+                    None => (directive, None),
+                };
+                is_token(name).then(|| (name.to_ascii_lowercase(), value))
+            })
+            .collect::<Option<_>>()?;
+        Some(CacheControl { directives })
+    }
+
+    fn encode(&self) -> HeaderValue {
+        HeaderValue::from_lossy(self.to_string())
+    }
+}
+
+impl fmt::Display for CacheControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, value)) in self.directives.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(name)?;
+            if let Some(value) = value {
+                write!(f, "={}", quote(value))?;
+            }
+        }
+        Ok(())
+    }
+}
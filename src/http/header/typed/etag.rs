@@ -0,0 +1,93 @@
+use std::fmt;
+
+use super::{text, TypedHeader};
+use crate::http::{HeaderName, HeaderValue};
+
+/// The `ETag` header, i.e. an entity tag that identifies a representation
+/// of a resource, which may be weak.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ETag {
+    weak: bool,
+    tag: String,
+}
+
+impl ETag {
+    /// Creates a strong entity tag, or returns `None` if the tag has characters
+    /// that an entity tag cannot have, such as `"` or whitespace.
+    pub fn strong(tag: &str) -> Option<Self> {
+        is_valid(tag).then(|| ETag {
+            weak: false,
+            tag: tag.to_owned(),
+        })
+    }
+
+    /// Creates a weak entity tag, or returns `None` if the tag has characters
+    /// that an entity tag cannot have, such as `"` or whitespace.
+    pub fn weak(tag: &str) -> Option<Self> {
+        is_valid(tag).then(|| ETag {
+            weak: true,
+            tag: tag.to_owned(),
+        })
+    }
+
+    /// Returns the tag, without the quotes and the weakness indicator.
+    #[inline]
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Checks whether the entity tag is weak.
+    #[inline]
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    /// Compares the entity tags with the strong comparison, under which both
+    /// must be strong and have the same tag.
+    pub fn strong_eq(&self, other: &ETag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// Compares the entity tags with the weak comparison, under which they
+    /// only need to have the same tag.
+    pub fn weak_eq(&self, other: &ETag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl TypedHeader for ETag {
+    const NAME: HeaderName = HeaderName::ETAG;
+
+    fn decode(value: &HeaderValue) -> Option<Self> {
+        let value = text(value)?;
+        let (weak, quoted) = match value.strip_prefix("W/") {
+            Some(quoted) => (true, quoted),
+            None => (false, value),
+        };
+        let tag = quoted.strip_prefix('"')?.strip_suffix('"')?;
+        is_valid(tag).then(|| ETag {
+            weak,
+            tag: tag.to_owned(),
+        })
+    }
+
+    fn encode(&self) -> HeaderValue {
+        HeaderValue::from_lossy(self.to_string())
+    }
+}
+
+impl fmt::Display for ETag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            f.write_str("W/")?;
+        }
+        write!(f, "\"{}\"", self.tag)
+    }
+}
+
+/// Checks whether the tag only has the characters of an entity tag, i.e.
+/// visible characters other than `"`, and non-ASCII ones.
+fn is_valid(tag: &str) -> bool {
+    tag.bytes()
+        .all(|b| b == 0x21 || (0x23..=0x7E).contains(&b) || b >= 0x80)
+}
@@ -0,0 +1,338 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::{is_token, parse_params, quote, split_list, text, TypedHeader};
+use crate::error::InvalidHeaderValue;
+use crate::http::{HeaderName, HeaderValue};
+
+/// The `Content-Type` header, i.e. the media type of the body, with its
+/// parameters such as `charset`.
+///
+/// The type, the subtype and the names of the parameters are case-insensitive,
+/// so they are kept in lowercase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentType {
+    mime: String,
+    params: Vec<(String, String)>,
+}
+
+impl ContentType {
+    /// `application/json`
+    pub fn json() -> Self {
+        ContentType::essence("application/json")
+    }
+
+    /// `text/html; charset=utf-8`
+    pub fn html() -> Self {
+        ContentType::essence("text/html").with_param("charset", "utf-8")
+    }
+
+    /// `text/plain; charset=utf-8`
+    pub fn text() -> Self {
+        ContentType::essence("text/plain").with_param("charset", "utf-8")
+    }
+
+    /// `application/x-www-form-urlencoded`
+    pub fn form_url_encoded() -> Self {
+        ContentType::essence("application/x-www-form-urlencoded")
+    }
+
+    /// `application/octet-stream`
+    pub fn octet_stream() -> Self {
+        ContentType::essence("application/octet-stream")
+    }
+
+    /// Creates a media type without parameters from a valid `type/subtype`.
+    fn essence(mime: &str) -> Self {
+        ContentType {
+            mime: mime.to_owned(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Adds a parameter, which replaces any parameter of the same name.
+    ///
+    /// The value is quoted when the header is encoded, if it needs to be.
+    pub fn with_param(mut self, name: &str, value: &str) -> Self {
+        let name = name.to_ascii_lowercase();
+        self.params.retain(|(n, _)| *n != name);
+        self.params.push((name, value.to_owned()));
+        self
+    }
+
+    /// Returns the media type without its parameters, e.g. `text/html`.
+    #[inline]
+    pub fn mime_type(&self) -> &str {
+        &self.mime
+    }
+
+    /// Returns the type of the media type, e.g. `text` for `text/html`.
+    pub fn main_type(&self) -> &str {
+        self.mime.split_once('/').map_or(&self.mime, |(t, _)| t)
+    }
+
+    /// Returns the subtype of the media type, e.g. `html` for `text/html`.
+    pub fn subtype(&self) -> &str {
+        self.mime.split_once('/').map_or("", |(_, s)| s)
+    }
+
+    /// Returns the value of the parameter, whose name is case-insensitive.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        find_param(&self.params, name)
+    }
+
+    /// Returns the `charset` parameter.
+    #[inline]
+    pub fn charset(&self) -> Option<&str> {
+        self.param("charset")
+    }
+}
+
+impl TypedHeader for ContentType {
+    const NAME: HeaderName = HeaderName::CONTENT_TYPE;
+
+    fn decode(value: &HeaderValue) -> Option<Self> {
+        let elements = split_list(text(value)?, b';');
+        let (&mime, params) = elements.split_first()?;
+        let (main_type, subtype) = mime.split_once('/')?;
+        if !is_token(main_type) || !is_token(subtype) {
+            return None;
+        }
+        Some(ContentType {
+            mime: mime.to_ascii_lowercase(),
+            params: parse_params(params.iter().copied())?,
+        })
+    }
+
+    fn encode(&self) -> HeaderValue {
+        HeaderValue::from_lossy(self.to_string())
+    }
+}
+
+impl FromStr for ContentType {
+    type Err = InvalidHeaderValue;
+
+    /// Parses a media type with its parameters, e.g. `text/csv; charset=utf-8`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        HeaderValue::try_from(s)
+            .ok()
+            .and_then(|value| ContentType::decode(&value))
+            .ok_or_else(|| InvalidHeaderValue::with_context(format!("Content-Type: {s}")))
+    }
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.mime)?;
+        write_params(f, &self.params)
+    }
+}
+
+/// The `Accept` header, i.e. the media ranges that the client accepts, with
+/// their relative quality.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Accept {
+    ranges: Vec<MediaRange>,
+}
+
+/// A media range of an `Accept` header, such as `text/html`, `text/*` or
+/// `*/*`, with its parameters and its quality.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaRange {
+    mime: String,
+    params: Vec<(String, String)>,
+    /// The quality in thousandths, from 0 to 1000.
+    quality: u16,
+}
+
+impl Accept {
+    /// Creates an `Accept` header without media ranges.
+    #[inline]
+    pub fn new() -> Self {
+        Accept::default()
+    }
+
+    /// Adds a media range, such as `text/html` or `image/*`, with a quality
+    /// between 0 and 1.
+    pub fn with_range(mut self, range: &str, quality: f32) -> Self {
+        self.ranges.push(MediaRange {
+            mime: range.to_ascii_lowercase(),
+            params: Vec::new(),
+            quality: (quality.clamp(0.0, 1.0) * 1000.0).round() as u16,
+        });
+        self
+    }
+
+    /// Returns the media ranges, in the order of the header.
+    #[inline]
+    pub fn ranges(&self) -> &[MediaRange] {
+        &self.ranges
+    }
+
+    /// Returns the quality with which the client accepts the media type,
+    /// e.g. `text/html` or `text/html; level=1`.
+    ///
+    /// The most specific media range that matches the media type gives its
+    /// quality, and a media type that no range matches has a quality of 0.
+    pub fn quality(&self, mime: &str) -> f32 {
+        let content = HeaderValue::try_from(mime)
+            .ok()
+            .and_then(|value| ContentType::decode(&value));
+        let Some(content) = content else {
+            return 0.0;
+        };
+        self.ranges
+            .iter()
+            .filter(|range| range.matches(&content))
+            .max_by_key(|range| range.specificity())
+            .map_or(0.0, MediaRange::quality)
+    }
+
+    /// Returns the media type that the client prefers among the available
+    /// ones, or `None` if it accepts none of them.
+    ///
+    /// Media types of the same quality are preferred in the order in which
+    /// they are given.
+    pub fn negotiate<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        let mut best = None;
+        for &mime in available {
+            let quality = self.quality(mime);
+            if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+                best = Some((mime, quality));
+            }
+        }
+        best.map(|(mime, _)| mime)
+    }
+}
+
+impl MediaRange {
+    /// Returns the media range without its parameters, e.g. `text/*`.
+    #[inline]
+    pub fn mime_type(&self) -> &str {
+        &self.mime
+    }
+
+    /// Returns the value of the parameter, whose name is case-insensitive.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        find_param(&self.params, name)
+    }
+
+    /// Returns the quality of the media range, between 0 and 1.
+    #[inline]
+    pub fn quality(&self) -> f32 {
+        f32::from(self.quality) / 1000.0
+    }
+
+    /// Checks whether the media range matches the media type and its parameters.
+    fn matches(&self, content: &ContentType) -> bool {
+        let mime_matches = match self.mime.split_once('/') {
+            Some(("*", "*")) => true,
+            Some((main_type, "*")) => main_type == content.main_type(),
+            _ => self.mime == content.mime,
+        };
+        mime_matches
+            && self
+                .params
+                .iter()
+                .all(|(name, value)| content.param(name) == Some(value))
+    }
+
+    /// Returns how specific the media range is, so that more specific ranges
+    /// take precedence.
+    fn specificity(&self) -> (bool, bool, usize) {
+        let (main_type, subtype) = self.mime.split_once('/').unwrap_or(("*", "*"));
+        (main_type != "*", subtype != "*", self.params.len())
+    }
+}
+
+impl TypedHeader for Accept {
+    const NAME: HeaderName = HeaderName::ACCEPT;
+
+    /// Parses the media ranges, ignoring the extension parameters that
+    /// follow the quality.
+    fn decode(value: &HeaderValue) -> Option<Self> {
+        let ranges = split_list(text(value)?, b',')
+            .into_iter()
+            .map(|element| {
+                let elements = split_list(element, b';');
+                let (&mime, params) = elements.split_first()?;
+                match mime.split_once('/')? {
+                    ("*", "*") => {}
+                    ("*", _) => return None,
+                    (main_type, subtype) if is_token(main_type) && is_token(subtype) => {}
+                    _ => return None,
+                }
+                let mut params = parse_params(params.iter().copied())?;
+                let quality = match params.iter().position(|(name, _)| name == "q") {
+                    Some(index) => {
+                        let quality = parse_quality(&params[index].1)?;
+                        params.truncate(index);
+                        quality
+                    }
+                    None => 1000,
+                };
+                Some(MediaRange {
+                    mime: mime.to_ascii_lowercase(),
+                    params,
+                    quality,
+                })
+            })
+            .collect::<Option<_>>()?;
+        Some(Accept { ranges })
+    }
+
+    fn encode(&self) -> HeaderValue {
+        HeaderValue::from_lossy(self.to_string())
+    }
+}
+
+impl fmt::Display for Accept {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, range) in self.ranges.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(&range.mime)?;
+            write_params(f, &range.params)?;
+            match range.quality {
+                1000 => {}
+                quality => {
+                    let decimals = format!("{quality:03}");
+                    write!(f, "; q=0.{}", decimals.trim_end_matches('0'))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a quality value, i.e. a number from 0 to 1 with up to three
+/// decimals, into thousandths.
+fn parse_quality(s: &str) -> Option<u16> {
+    let (int, decimals) = s.split_once('.').unwrap_or((s, ""));
+    if decimals.len() > 3 || !decimals.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let decimals = format!("{decimals:0<3}").parse::<u16>().ok()?;
+    match int {
+        "0" => Some(decimals),
+        "1" if decimals == 0 => Some(1000),
+        _ => None,
+    }
+}
+
+/// Returns the value of the parameter, whose name is case-insensitive.
+fn find_param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Writes the parameters of a media type or range.
+fn write_params(f: &mut fmt::Formatter<'_>, params: &[(String, String)]) -> fmt::Result {
+    for (name, value) in params {
+        write!(f, "; {}={}", name, quote(value))?;
+    }
+    Ok(())
+}
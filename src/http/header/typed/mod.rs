@@ -0,0 +1,232 @@
+pub mod authorization;
+pub mod cache_control;
+pub mod etag;
+pub mod media;
+pub mod range;
+
+use std::borrow::Cow;
+use std::fmt::Write;
+
+use crate::http::{HeaderName, HeaderValue};
+
+pub use authorization::Authorization;
+pub use cache_control::CacheControl;
+pub use etag::ETag;
+pub use media::{Accept, ContentType, MediaRange};
+pub use range::{ByteRange, Range};
+
+/// A header whose value is parsed into, and encoded from, a type.
+///
+/// Typed headers are read with `HeaderMap::typed_get` and set with
+/// `HeaderMap::typed_insert`, or with `with_typed_header` on the builders of
+/// requests and responses.
+pub trait TypedHeader: Sized {
+    /// The name of the header.
+    const NAME: HeaderName;
+
+    /// Parses the value of the header, or returns `None` if it is malformed.
+    fn decode(value: &HeaderValue) -> Option<Self>;
+
+    /// Encodes the header into its value.
+    fn encode(&self) -> HeaderValue;
+}
+
+/// The `Content-Length` header, i.e. the length of the body in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContentLength(pub u64);
+
+impl TypedHeader for ContentLength {
+    const NAME: HeaderName = HeaderName::CONTENT_LENGTH;
+
+    fn decode(value: &HeaderValue) -> Option<Self> {
+        let value = text(value)?;
+        if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        value.parse().ok().map(ContentLength)
+    }
+
+    fn encode(&self) -> HeaderValue {
+        HeaderValue::from(self.0)
+    }
+}
+
+/// The `Host` header, i.e. the host and the optional port of the target of
+/// the request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Host {
+    hostname: String,
+    port: Option<u16>,
+}
+
+impl Host {
+    /// Creates a `Host` header of the host name, or IP address, and the port.
+    ///
+    /// IPv6 addresses are given without brackets.
+    pub fn new(hostname: &str, port: Option<u16>) -> Self {
+        Host {
+            hostname: hostname.to_owned(),
+            port,
+        }
+    }
+
+    /// Returns the host name or IP address, without the brackets of an IPv6 address.
+    #[inline]
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    /// Returns the port, if the header has one.
+    #[inline]
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+}
+
+impl TypedHeader for Host {
+    const NAME: HeaderName = HeaderName::HOST;
+
+    fn decode(value: &HeaderValue) -> Option<Self> {
+        let value = text(value)?;
+        let (hostname, port) = match value.strip_prefix('[') {
+            Some(rest) => {
+                let (hostname, rest) = rest.split_once(']')?;
+                if !hostname
+                    .bytes()
+                    .all(|b| b.is_ascii_hexdigit() || b == b':' || b == b'.')
+                {
+                    return None;
+                }
+                match rest {
+                    "" => (hostname, None),
+                    rest => (hostname, Some(rest.strip_prefix(':')?)),
+                }
+            }
+            None => match value.split_once(':') {
+                Some((hostname, port)) => (hostname, Some(port)),
+                None => (value, None),
+            },
+        };
+        let valid = |b: u8| b.is_ascii_alphanumeric() || b"-._~%!$&'()*+,;=".contains(&b);
+        if !value.starts_with('[') && !hostname.bytes().all(valid) {
+            return None;
+        }
+        // The port may be empty, as if there were none.
+        let port = match port {
+            Some("") | None => None,
+            Some(port) if port.bytes().all(|b| b.is_ascii_digit()) => Some(port.parse().ok()?),
+            Some(_) => return None,
+        };
+        Some(Host::new(hostname, port))
+    }
+
+    fn encode(&self) -> HeaderValue {
+        let mut value = match self.hostname.contains(':') {
+            true => format!("[{}]", self.hostname),
+            false => self.hostname.clone(),
+        };
+        if let Some(port) = self.port {
+            let _ = write!(value, ":{port}");
+        }
+        HeaderValue::from_lossy(value)
+    }
+}
+
+/// Returns the value of a header as text without surrounding whitespace,
+/// or `None` if it is not UTF-8.
+fn text(value: &HeaderValue) -> Option<&str> {
+    std::str::from_utf8(value.as_bytes())
+        .ok()
+        .map(|value| value.trim_matches([' ', '\t']))
+}
+
+/// Checks whether the byte may appear in a token, such as a media type or
+/// the name of a parameter.
+fn is_token_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+/// Checks whether the string is a non-empty token.
+fn is_token(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(is_token_char)
+}
+
+/// Splits the string at the separator, except inside quoted strings, and
+/// returns the trimmed elements that are not empty.
+fn split_list(s: &str, separator: u8) -> Vec<&str> {
+    let mut elements = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (i, b) in s.bytes().enumerate() {
+        match b {
+            _ if escaped => escaped = false,
+            b'\\' if quoted => escaped = true,
+            b'"' => quoted = !quoted,
+            b if b == separator && !quoted => {
+                elements.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    elements.push(&s[start..]);
+    elements
+        .into_iter()
+        .map(|element| element.trim_matches([' ', '\t']))
+        .filter(|element| !element.is_empty())
+        .collect()
+}
+
+/// Parses the value of a parameter, which is either a token or a quoted
+/// string whose escapes are removed.
+fn parse_param_value(s: &str) -> Option<String> {
+    let Some(quoted) = s.strip_prefix('"') else {
+        return is_token(s).then(|| s.to_owned());
+    };
+    let mut value = String::new();
+    let mut chars = quoted.chars();
+    loop {
+        match chars.next()? {
+            '"' => return chars.as_str().is_empty().then_some(value),
+            '\\' => value.push(chars.next()?),
+            c if c.is_control() && c != '\t' => return None,
+            c => value.push(c),
+        }
+    }
+}
+
+/// Parses the `;`-separated parameters of an element, whose names are
+/// case-insensitive and returned in lowercase.
+fn parse_params<'a, I>(params: I) -> Option<Vec<(String, String)>>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    params
+        .into_iter()
+        .map(|param| {
+            let (name, value) = param.split_once('=')?;
+            let name = name.trim_end_matches([' ', '\t']);
+            let value = value.trim_start_matches([' ', '\t']);
+            is_token(name)
+                .then(|| Some((name.to_ascii_lowercase(), parse_param_value(value)?)))
+                .flatten()
+        })
+        .collect()
+}
+
+/// Returns the value of a parameter as a token, or as a quoted string if it
+/// is not a token.
+fn quote(value: &str) -> Cow<'_, str> {
+    if is_token(value) {
+        return Cow::Borrowed(value);
+    }
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    Cow::Owned(quoted)
+}
@@ -0,0 +1,125 @@
+use std::fmt;
+
+use super::{split_list, text, TypedHeader};
+use crate::http::{HeaderName, HeaderValue};
+
+/// The `Range` header, i.e. the byte ranges of the representation that the
+/// client requests. Ranges of units other than bytes are not supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Range {
+    ranges: Vec<ByteRange>,
+}
+
+/// A range of bytes of a `Range` header, whose positions are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// The bytes from the first position to the last one, e.g. `0-499`.
+    Bounded(u64, u64),
+    /// The bytes from the position to the end, e.g. `9500-`.
+    From(u64),
+    /// The given number of bytes at the end, e.g. `-500`.
+    Suffix(u64),
+}
+
+impl Range {
+    /// Creates a `Range` header of the byte ranges.
+    pub fn bytes<I>(ranges: I) -> Self
+    where
+        I: IntoIterator<Item = ByteRange>,
+    {
+        Range {
+            ranges: ranges.into_iter().collect(),
+        }
+    }
+
+    /// Returns the byte ranges, in the order of the header.
+    #[inline]
+    pub fn ranges(&self) -> &[ByteRange] {
+        &self.ranges
+    }
+
+    /// Returns the first and last positions of the ranges that can be served
+    /// from a representation of the length, i.e. the satisfiable ones.
+    ///
+    /// An empty result is answered with `416 Range Not Satisfiable`.
+    pub fn satisfiable(&self, len: u64) -> Vec<(u64, u64)> {
+        self.ranges
+            .iter()
+            .filter_map(|range| range.resolve(len))
+            .collect()
+    }
+}
+
+impl ByteRange {
+    /// Returns the first and last positions of the range in a representation
+    /// of the length, or `None` if the range is not satisfiable.
+    ///
+    /// Ranges that go past the end of the representation are shortened.
+    pub fn resolve(&self, len: u64) -> Option<(u64, u64)> {
+        match *self {
+            ByteRange::Bounded(first, last) if first < len => Some((first, last.min(len - 1))),
+            ByteRange::From(first) if first < len => Some((first, len - 1)),
+            ByteRange::Suffix(suffix) if suffix > 0 && len > 0 => {
+                Some((len.saturating_sub(suffix), len - 1))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl TypedHeader for Range {
+    const NAME: HeaderName = HeaderName::RANGE;
+
+    fn decode(value: &HeaderValue) -> Option<Self> {
+        let (unit, ranges) = text(value)?.split_once('=')?;
+        if !unit.trim().eq_ignore_ascii_case("bytes") {
+            return None;
+        }
+        let ranges: Vec<_> = split_list(ranges, b',')
+            .into_iter()
+            .map(|range| {
+                let (first, last) = range.split_once('-')?;
+                match (position(first.trim()), position(last.trim())) {
+                    (Some(first), Some(last)) if first <= last => {
+                        Some(ByteRange::Bounded(first, last))
+                    }
+                    (Some(first), None) if last.trim().is_empty() => Some(ByteRange::From(first)),
+                    (None, Some(suffix)) if first.trim().is_empty() => {
+                        Some(ByteRange::Suffix(suffix))
+                    }
+                    _ => None,
+                }
+            })
+            .collect::<Option<_>>()?;
+        (!ranges.is_empty()).then_some(Range { ranges })
+    }
+
+    fn encode(&self) -> HeaderValue {
+        HeaderValue::from_lossy(self.to_string())
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("bytes=")?;
+        for (i, range) in self.ranges.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            match range {
+                ByteRange::Bounded(first, last) => write!(f, "{first}-{last}")?,
+                ByteRange::From(first) => write!(f, "{first}-")?,
+                ByteRange::Suffix(suffix) => write!(f, "-{suffix}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a position of a byte range, which only has digits.
+fn position(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
@@ -1,44 +1,224 @@
 use bytes::Bytes;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
-#[derive(Debug, Clone, PartialEq)]
+use crate::error::InvalidHeaderValue;
+
+/// The value of a header.
+///
+/// A value has no control characters other than horizontal tabs, so that it
+/// cannot end the line of its header, e.g. with an echoed CR LF. Bytes that
+/// are not ASCII, i.e. obs-text, are accepted for compatibility, but they are
+/// not text: `to_str` rejects them.
+///
+/// A value can be marked as sensitive, such as credentials, so that it is
+/// redacted when the value, or the `HeaderMap` that holds it, is formatted
+/// with `Debug`, which is the format meant for logging. `Display` writes the
+/// value as it is sent, as do the `Display` implementations of `Request` and
+/// `Response`.
+#[derive(Clone)]
 pub struct HeaderValue {
     inner: Bytes,
+    sensitive: bool,
 }
 
 impl HeaderValue {
+    /// Creates a `HeaderValue` from a static string, without copying it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the string has a control character other than a horizontal
+    /// tab. In a constant, the panic is a compile-time error.
+    pub const fn from_static(value: &'static str) -> Self {
+        let bytes = value.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            if !is_valid(bytes[i]) {
+                panic!("invalid header value");
+            }
+            i += 1;
+        }
+        HeaderValue {
+            inner: Bytes::from_static(bytes),
+            sensitive: false,
+        }
+    }
+
+    /// Creates a `HeaderValue` from bytes, or fails with `InvalidHeaderValue`
+    /// if they have a control character other than a horizontal tab.
+    #[inline]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, InvalidHeaderValue> {
+        HeaderValue::try_from(Bytes::copy_from_slice(bytes))
+    }
+
+    /// Creates a `HeaderValue` from a string whose control characters are
+    /// replaced with spaces, for values that are assembled from parts which
+    /// are not validated, such as typed headers.
+    pub(crate) fn from_lossy(value: String) -> Self {
+        let mut bytes = value.into_bytes();
+        for b in bytes.iter_mut().filter(|b| !is_valid(**b)) {
+            *b = b' ';
+        }
+        HeaderValue {
+            inner: Bytes::from(bytes),
+            sensitive: false,
+        }
+    }
+
     /// Consumes the `HeaderValue` struct and return the value in `Bytes`.
     pub fn as_bytes(&self) -> &Bytes {
         &self.inner
     }
+
+    /// Returns the value as a string, or fails with `InvalidHeaderValue` if it
+    /// has bytes that are not visible ASCII characters, spaces or tabs.
+    pub fn to_str(&self) -> Result<&str, InvalidHeaderValue> {
+        // The value has no control characters, so it is text unless it has
+        // obs-text, and ASCII text is valid UTF-8.
+        match self.inner.is_ascii() {
+            true => std::str::from_utf8(&self.inner)
+                .map_err(|_| InvalidHeaderValue::with_context(self.context())),
+            false => Err(InvalidHeaderValue::with_context(self.context())),
+        }
+    }
+
+    /// Marks the value as sensitive, or not, so that it is redacted when it
+    /// is formatted with `Debug` or reported in an error.
+    #[inline]
+    pub fn set_sensitive(&mut self, sensitive: bool) {
+        self.sensitive = sensitive;
+    }
+
+    /// Checks whether the value is sensitive.
+    #[inline]
+    pub fn is_sensitive(&self) -> bool {
+        self.sensitive
+    }
+
+    /// Returns the value, with its control characters and non-ASCII bytes
+    /// escaped, as the context of an error, or a placeholder if it is
+    /// sensitive.
+    pub(crate) fn context(&self) -> String {
+        match self.sensitive {
+            true => "[sensitive]".to_owned(),
+            false => self.inner.escape_ascii().to_string(),
+        }
+    }
 }
 
-impl<const N: usize> From<&[u8; N]> for HeaderValue {
-    fn from(slice: &[u8; N]) -> Self {
-        Self {
-            inner: Bytes::copy_from_slice(slice),
+impl TryFrom<Bytes> for HeaderValue {
+    type Error = InvalidHeaderValue;
+
+    fn try_from(bytes: Bytes) -> Result<Self, Self::Error> {
+        if !bytes.iter().all(|&b| is_valid(b)) {
+            return Err(InvalidHeaderValue::with_context(
+                bytes.escape_ascii().to_string(),
+            ));
         }
+        Ok(HeaderValue {
+            inner: bytes,
+            sensitive: false,
+        })
+    }
+}
+
+impl<const N: usize> TryFrom<&[u8; N]> for HeaderValue {
+    type Error = InvalidHeaderValue;
+
+    #[inline]
+    fn try_from(slice: &[u8; N]) -> Result<Self, Self::Error> {
+        HeaderValue::from_bytes(slice)
     }
 }
 
-impl From<&[u8]> for HeaderValue {
-    fn from(slice: &[u8]) -> Self {
-        Self {
-            inner: Bytes::copy_from_slice(slice),
+impl TryFrom<&[u8]> for HeaderValue {
+    type Error = InvalidHeaderValue;
+
+    #[inline]
+    fn try_from(slice: &[u8]) -> Result<Self, Self::Error> {
+        HeaderValue::from_bytes(slice)
+    }
+}
+
+impl TryFrom<&str> for HeaderValue {
+    type Error = InvalidHeaderValue;
+
+    #[inline]
+    fn try_from(slice: &str) -> Result<Self, Self::Error> {
+        HeaderValue::from_bytes(slice.as_bytes())
+    }
+}
+
+impl TryFrom<Vec<u8>> for HeaderValue {
+    type Error = InvalidHeaderValue;
+
+    #[inline]
+    fn try_from(vec: Vec<u8>) -> Result<Self, Self::Error> {
+        HeaderValue::try_from(Bytes::from(vec))
+    }
+}
+
+impl TryFrom<String> for HeaderValue {
+    type Error = InvalidHeaderValue;
+
+    #[inline]
+    fn try_from(string: String) -> Result<Self, Self::Error> {
+        HeaderValue::try_from(Bytes::from(string))
+    }
+}
+
+macro_rules! from_integers {
+    ($($int:ty),+) => {
+        $(
+        impl From<$int> for HeaderValue {
+            /// Creates a `HeaderValue` of the decimal representation of the number.
+            fn from(num: $int) -> Self {
+                HeaderValue {
+                    inner: Bytes::from(num.to_string()),
+                    sensitive: false,
+                }
+            }
         }
+        )+
+    };
+}
+
+from_integers!(u16, u32, u64, usize, i16, i32, i64, isize);
+
+impl PartialEq for HeaderValue {
+    /// Compares the bytes of the values, whether they are sensitive or not.
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
     }
 }
 
-impl From<&str> for HeaderValue {
-    fn from(slice: &str) -> Self {
-        Self {
-            inner: Bytes::copy_from_slice(slice.as_bytes()),
+impl Eq for HeaderValue {}
+
+impl Hash for HeaderValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state);
+    }
+}
+
+impl fmt::Debug for HeaderValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.sensitive {
+            true => f.write_str("Sensitive"),
+            false => write!(f, "\"{}\"", self.inner.escape_ascii()),
         }
     }
 }
 
 impl fmt::Display for HeaderValue {
+    /// Writes the value, whether it is sensitive or not, with the bytes that
+    /// are not UTF-8 replaced.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", String::from_utf8_lossy(self.inner.as_ref()))
     }
 }
+
+/// Checks whether the byte may appear in a value, i.e. whether it is not a
+/// control character other than a horizontal tab.
+const fn is_valid(b: u8) -> bool {
+    b == b'\t' || (b >= b' ' && b != 0x7F)
+}
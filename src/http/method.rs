@@ -8,7 +8,9 @@ use crate::error::InvalidMethod;
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 enum Inner {
     Get,
+    Head,
     Post,
+    Connect,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -24,13 +26,21 @@ impl Method {
     // GET
     pub const GET: Method = Method(Get);
 
+    // HEAD
+    pub const HEAD: Method = Method(Head);
+
     // POST
     pub const POST: Method = Method(Post);
 
+    // CONNECT
+    pub const CONNECT: Method = Method(Connect);
+
     pub fn as_str(&self) -> &str {
         match self.0 {
             Get => "GET",
+            Head => "HEAD",
             Post => "POST",
+            Connect => "CONNECT",
         }
     }
 }
@@ -42,8 +52,10 @@ impl<'a> TryFrom<&'a str> for Method {
     fn try_from(t: &'a str) -> Result<Self, Self::Error> {
         match t {
             "GET" => Ok(Method::GET),
+            "HEAD" => Ok(Method::HEAD),
             "POST" => Ok(Method::POST),
-            _ => Err(InvalidMethod),
+            "CONNECT" => Ok(Method::CONNECT),
+            _ => Err(InvalidMethod::with_context(t)),
         }
     }
 }
@@ -1,17 +1,27 @@
+pub(crate) mod base64;
 pub mod body;
+pub mod date;
+pub(crate) mod decode;
+pub mod encode;
 pub mod header;
 pub mod method;
 pub mod request;
 pub mod response;
 pub mod status;
+pub mod transport;
+pub mod upgrade;
 pub mod uri;
 pub mod version;
 
-pub use body::Body;
+pub use body::{Body, FileBody, FromBody};
+pub use date::HttpDate;
+pub use encode::Encode;
 pub use header::{HeaderMap, HeaderName, HeaderValue};
 pub use method::Method;
-pub use request::Request;
+pub use request::{ConnectionInfo, Request};
 pub use response::Response;
 pub use status::StatusCode;
+pub use transport::Transport;
+pub use upgrade::Upgraded;
 pub use uri::Uri;
 pub use version::Version;
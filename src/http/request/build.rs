@@ -1,5 +1,6 @@
 use super::parts::Parts;
 use crate::error::{Error, Result};
+use crate::http::header::TypedHeader;
 use crate::http::{Body, HeaderName, HeaderValue, Method, Request, Uri, Version};
 
 /// An HTTP request builder
@@ -70,7 +71,8 @@ impl Builder {
     /// Inserts a pair of header-name and header-value to the `HeaderMap`.
     pub fn with_header<N, V>(self, name: N, val: V) -> Self
     where
-        HeaderValue: From<V>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<Error>,
         HeaderName: TryFrom<N>,
         <HeaderName as TryFrom<N>>::Error: Into<Error>,
     {
@@ -81,9 +83,37 @@ impl Builder {
         Self { inner }
     }
 
+    /// Inserts a typed header to the `HeaderMap`.
+    pub fn with_typed_header<H: TypedHeader>(self, header: H) -> Self {
+        let inner = self.inner.map(move |mut head| {
+            head.headers.typed_insert(header);
+            head
+        });
+        Self { inner }
+    }
+
     /// Sets the body of the request that the `Builder` is constructing.
-    #[inline]
+    ///
+    /// As for a response, the `Content-Length` header is set to the length
+    /// of the body, or, if the length is not known, the body is sent with
+    /// chunked transfer-coding. An empty body of a method that does not expect
+    /// one, such as `GET`, has neither header.
     pub fn with_body<T: Body>(self, body: T) -> Result<Request<T>> {
+        let len = body.content_len();
+        let inner = self.inner.map(move |mut head| {
+            match len {
+                Some(0) if head.method != Method::POST => head.headers.remove_body_len(),
+                len => head.headers.set_body_len(len),
+            }
+            head
+        });
+        Self { inner }.with_unframed_body(body)
+    }
+
+    /// Sets the body of the request without setting the headers that delimit
+    /// it, e.g. for the head of a received request, whose headers are kept.
+    #[inline]
+    pub(crate) fn with_unframed_body<T>(self, body: T) -> Result<Request<T>> {
         self.inner.map(move |head| Request { head, body })
     }
 }
@@ -0,0 +1,44 @@
+/// Information about the connection that a request was received on.
+///
+/// It is filled in by the listener that accepted the connection, e.g. with
+/// the protocol that was negotiated through TLS ALPN and the server name that
+/// the client requested through SNI.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    alpn_protocol: Option<Vec<u8>>,
+    server_name: Option<String>,
+}
+
+impl ConnectionInfo {
+    /// Creates the information of a plain connection.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the application protocol that was negotiated through ALPN.
+    #[inline]
+    pub fn with_alpn_protocol<P: Into<Vec<u8>>>(mut self, protocol: P) -> Self {
+        self.alpn_protocol = Some(protocol.into());
+        self
+    }
+
+    /// Sets the server name that the client requested through SNI.
+    #[inline]
+    pub fn with_server_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.server_name = Some(name.into());
+        self
+    }
+
+    /// Returns the application protocol that was negotiated through ALPN, e.g. `b"http/1.1"`.
+    #[inline]
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.alpn_protocol.as_deref()
+    }
+
+    /// Returns the server name that the client requested through SNI.
+    #[inline]
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+}
@@ -1,15 +1,17 @@
 pub mod build;
+pub mod connection;
 pub mod parts;
 
 use std::fmt;
 use std::io::{BufRead, BufReader, Read};
-use std::net::TcpStream;
 
 use crate::error::*;
 use crate::http::*;
 use build::Builder;
 use parts::Parts;
 
+pub use connection::ConnectionInfo;
+
 use bytes::Bytes;
 
 pub struct Request<T> {
@@ -24,54 +26,124 @@ impl Request<()> {
         Builder::new()
     }
 
-    /// Creates a new `Request` from a TCP Stream.
-    pub fn from_stream(stream: &mut TcpStream) -> Result<Request<Bytes>> {
-        let mut bufreader = BufReader::new(stream);
+    /// Creates a new `Request` from a stream, such as a `TcpStream`.
+    ///
+    /// The stream is read through a `BufReader`, so bytes that follow the
+    /// request may be read from the stream and discarded.
+    pub fn from_stream<T: FromBody, S: Read>(stream: &mut S) -> Result<Request<T>> {
+        Request::from_reader(&mut BufReader::new(stream))
+    }
+
+    /// Creates a new `Request` from a buffered reader.
+    ///
+    /// Reads exactly the bytes of the request, so the reader can be any
+    /// `BufRead`, from a `BufReader` over a socket to a byte slice. The body
+    /// of the request is converted to `T` from the received bytes.
+    ///
+    /// A request line without a version is read as a simple request of
+    /// HTTP/0.9. Versions whose major is not 1 fail with `UnsupportedVersion`,
+    /// which is answered with `505 HTTP Version Not Supported`, and a token
+    /// that is not a version with `InvalidVersion`, i.e. `400 Bad Request`.
+    ///
+    /// A head larger than 64 KiB fails with `HeadersTooLarge`, and a body
+    /// larger than 16 MiB with `PayloadTooLarge`.
+    pub fn from_reader<T: FromBody, R: BufRead>(bufreader: &mut R) -> Result<Request<T>> {
+        Request::read_head(bufreader, decode::MAX_HEAD_SIZE, &mut None)?
+            .read_body(bufreader, decode::MAX_BODY_SIZE)
+    }
 
-        // Parse the request-line
-        let mut request_line = String::new();
-        bufreader
-            .read_line(&mut request_line)
-            .expect("Failed to read first line.");
+    /// Reads the request line and the headers of a request, and returns the
+    /// request without its body, which `read_body` reads.
+    ///
+    /// This lets the server decide whether to read the body, e.g. to answer
+    /// `Expect: 100-continue`, once the headers are known. A request with
+    /// both `Transfer-Encoding` and `Content-Length`, or with different
+    /// `Content-Length` values, fails with `InvalidHeaderValue`, and a head
+    /// larger than `max_size` with `HeadersTooLarge`.
+    ///
+    /// The version of the request line is stored in `version` once it has
+    /// been parsed, so that an error that follows can be answered with it.
+    pub(crate) fn read_head<R: BufRead>(
+        bufreader: &mut R,
+        max_size: usize,
+        version: &mut Option<Version>,
+    ) -> Result<Request<()>> {
+        // Parse the request-line, ignoring the empty lines that clients may
+        // send between the requests of a persistent connection
+        let mut budget = max_size;
+        let mut line = Vec::new();
+        while line.trim_ascii().is_empty() {
+            decode::read_line(bufreader, &mut line, &mut budget, decode::head_too_large)?;
+        }
+        let request_line = String::from_utf8_lossy(&line);
         let mut request_line = request_line.trim().split(' ');
-        let mut request = Request::builder()
-            .with_method(
-                request_line
-                    .next()
-                    .ok_or_else(|| Error::from(InvalidMethod))?,
-            )
-            .with_uri(request_line.next().ok_or_else(|| Error::from(InvalidUri))?)
-            .with_version(
-                request_line
-                    .next()
-                    .ok_or_else(|| Error::from(InvalidVersion))?,
-            );
-
-        // Parse the header lines
-        let mut header_line = String::new();
-        let mut content_len = "0".to_string();
-        while bufreader.read_line(&mut header_line).is_ok() {
-            if header_line == "\r\n" {
-                break;
-            }
-            if let Some((name, val)) = header_line.trim().split_once(": ") {
-                if name == "Content-Length" {
-                    content_len = val.to_string();
-                }
-                request = request.with_header(name.as_bytes(), val.as_bytes());
-                header_line.clear();
-            } else {
-                panic!("Invalid header line {header_line}");
+        let method = request_line
+            .next()
+            .ok_or_else(|| Error::from(InvalidMethod::new()))?;
+        let uri = request_line
+            .next()
+            .ok_or_else(|| Error::from(InvalidUri::new()))?;
+        let request = Request::builder().with_method(method).with_uri(uri);
+
+        // A request line without a version is a simple request of HTTP/0.9,
+        // which has neither headers nor a body. Other requests must be HTTP/1,
+        // since HTTP/2 starts with its connection preface instead.
+        let parsed = match request_line.next() {
+            Some(version) => version::parse_http1(version)?,
+            None if method == "GET" => {
+                return request
+                    .with_version(Version::HTTP_09)
+                    .with_unframed_body(());
             }
+            None => return Err(InvalidVersion::new().into()),
+        };
+        *version = Some(parsed);
+        let mut request = request.with_version(parsed);
+        for (name, value) in decode::read_headers(bufreader, false, &mut budget)? {
+            request = request.with_header(name, value);
+        }
+        let request = request.with_unframed_body(())?;
+        // A body that is delimited both ways may be delimited the other way
+        // by a proxy in front of the server, which would smuggle a request.
+        let headers = request.headers();
+        if headers.get(&HeaderName::TRANSFER_ENCODING).is_some()
+            && headers.get(&HeaderName::CONTENT_LENGTH).is_some()
+        {
+            let context = "Transfer-Encoding with Content-Length";
+            return Err(InvalidHeaderValue::with_context(context).into());
         }
+        Ok(request)
+    }
+
+    /// Reads the body of the request whose head has been read, which is sent
+    /// with chunked transfer-coding or has the length of its `Content-Length`
+    /// header, and fails with `PayloadTooLarge` if it is longer than `max_len`.
+    pub(crate) fn read_body<T: FromBody, R: BufRead>(
+        self,
+        bufreader: &mut R,
+        max_len: usize,
+    ) -> Result<Request<T>> {
+        let body = self.read_body_bytes(bufreader, max_len)?;
+        self.with_body_bytes(body)
+    }
 
-        // Parse the request's body
-        let len = content_len
-            .parse::<usize>()
-            .map_err(|_| Error::from(InvalidHeaderName))?;
-        let mut body = vec![0_u8; len];
-        let _ = bufreader.read_exact(&mut body);
-        request.with_body(Bytes::copy_from_slice(&body))
+    /// Reads the bytes of the body of the request whose head has been read,
+    /// so that they can be converted later, e.g. on another thread.
+    pub(crate) fn read_body_bytes<R: BufRead>(
+        &self,
+        bufreader: &mut R,
+        max_len: usize,
+    ) -> Result<Bytes> {
+        decode::read_body(bufreader, self.headers(), false, max_len)
+    }
+
+    /// Sets the body of the request whose head has been received, which is
+    /// converted to `T` from the received bytes.
+    pub(crate) fn with_body_bytes<T: FromBody>(self, bytes: Bytes) -> Result<Request<T>> {
+        Ok(Request {
+            head: self.head,
+            body: T::from_bytes(bytes)?,
+        })
     }
 }
 
@@ -109,11 +181,43 @@ impl<T> Request<T> {
         self.head.headers.get(&header_name)
     }
 
+    /// Returns the information of the connection that the `Request` was received on.
+    #[inline]
+    pub fn connection(&self) -> &ConnectionInfo {
+        &self.head.connection
+    }
+
+    /// Returns the application protocol that was negotiated through TLS ALPN
+    /// on the connection of the `Request`, if any.
+    #[inline]
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.head.connection.alpn_protocol()
+    }
+
+    /// Sets the information of the connection that the `Request` was received on.
+    #[inline]
+    pub(crate) fn set_connection(&mut self, connection: ConnectionInfo) {
+        self.head.connection = connection;
+    }
+
     /// Returns a reference to the body of the `Request`.
     #[inline]
     pub fn body(&self) -> &T {
         &self.body
     }
+
+    /// Creates a `Request` from its head and its body, whose headers are
+    /// kept as they are.
+    #[inline]
+    pub(crate) fn from_parts(head: Parts, body: T) -> Request<T> {
+        Request { head, body }
+    }
+
+    /// Splits the `Request` into its head and its body.
+    #[inline]
+    pub(crate) fn into_parts(self) -> (Parts, T) {
+        (self.head, self.body)
+    }
 }
 
 impl<T> fmt::Display for Request<T>
@@ -1,3 +1,4 @@
+use super::ConnectionInfo;
 use crate::http::{HeaderMap, Method, Uri, Version};
 
 #[derive(Default)]
@@ -13,6 +14,9 @@ pub struct Parts {
 
     /// The request's headers
     pub headers: HeaderMap,
+
+    /// The connection that the request was received on
+    pub connection: ConnectionInfo,
 }
 
 impl Parts {
@@ -1,5 +1,6 @@
 use super::parts::Parts;
 use crate::error::{Error, Result};
+use crate::http::header::TypedHeader;
 use crate::http::{Body, HeaderName, HeaderValue, Response, StatusCode, Version};
 
 /// An HTTP response builder
@@ -58,7 +59,8 @@ impl Builder {
     /// Inserts a pair of header-name and header-value to the `HeaderMap`.
     pub fn with_header<N, V>(self, name: N, val: V) -> Self
     where
-        HeaderValue: From<V>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<Error>,
         HeaderName: TryFrom<N>,
         <HeaderName as TryFrom<N>>::Error: Into<Error>,
     {
@@ -70,10 +72,44 @@ impl Builder {
         Builder { inner }
     }
 
+    /// Inserts a typed header to the `HeaderMap`.
+    pub fn with_typed_header<H: TypedHeader>(self, header: H) -> Self {
+        let inner = self.inner.map(move |mut head| {
+            head.headers.typed_insert(header);
+            head
+        });
+
+        Builder { inner }
+    }
+
     /// Sets the body of the response that the `Builder` is constructing.
+    ///
+    /// The `Content-Length` header is set to the length of the body, or, if
+    /// the length is not known, the body is sent with chunked transfer-coding.
+    /// Informational (1xx) and `204 No Content` responses have no body, so
+    /// neither header is set. A `304 Not Modified` response has no body
+    /// either, but keeps the headers of the representation it validates.
     pub fn with_body<T: Body>(self, body: T) -> Result<Response<T>> {
-        let len = format!("{}", body.content_len());
-        let builder = self.with_header(b"Content-Length", len.as_bytes());
-        builder.inner.map(move |head| Response { head, body })
+        let len = body.content_len();
+        let inner = self.inner.map(move |mut head| {
+            match head.status.code().get() {
+                100..=199 | 204 => head.headers.remove_body_len(),
+                304 => {}
+                _ => head.headers.set_body_len(len),
+            }
+            head
+        });
+        Builder { inner }.with_unframed_body(body)
+    }
+
+    /// Sets the body of the response without a `Content-Length` header or
+    /// chunked transfer-coding, so that the body is delimited by closing the
+    /// connection.
+    pub(crate) fn with_unframed_body<T: Body>(self, body: T) -> Result<Response<T>> {
+        self.inner.map(move |head| Response {
+            head,
+            body,
+            upgrade: None,
+        })
     }
 }
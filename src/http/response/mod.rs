@@ -1,18 +1,27 @@
 pub mod build;
 pub mod parts;
 
-use crate::http::header::HeaderMap;
+use crate::error::{InvalidStatusCode, Result};
+use crate::http::body::FromBody;
+use crate::http::decode;
+use crate::http::header::{HeaderMap, HeaderName};
+use crate::http::method::Method;
 use crate::http::status::StatusCode;
-use crate::http::version::Version;
+use crate::http::upgrade::{OnUpgrade, Upgraded};
+use crate::http::version::{self, Version};
 
 use build::Builder;
 use parts::Parts;
 
 use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+
+use bytes::Bytes;
 
 pub struct Response<T> {
     head: Parts,
     body: T,
+    upgrade: Option<OnUpgrade>,
 }
 
 impl Response<()> {
@@ -21,6 +30,81 @@ impl Response<()> {
     pub fn builder() -> Builder {
         Builder::new()
     }
+
+    /// Creates a new `Response` from a stream, such as a `TcpStream`.
+    ///
+    /// The stream is read through a `BufReader`, so bytes that follow the
+    /// response may be read from the stream and discarded.
+    pub fn from_stream<T: FromBody, S: Read>(stream: &mut S) -> Result<Response<T>> {
+        Response::from_reader(&mut BufReader::new(stream))
+    }
+
+    /// Creates a new `Response` from a buffered reader, as the answer to a
+    /// `GET` request.
+    ///
+    /// Reads exactly the bytes of the response, so the reader can be any
+    /// `BufRead`. The body, which is sent with chunked transfer-coding, has
+    /// the length of the `Content-Length` header, or else lasts until the
+    /// stream ends, is converted to `T` from the received bytes.
+    ///
+    /// Headers whose names are not known are skipped, and an interim (1xx)
+    /// response is returned as it is, without the response that follows it.
+    pub fn from_reader<T: FromBody, R: BufRead>(bufreader: &mut R) -> Result<Response<T>> {
+        Response::from_reader_for(bufreader, &Method::GET)
+    }
+
+    /// Creates a new `Response` from a buffered reader, as the answer to a
+    /// request of the method, which tells whether the response has a body,
+    /// e.g. none for `HEAD`.
+    pub fn from_reader_for<T: FromBody, R: BufRead>(
+        bufreader: &mut R,
+        method: &Method,
+    ) -> Result<Response<T>> {
+        Response::read_head(bufreader)?.read_body(bufreader, method)
+    }
+
+    /// Reads the status line and the headers of a response, and returns the
+    /// response without its body, which `read_body` reads.
+    ///
+    /// A head larger than 64 KiB fails with `HeadersTooLarge`.
+    pub(crate) fn read_head<R: BufRead>(bufreader: &mut R) -> Result<Response<()>> {
+        let mut budget = decode::MAX_HEAD_SIZE;
+        let mut line = Vec::new();
+        decode::read_line(bufreader, &mut line, &mut budget, decode::head_too_large)?;
+        let line = String::from_utf8_lossy(&line);
+        let mut status_line = line.splitn(3, ' ');
+        let version = version::parse_http1(status_line.next().unwrap_or_default())?;
+        // The reason phrase is optional, and the canonical one is kept instead
+        let status = status_line
+            .next()
+            .filter(|status| status.len() == 3)
+            .ok_or_else(|| InvalidStatusCode::with_context(line.as_ref()))?;
+        let mut response = Response::builder()
+            .with_version(version)
+            .with_status(status);
+        for (name, value) in decode::read_headers(bufreader, true, &mut budget)? {
+            response = response.with_header(name, value);
+        }
+        response.with_unframed_body(())
+    }
+
+    /// Reads the body of the response whose head has been read, as the
+    /// answer to a request of the method.
+    pub(crate) fn read_body<T: FromBody, R: BufRead>(
+        self,
+        bufreader: &mut R,
+        method: &Method,
+    ) -> Result<Response<T>> {
+        let body = match self.has_body(method) {
+            true => decode::read_body(bufreader, self.headers(), true, usize::MAX)?,
+            false => Bytes::new(),
+        };
+        Ok(Response {
+            head: self.head,
+            body: T::from_bytes(body)?,
+            upgrade: None,
+        })
+    }
 }
 
 impl<T> Response<T> {
@@ -47,6 +131,130 @@ impl<T> Response<T> {
     pub fn headers(&self) -> &HeaderMap {
         &self.head.headers
     }
+
+    /// Returns a mutable reference to the header-map of the `Response`.
+    #[inline]
+    pub(crate) fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.head.headers
+    }
+
+    /// Sets the function that takes over the connection once the `Response`
+    /// has been written, e.g. to speak another protocol after `101 Switching
+    /// Protocols`, or to tunnel the connection of a `CONNECT` request.
+    ///
+    /// The function runs on the worker thread that served the request, and
+    /// receives the connection with the bytes that have already been read
+    /// from it. Over HTTP/2, it receives the stream of the request instead,
+    /// whose writes are the body of the `Response`.
+    #[inline]
+    pub fn with_upgrade<F>(mut self, on_upgrade: F) -> Self
+    where
+        F: FnOnce(Upgraded) + Send + 'static,
+    {
+        self.upgrade = Some(Box::new(on_upgrade));
+        self
+    }
+
+    /// Removes the `Content-Length` and `Transfer-Encoding` headers that the
+    /// `Response` must not have as the answer to a request of the method,
+    /// i.e. those of informational (1xx) and `204 No Content` responses, and
+    /// of the successful responses to `CONNECT`.
+    pub(crate) fn conform_to_method(&mut self, method: &Method) {
+        let status = self.head.status.code().get();
+        if (100..200).contains(&status)
+            || status == 204
+            || *method == Method::CONNECT && (200..300).contains(&status)
+        {
+            self.head.headers.remove_body_len();
+        }
+    }
+
+    /// Adapts the `Response` to the method and the version of the request
+    /// that it answers, and returns whether the connection serves further
+    /// requests once the `Response` has been written.
+    ///
+    /// An HTTP/0.9 response is only its body. HTTP/1.0 has no chunked
+    /// transfer-coding, so a body of unknown length is delimited by closing
+    /// the connection instead. Other responses keep the version they were
+    /// built with, unless it cannot be sent over HTTP/1.
+    ///
+    /// The connection persists if the client asked to keep it alive, the end
+    /// of the body is known, and the `Response` neither closes the connection
+    /// nor takes it over. The `Connection` header tells the client whether
+    /// it persists.
+    pub(crate) fn conform_to(
+        &mut self,
+        method: &Method,
+        version: Version,
+        keep_alive: bool,
+    ) -> bool {
+        self.conform_to_method(method);
+        if version == Version::HTTP_09 {
+            self.head.version = version;
+            return false;
+        }
+        let has_body = self.has_body(method);
+        let mut persistent = keep_alive && (!has_body || self.is_delimited());
+        if version == Version::HTTP_10
+            && self
+                .head
+                .headers
+                .remove(&HeaderName::TRANSFER_ENCODING)
+                .is_some()
+            && has_body
+        {
+            persistent = false;
+        }
+        if self
+            .head
+            .headers
+            .has_token(&HeaderName::CONNECTION, "close")
+        {
+            persistent = false;
+        }
+        if self.upgrade.is_none() {
+            if !persistent {
+                let _ = self.head.headers.insert(b"Connection", b"close");
+            } else if version == Version::HTTP_10 {
+                let _ = self.head.headers.insert(b"Connection", b"keep-alive");
+            }
+        }
+        if self.head.version.major() != 1 {
+            self.head.version = Version::HTTP_11;
+        }
+        persistent && self.upgrade.is_none()
+    }
+
+    /// Returns whether the body of the `Response` is sent as the answer to a
+    /// request of the method.
+    ///
+    /// Responses to `HEAD` requests, informational (1xx), `204 No Content`
+    /// and `304 Not Modified` responses have no body, whatever their headers
+    /// say, and a successful response to `CONNECT` starts a tunnel instead.
+    pub(crate) fn has_body(&self, method: &Method) -> bool {
+        let status = self.head.status.code().get();
+        !(*method == Method::HEAD
+            || (100..200).contains(&status)
+            || status == 204
+            || status == 304
+            || *method == Method::CONNECT && (200..300).contains(&status))
+    }
+
+    /// Returns whether the client can tell where the body ends without the
+    /// connection being closed.
+    pub(crate) fn is_delimited(&self) -> bool {
+        self.head.headers.get(&HeaderName::CONTENT_LENGTH).is_some()
+            || self
+                .head
+                .headers
+                .has_token(&HeaderName::TRANSFER_ENCODING, "chunked")
+    }
+
+    /// Takes the function that takes over the connection, if there is one.
+    #[inline]
+    pub(crate) fn take_upgrade(&mut self) -> Option<OnUpgrade> {
+        self.upgrade.take()
+    }
 }
 
 impl<T: fmt::Display> fmt::Display for Response<T> {
@@ -56,7 +264,7 @@ impl<T: fmt::Display> fmt::Display for Response<T> {
             "{} {} {}\r\n",
             self.version(),
             self.status().code(),
-            self.status().msg().expect("Guaranteed by construction.")
+            self.status().msg().unwrap_or_default()
         )?;
         for (name, val) in self.headers() {
             write!(f, "{}: {}\r\n", name, val)?;
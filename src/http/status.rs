@@ -5,7 +5,7 @@ use std::num::NonZeroU16;
 use crate::error::InvalidStatusCode;
 
 /// An HTTP status code.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct StatusCode(NonZeroU16);
 
 impl Default for StatusCode {
@@ -30,9 +30,10 @@ impl TryFrom<u16> for StatusCode {
     type Error = InvalidStatusCode;
 
     fn try_from(num: u16) -> Result<StatusCode, Self::Error> {
-        NonZeroU16::new(num)
-            .map(StatusCode)
-            .ok_or(InvalidStatusCode)
+        match NonZeroU16::new(num) {
+            Some(code) if (100..1000).contains(&num) => Ok(StatusCode(code)),
+            _ => Err(InvalidStatusCode::with_context(num.to_string())),
+        }
     }
 }
 
@@ -40,14 +41,16 @@ impl<'a> TryFrom<&'a str> for StatusCode {
     type Error = InvalidStatusCode;
 
     fn try_from(s: &'a str) -> Result<StatusCode, Self::Error> {
-        let num = s.parse::<u16>().map_err(|_| InvalidStatusCode)?;
+        let num = s
+            .parse::<u16>()
+            .map_err(|_| InvalidStatusCode::with_context(s))?;
         StatusCode::try_from(num)
     }
 }
 
 impl fmt::Display for StatusCode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let reason = canonical_reason(self.0.into()).expect("Failed to get canonical reason");
+        let reason = canonical_reason(self.0.into()).unwrap_or_default();
         write!(f, "{} {}", self.0, reason)
     }
 }
@@ -75,7 +78,27 @@ macro_rules! status_codes {
 }
 
 status_codes! {
+    (100, CONTINUE, "Continue");
+    (101, SWITCHING_PROTOCOLS, "Switching Protocols");
     (200, OK, "OK");
     (201, CREATED, "Created");
+    (301, MOVED_PERMANENTLY, "Moved Permanently");
+    (302, FOUND, "Found");
+    (303, SEE_OTHER, "See Other");
+    (307, TEMPORARY_REDIRECT, "Temporary Redirect");
+    (308, PERMANENT_REDIRECT, "Permanent Redirect");
+    (400, BAD_REQUEST, "Bad Request");
     (404, NOT_FOUND, "Not Found");
+    (405, METHOD_NOT_ALLOWED, "Method Not Allowed");
+    (408, REQUEST_TIMEOUT, "Request Timeout");
+    (413, PAYLOAD_TOO_LARGE, "Payload Too Large");
+    (415, UNSUPPORTED_MEDIA_TYPE, "Unsupported Media Type");
+    (417, EXPECTATION_FAILED, "Expectation Failed");
+    (422, UNPROCESSABLE_ENTITY, "Unprocessable Entity");
+    (426, UPGRADE_REQUIRED, "Upgrade Required");
+    (431, REQUEST_HEADER_FIELDS_TOO_LARGE, "Request Header Fields Too Large");
+    (500, INTERNAL_SERVER_ERROR, "Internal Server Error");
+    (501, NOT_IMPLEMENTED, "Not Implemented");
+    (502, BAD_GATEWAY, "Bad Gateway");
+    (505, HTTP_VERSION_NOT_SUPPORTED, "HTTP Version Not Supported");
 }
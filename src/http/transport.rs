@@ -0,0 +1,45 @@
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+/// A bidirectional byte stream, that the server reads requests from and
+/// writes responses to.
+///
+/// It is implemented for `TcpStream`, Unix domain sockets and TLS streams.
+/// Other streams, such as in-memory test doubles, implement it with
+/// `impl Transport for MyStream {}`, since they cannot time out.
+pub trait Transport: Read + Write + Send {
+    /// Sets how long a read may block before it fails with `WouldBlock` or
+    /// `TimedOut`, or lets reads block indefinitely if `None`.
+    ///
+    /// The server sets it to close idle connections and clients that are too
+    /// slow to send their requests. By default, it does nothing.
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        let _ = timeout;
+        Ok(())
+    }
+}
+
+impl Transport for TcpStream {
+    #[inline]
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+#[cfg(unix)]
+impl Transport for UnixStream {
+    #[inline]
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    #[inline]
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        (**self).set_read_timeout(timeout)
+    }
+}
@@ -0,0 +1,70 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use bytes::Bytes;
+
+use crate::http::transport::Transport;
+
+/// The function that takes over a connection once its response has been written.
+pub(crate) type OnUpgrade = Box<dyn FnOnce(Upgraded) + Send + 'static>;
+
+/// A connection that a handler has taken over, once the response that
+/// upgraded it has been written.
+///
+/// Reading from it returns the bytes that the server had already read from
+/// the connection but not consumed first, then the bytes of the connection.
+pub struct Upgraded {
+    reader: BufReader<Box<dyn Transport>>,
+}
+
+impl Upgraded {
+    /// Creates an upgraded connection from the reader that the request was read from.
+    #[inline]
+    pub(crate) fn new(reader: BufReader<Box<dyn Transport>>) -> Self {
+        Upgraded { reader }
+    }
+
+    /// Returns the bytes that the client sent after the request, which the
+    /// server has read from the connection but not consumed.
+    #[inline]
+    pub fn buffered(&self) -> &[u8] {
+        self.reader.buffer()
+    }
+
+    /// Returns the raw connection, together with the bytes that have been read
+    /// from it but not consumed.
+    pub fn into_parts(self) -> (Box<dyn Transport>, Bytes) {
+        let buffered = Bytes::copy_from_slice(self.reader.buffer());
+        (self.reader.into_inner(), buffered)
+    }
+}
+
+impl Read for Upgraded {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl BufRead for Upgraded {
+    #[inline]
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt)
+    }
+}
+
+impl Write for Upgraded {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.reader.get_mut().write(buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.reader.get_mut().flush()
+    }
+}
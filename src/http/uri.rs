@@ -25,6 +25,40 @@ impl Uri {
             }
         }
     }
+
+    /// Returns the path component of the URI, without the query string.
+    #[inline]
+    pub fn path(&self) -> &str {
+        match self.inner.split_once('?') {
+            Some((path, _)) => path,
+            None => &self.inner,
+        }
+    }
+
+    /// Returns the query string of the URI, if there is one.
+    #[inline]
+    pub fn query(&self) -> Option<&str> {
+        self.inner.split_once('?').map(|(_, query)| query)
+    }
+}
+
+/// Decodes a percent-encoded string.
+///
+/// Returns `None` if an escape sequence is malformed or the decoded bytes
+/// are not valid UTF-8.
+pub fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = s.bytes();
+    let mut decoded = Vec::with_capacity(s.len());
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hi = (bytes.next()? as char).to_digit(16)?;
+            let lo = (bytes.next()? as char).to_digit(16)?;
+            decoded.push((hi * 16 + lo) as u8);
+        } else {
+            decoded.push(byte);
+        }
+    }
+    String::from_utf8(decoded).ok()
 }
 
 impl PartialEq<str> for Uri {
@@ -1,15 +1,17 @@
 use std::convert::TryFrom;
 use std::fmt;
 
-use crate::error::InvalidVersion;
+use crate::error::{InvalidVersion, Result, UnsupportedVersion};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Http {
+    Http09,
+    Http10,
     Http11,
     Http2,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Version(Http);
 
 impl Default for Version {
@@ -20,21 +22,66 @@ impl Default for Version {
 }
 
 impl Version {
+    /// `HTTP/0.9`
+    pub const HTTP_09: Version = Version(Http::Http09);
+
+    /// `HTTP/1.0`
+    pub const HTTP_10: Version = Version(Http::Http10);
+
     /// `HTTP/1.1`
     pub const HTTP_11: Version = Version(Http::Http11);
 
     /// `HTTP/2.0
     pub const HTTP_2: Version = Version(Http::Http2);
+
+    /// Returns the major version number.
+    pub fn major(&self) -> u8 {
+        match self.0 {
+            Http::Http09 => 0,
+            Http::Http10 | Http::Http11 => 1,
+            Http::Http2 => 2,
+        }
+    }
+}
+
+/// Parses the version of a message that is read from an HTTP/1 connection.
+///
+/// A token that is not an HTTP version at all fails with `InvalidVersion`,
+/// and a version whose major is not 1, such as `HTTP/2.0` or `HTTP/3.0`,
+/// fails with `UnsupportedVersion`.
+pub(crate) fn parse_http1(t: &str) -> Result<Version> {
+    let is_http = match t.as_bytes() {
+        [b'H', b'T', b'T', b'P', b'/', major] => major.is_ascii_digit(),
+        [b'H', b'T', b'T', b'P', b'/', major, b'.', minor] => {
+            major.is_ascii_digit() && minor.is_ascii_digit()
+        }
+        _ => false,
+    };
+    if !is_http {
+        return Err(InvalidVersion::with_context(t).into());
+    }
+    match Version::try_from(t) {
+        Ok(version) if version.major() == 1 => Ok(version),
+        _ => Err(UnsupportedVersion::with_context(t).into()),
+    }
 }
 
 impl<'a> TryFrom<&'a str> for Version {
     type Error = InvalidVersion;
 
-    fn try_from(t: &'a str) -> Result<Self, Self::Error> {
-        match t {
-            "HTTP/1.1" => Ok(Version::HTTP_11),
-            "HTTP/2.0" => Ok(Version::HTTP_2),
-            _ => Err(InvalidVersion),
+    /// Parses a version of the form `HTTP/<major>.<minor>`.
+    ///
+    /// Minor versions of HTTP/1 above 1 are taken as `HTTP/1.1`, the highest
+    /// minor version that is implemented, as RFC 9110 recommends.
+    fn try_from(t: &'a str) -> std::result::Result<Self, Self::Error> {
+        match t.as_bytes() {
+            b"HTTP/0.9" => Ok(Version::HTTP_09),
+            b"HTTP/1.0" => Ok(Version::HTTP_10),
+            [b'H', b'T', b'T', b'P', b'/', b'1', b'.', minor] if minor.is_ascii_digit() => {
+                Ok(Version::HTTP_11)
+            }
+            b"HTTP/2.0" | b"HTTP/2" => Ok(Version::HTTP_2),
+            _ => Err(InvalidVersion::with_context(t)),
         }
     }
 }
@@ -44,6 +91,8 @@ impl fmt::Display for Version {
         use self::Http::*;
 
         f.write_str(match self.0 {
+            Http09 => "HTTP/0.9",
+            Http10 => "HTTP/1.0",
             Http11 => "HTTP/1.1",
             Http2 => "HTTP/2.0",
         })
@@ -1,17 +1,36 @@
+use std::any::TypeId;
 use std::collections::HashMap;
-use std::net::{TcpListener, ToSocketAddrs};
+use std::fmt;
+use std::net::ToSocketAddrs;
+#[cfg(any(unix, feature = "tls"))]
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
-use super::RequestHandler;
+use super::connection::ConnectionOptions;
+use super::extract::{RequestContext, StateMap};
+use super::tcp::TcpOptions;
+#[cfg(feature = "tls")]
+use super::tls::{self, TlsConfig};
+use super::transport::Listener;
+use super::websocket::WebSocket;
+use super::{ErrorHandler, ErrorReporter, ExpectHandler, RequestHandler, WebSocketHandler};
 use crate::error::{Error, FailedConnection, NoErrorHandler, Result};
-use crate::http::{Method, Uri};
+use crate::http::{HeaderValue, Method, Request, Response, StatusCode, Uri};
+use crate::server::handler::{self, Handler};
+#[cfg(unix)]
+use crate::server::unix::UnixSocketListener;
 use crate::server::{pool::ThreadPool, HttpServer, Router};
 
-pub struct Builder<T> {
-    inner: Result<Parts<T>>,
+/// An HTTP server builder.
+///
+/// `Req` is the type of the body of the requests that the handlers receive,
+/// and `Res` the type of the body of the responses that they return.
+pub struct Builder<Req, Res = Req> {
+    inner: Result<Parts<Req, Res>>,
 }
 
-impl<T> Default for Builder<T> {
+impl<Req, Res> Default for Builder<Req, Res> {
     #[inline]
     fn default() -> Self {
         Builder {
@@ -20,13 +39,13 @@ impl<T> Default for Builder<T> {
     }
 }
 
-impl<T> Builder<T> {
+impl<Req: 'static, Res: 'static> Builder<Req, Res> {
     /// Specifies the size of the thread pool for the Server that is constructed.
     #[inline]
     pub fn workers(self, size: usize) -> Self {
-        let inner = self.inner.and_then(move |mut parts| {
+        let inner = self.inner.map(move |mut parts| {
             parts.workers = size;
-            Ok(parts)
+            parts
         });
         Self { inner }
     }
@@ -34,66 +53,481 @@ impl<T> Builder<T> {
     /// Adds a route.
     ///
     /// A route is a request path and the corresponding functions that handles
-    /// the request.
+    /// the request. Segments of the path of the form `:name` capture the
+    /// corresponding segment of the request path, which can be extracted by the
+    /// handler with `Path`.
     #[inline]
-    pub fn route<P, M>(self, path: P, method: M, handler: RequestHandler<T>) -> Self
+    pub fn route<P, M, H, Args>(self, path: P, method: M, handler: H) -> Self
     where
         Uri: TryFrom<P>,
         Method: TryFrom<M>,
         <Uri as TryFrom<P>>::Error: Into<Error>,
         <Method as TryFrom<M>>::Error: Into<Error>,
+        H: Handler<Req, Res, Args>,
+        Args: 'static,
     {
         let inner = self.inner.and_then(move |mut parts| {
             let uri = TryFrom::try_from(path).map_err(Into::into)?;
             let method = TryFrom::try_from(method).map_err(Into::into)?;
-            parts.routes.insert((uri, method), handler);
+            parts.routes.insert((uri, method), handler::boxed(handler));
+            Ok(parts)
+        });
+        Self { inner }
+    }
+
+    /// Adds a WebSocket route.
+    ///
+    /// The requests to the path that ask to upgrade to the WebSocket protocol
+    /// are answered with `101 Switching Protocols`, and the handler takes over
+    /// the connection as a `WebSocket`. It also receives the parameters that
+    /// are captured from the path and the application state. A request whose
+    /// handshake is invalid fails with `WebSocketError`.
+    #[inline]
+    pub fn websocket<P, F>(self, path: P, handler: F) -> Self
+    where
+        Uri: TryFrom<P>,
+        <Uri as TryFrom<P>>::Error: Into<Error>,
+        F: Fn(&Request<Req>, &RequestContext<'_>, WebSocket) + Send + Sync + 'static,
+    {
+        let inner = self.inner.and_then(move |mut parts| {
+            let uri = TryFrom::try_from(path).map_err(Into::into)?;
+            parts.websockets.insert(uri, Box::new(handler));
             Ok(parts)
         });
         Self { inner }
     }
 
     /// Sets the request error handler.
+    ///
+    /// The error handler receives the request and the error that occurred,
+    /// either while routing the request or returned by its handler. The status
+    /// code that corresponds to the error is given by `Error::status`.
+    #[inline]
+    pub fn route_err<F>(self, handler: F) -> Self
+    where
+        F: Fn(&Request<Req>, &Error) -> Result<Response<Res>> + Send + Sync + 'static,
+    {
+        let inner = self.inner.map(move |mut parts| {
+            parts.error_handler = Some(Box::new(handler));
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Sets the error handler for the errors that correspond to a status code.
+    ///
+    /// Errors of other status codes are handled by the request error handler.
+    #[inline]
+    pub fn route_status<S, F>(self, status: S, handler: F) -> Self
+    where
+        StatusCode: TryFrom<S>,
+        <StatusCode as TryFrom<S>>::Error: Into<Error>,
+        F: Fn(&Request<Req>, &Error) -> Result<Response<Res>> + Send + Sync + 'static,
+    {
+        let inner = self.inner.and_then(move |mut parts| {
+            let status = TryFrom::try_from(status).map_err(Into::into)?;
+            parts
+                .status_handlers
+                .insert(status, Box::new(handler) as ErrorHandler<Req, Res>);
+            Ok(parts)
+        });
+        Self { inner }
+    }
+
+    /// Sets the function that reports the errors that no client is answered
+    /// with, i.e. the failures of the listeners to accept a connection, or to
+    /// establish it, e.g. its TLS handshake, or to stop on shutdown.
+    ///
+    /// By default, the errors are printed to the standard error.
+    #[inline]
+    pub fn report_err<F>(self, reporter: F) -> Self
+    where
+        F: Fn(&Error) + Send + Sync + 'static,
+    {
+        let inner = self.inner.map(move |mut parts| {
+            parts.error_reporter = Some(Arc::new(reporter));
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Sets the function that decides, from the head of a request with
+    /// `Expect: 100-continue`, whether the server reads its body.
+    ///
+    /// Clients that send the header wait for `100 Continue` before they send
+    /// the body, which the server sends once the function accepts the request.
+    /// An error, such as `PayloadTooLarge` or `ExpectationFailed`, is answered
+    /// with its status code instead, and the body is never read. Without the
+    /// function, every such request is continued.
+    #[inline]
+    pub fn expect_continue<F>(self, handler: F) -> Self
+    where
+        F: Fn(&Request<()>) -> Result<()> + Send + Sync + 'static,
+    {
+        let inner = self.inner.map(move |mut parts| {
+            parts.expect_handler = Some(Box::new(handler));
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Sets the `Server` header that is added to the responses whose handler
+    /// has not set one, or leaves it out if `None`. The default is `tiny-http`.
+    ///
+    /// The `Date` header is always added to the responses without one. A
+    /// value with control characters fails with `InvalidHeaderValue`.
+    #[inline]
+    pub fn server_header(self, value: Option<&str>) -> Self {
+        let server_name = value.map(HeaderValue::try_from).transpose();
+        let inner = self.inner.and_then(move |mut parts| {
+            parts.server_name = server_name?;
+            Ok(parts)
+        });
+        Self { inner }
+    }
+
+    /// Registers application state, that handlers can extract with `State<S>`.
+    ///
+    /// Only one value can be registered per type; registering another value
+    /// of the same type replaces the previous one.
+    #[inline]
+    pub fn state<S>(self, state: S) -> Self
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let inner = self.inner.map(move |mut parts| {
+            parts.state.insert(TypeId::of::<S>(), Box::new(state));
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Sets `SO_REUSEADDR` on the TCP sockets of the server.
+    ///
+    /// It is enabled by default on Unix, so that a restarted server can bind to
+    /// an address whose previous connections are still in `TIME_WAIT`.
+    #[inline]
+    pub fn reuse_address(self, enabled: bool) -> Self {
+        let inner = self.inner.map(move |mut parts| {
+            parts.tcp.reuse_address = enabled;
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Sets `SO_REUSEPORT` on the TCP sockets of the server, so that several
+    /// processes can listen to the same address. It has no effect on Windows.
+    #[inline]
+    pub fn reuse_port(self, enabled: bool) -> Self {
+        let inner = self.inner.map(move |mut parts| {
+            parts.tcp.reuse_port = enabled;
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Sets `IPV6_V6ONLY` on the TCP sockets of IPv6 addresses, so that they
+    /// do not also accept IPv4 connections.
+    #[inline]
+    pub fn only_v6(self, enabled: bool) -> Self {
+        let inner = self.inner.map(move |mut parts| {
+            parts.tcp.only_v6 = enabled;
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Sets the maximum number of pending connections of the TCP sockets of
+    /// the server. The default is 128.
+    #[inline]
+    pub fn backlog(self, backlog: u32) -> Self {
+        let inner = self.inner.map(move |mut parts| {
+            parts.tcp.backlog = i32::try_from(backlog).unwrap_or(i32::MAX);
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Sets how long a persistent connection may stay idle, waiting for the
+    /// next request, before the server closes it, or no limit if `None` or
+    /// zero. The default is 5 seconds.
+    ///
+    /// An idle connection keeps its worker thread, so the limit is what frees
+    /// the workers of clients that keep their connections open.
     #[inline]
-    pub fn route_err(self, handler: RequestHandler<T>) -> Self {
+    pub fn keep_alive_timeout(self, timeout: Option<Duration>) -> Self {
+        let inner = self.inner.map(move |mut parts| {
+            parts.connection.keep_alive_timeout = timeout.filter(|timeout| !timeout.is_zero());
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Sets how long the head of a request may take to arrive once it has
+    /// started, or no limit if `None` or zero. The default is 10 seconds.
+    ///
+    /// A request whose head is too slow, or whose body stops arriving for as
+    /// long, is answered with `408 Request Timeout` and its connection is
+    /// closed, so that a slow client cannot hold a worker thread. The limit
+    /// also applies to establishing a connection, e.g. its TLS handshake.
+    #[inline]
+    pub fn header_read_timeout(self, timeout: Option<Duration>) -> Self {
+        let inner = self.inner.map(move |mut parts| {
+            parts.connection.header_read_timeout = timeout.filter(|timeout| !timeout.is_zero());
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Sets the size of the largest head of a request, i.e. its request line
+    /// and headers, that the server reads. The default is 64 KiB.
+    ///
+    /// A larger head is answered with `431 Request Header Fields Too Large`
+    /// and its connection is closed. Over HTTP/2, the size is advertised as
+    /// `SETTINGS_MAX_HEADER_LIST_SIZE`, and a larger header block closes the
+    /// connection.
+    #[inline]
+    pub fn max_head_size(self, size: usize) -> Self {
+        let inner = self.inner.map(move |mut parts| {
+            parts.connection.max_head_size = size;
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Sets the size of the largest body of a request that the server reads,
+    /// since bodies are buffered whole before they are handled. The default
+    /// is 16 MiB.
+    ///
+    /// A larger body is answered with `413 Payload Too Large` and its
+    /// connection is closed, before the body is read if its length is known.
+    /// Over HTTP/2, the stream is reset with `REFUSED_STREAM`.
+    #[inline]
+    pub fn max_body_size(self, size: usize) -> Self {
+        let inner = self.inner.map(move |mut parts| {
+            parts.connection.max_body_size = size;
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Sets `TCP_NODELAY` on the connections that the server accepts.
+    #[inline]
+    pub fn nodelay(self, enabled: bool) -> Self {
+        let inner = self.inner.map(move |mut parts| {
+            parts.tcp.nodelay = enabled;
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Serves HTTPS with the certificate chain and the private key of the PEM files.
+    ///
+    /// The certificate is used for the connections whose server name, requested
+    /// through SNI, has no certificate of its own.
+    #[cfg(feature = "tls")]
+    pub fn tls<C, K>(self, cert_path: C, key_path: K) -> Self
+    where
+        C: AsRef<Path>,
+        K: AsRef<Path>,
+    {
+        let inner = self.inner.and_then(move |mut parts| {
+            let key = tls::load_certified_key(cert_path, key_path)?;
+            parts.tls.set_default(key);
+            Ok(parts)
+        });
+        Self { inner }
+    }
+
+    /// Serves HTTPS with the certificate chain and the private key of the PEM
+    /// files to the connections that request the server name through SNI.
+    #[cfg(feature = "tls")]
+    pub fn tls_sni<C, K>(self, server_name: &str, cert_path: C, key_path: K) -> Self
+    where
+        C: AsRef<Path>,
+        K: AsRef<Path>,
+    {
         let inner = self.inner.and_then(move |mut parts| {
-            parts.error_handler = Some(handler);
+            let key = tls::load_certified_key(cert_path, key_path)?;
+            parts.tls.insert(server_name, key);
             Ok(parts)
         });
         Self { inner }
     }
 
+    /// Sets the protocols that the server offers through ALPN, in order of
+    /// preference. The default is `h2`, then `http/1.1`.
+    ///
+    /// The negotiated protocol is returned by `Request::alpn_protocol`.
+    #[cfg(feature = "tls")]
+    pub fn alpn_protocols<I, P>(self, protocols: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<Vec<u8>>,
+    {
+        let protocols = protocols.into_iter().map(Into::into).collect();
+        let inner = self.inner.map(move |mut parts| {
+            parts.tls.set_alpn_protocols(protocols);
+            parts
+        });
+        Self { inner }
+    }
+
     /// Consumes the builder and returns an HTTP server that listens to the specified
     /// address.
-    pub fn bind<A: ToSocketAddrs>(self, addr: A) -> Result<HttpServer<T>> {
-        let listener = TcpListener::bind(addr).or(Err(Error::from(FailedConnection)))?;
-        let (pool, router) = self.inner.and_then(move |parts| {
-            let pool = ThreadPool::new(parts.workers);
-            let error_handler = parts.error_handler.ok_or(Error::from(NoErrorHandler))?;
-            let router = Arc::new(Router::from(parts.routes, error_handler));
-            Ok((pool, router))
+    ///
+    /// If binding fails, the returned `FailedConnection` error has the address
+    /// as context and the underlying `io::Error` as source.
+    pub fn bind<A>(self, addr: A) -> Result<HttpServer<Req, Res>>
+    where
+        A: ToSocketAddrs + fmt::Debug,
+    {
+        self.into_server()?.also_bind(addr)
+    }
+
+    /// Sets the permissions of the socket file that `bind_unix` creates, e.g. `0o660`.
+    #[cfg(unix)]
+    #[inline]
+    pub fn unix_permissions(self, mode: u32) -> Self {
+        let inner = self.inner.map(move |mut parts| {
+            parts.unix_permissions = Some(mode);
+            parts
+        });
+        Self { inner }
+    }
+
+    /// Consumes the builder and returns an HTTP server that listens to the Unix
+    /// domain socket at the specified path.
+    ///
+    /// A stale socket file at the path is removed before binding, and the socket
+    /// file is removed when the server is dropped.
+    #[cfg(unix)]
+    pub fn bind_unix<P: AsRef<Path>>(self, path: P) -> Result<HttpServer<Req, Res>> {
+        let mode = self
+            .inner
+            .as_ref()
+            .ok()
+            .and_then(|parts| parts.unix_permissions);
+        let listener = UnixSocketListener::bind(&path, mode).map_err(|err| {
+            FailedConnection::with_context(path.as_ref().display().to_string()).with_source(err)
         })?;
+        self.listen(listener)
+    }
+
+    /// Consumes the builder and returns an HTTP server that serves the connections
+    /// accepted by the listener.
+    pub fn listen<L: Listener + 'static>(self, listener: L) -> Result<HttpServer<Req, Res>> {
+        Ok(self.into_server()?.also_listen(listener))
+    }
+
+    /// Consumes the builder and returns an HTTP server without listeners.
+    fn into_server(mut self) -> Result<HttpServer<Req, Res>> {
+        let tcp = self.tcp_options();
+        let options = self.connection_options();
+        let reporter = self
+            .inner
+            .as_mut()
+            .ok()
+            .and_then(|parts| parts.error_reporter.take());
+        #[cfg(feature = "tls")]
+        let tls = match &self.inner {
+            Ok(parts) => parts.tls.server_config()?,
+            Err(_) => None,
+        };
+        let (workers, router) = self.into_router()?;
 
         Ok(HttpServer {
-            listener,
-            pool,
-            router,
+            listeners: Vec::new(),
+            pool: ThreadPool::new(workers),
+            router: Arc::new(router),
+            tcp,
+            #[cfg(feature = "tls")]
+            tls,
+            options: Arc::new(options),
+            reporter: reporter.unwrap_or_else(|| Arc::new(super::print_error)),
+        })
+    }
+
+    /// Returns the options of the connections of the builder.
+    fn connection_options(&self) -> ConnectionOptions {
+        self.inner
+            .as_ref()
+            .map(|parts| ConnectionOptions {
+                keep_alive_timeout: parts.connection.keep_alive_timeout,
+                header_read_timeout: parts.connection.header_read_timeout,
+                max_head_size: parts.connection.max_head_size,
+                max_body_size: parts.connection.max_body_size,
+                ..ConnectionOptions::default()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the TCP socket options of the builder.
+    fn tcp_options(&self) -> TcpOptions {
+        self.inner
+            .as_ref()
+            .map(|parts| parts.tcp.clone())
+            .unwrap_or_default()
+    }
+
+    /// Consumes the builder and returns the `Router` of its routes, error handlers
+    /// and state, together with the size of the thread pool.
+    pub(crate) fn into_router(self) -> Result<(usize, Router<Req, Res>)> {
+        self.inner.and_then(move |parts| {
+            let error_handler = parts
+                .error_handler
+                .ok_or_else(|| Error::from(NoErrorHandler::new()))?;
+            let router = Router::from(
+                parts.routes,
+                parts.websockets,
+                error_handler,
+                parts.status_handlers,
+                parts.expect_handler,
+                parts.server_name,
+                parts.state,
+            );
+            Ok((parts.workers, router))
         })
     }
 }
 
-struct Parts<T> {
+struct Parts<Req, Res> {
     workers: usize,
-    routes: HashMap<(Uri, Method), RequestHandler<T>>,
-    error_handler: Option<RequestHandler<T>>,
+    routes: HashMap<(Uri, Method), RequestHandler<Req, Res>>,
+    websockets: HashMap<Uri, WebSocketHandler<Req>>,
+    error_handler: Option<ErrorHandler<Req, Res>>,
+    status_handlers: HashMap<StatusCode, ErrorHandler<Req, Res>>,
+    expect_handler: Option<ExpectHandler>,
+    error_reporter: Option<ErrorReporter>,
+    server_name: Option<HeaderValue>,
+    state: StateMap,
+    tcp: TcpOptions,
+    connection: ConnectionOptions,
+    #[cfg(feature = "tls")]
+    tls: TlsConfig,
+    #[cfg(unix)]
+    unix_permissions: Option<u32>,
 }
 
-impl<T> Default for Parts<T> {
+impl<Req, Res> Default for Parts<Req, Res> {
     #[inline]
     fn default() -> Self {
         Parts {
             workers: 1,
             routes: HashMap::new(),
+            websockets: HashMap::new(),
             error_handler: None,
+            status_handlers: HashMap::new(),
+            expect_handler: None,
+            error_reporter: None,
+            server_name: Some(HeaderValue::from_static("tiny-http")),
+            state: StateMap::new(),
+            tcp: TcpOptions::default(),
+            connection: ConnectionOptions::default(),
+            #[cfg(feature = "tls")]
+            tls: TlsConfig::default(),
+            #[cfg(unix)]
+            unix_permissions: None,
         }
     }
 }
@@ -0,0 +1,161 @@
+use std::io::{self, BufRead, BufReader, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use super::Transport;
+use crate::error::{Error, ErrorKind, RequestTimeout};
+use crate::http::decode;
+
+/// How often a connection that waits for a request checks whether the
+/// server is shutting down.
+pub(crate) const SHUTDOWN_POLL: Duration = Duration::from_millis(200);
+
+/// The options of the connections that the server serves, shared with the
+/// threads that serve them.
+#[derive(Debug)]
+pub(crate) struct ConnectionOptions {
+    /// How long a connection waits for the next request before it is closed.
+    pub keep_alive_timeout: Option<Duration>,
+
+    /// How long the head of a request may take to arrive, and every read of
+    /// its body may block.
+    pub header_read_timeout: Option<Duration>,
+
+    /// The size of the largest head of a request, or header block over
+    /// HTTP/2.
+    pub max_head_size: usize,
+
+    /// The size of the largest body of a request.
+    pub max_body_size: usize,
+
+    /// Set once the server shuts down, so that connections are closed
+    /// between requests.
+    pub shutdown: AtomicBool,
+}
+
+impl Default for ConnectionOptions {
+    #[inline]
+    fn default() -> Self {
+        ConnectionOptions {
+            keep_alive_timeout: Some(Duration::from_secs(5)),
+            header_read_timeout: Some(Duration::from_secs(10)),
+            max_head_size: decode::MAX_HEAD_SIZE,
+            max_body_size: decode::MAX_BODY_SIZE,
+            shutdown: AtomicBool::new(false),
+        }
+    }
+}
+
+impl ConnectionOptions {
+    /// Checks whether the server is shutting down.
+    #[inline]
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst)
+    }
+
+    /// Waits until the client sends the next bytes of the connection.
+    ///
+    /// Returns `false` if the client closes the connection, or leaves it idle
+    /// for the keep-alive timeout, or if the server shuts down meanwhile.
+    pub fn wait_for_request(&self, reader: &mut BufReader<Box<dyn Transport>>) -> bool {
+        if !reader.buffer().is_empty() {
+            return true;
+        }
+        let deadline = self
+            .keep_alive_timeout
+            .map(|timeout| Instant::now() + timeout);
+        loop {
+            if self.is_shutting_down() {
+                return false;
+            }
+            let timeout = match deadline {
+                Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    Some(left) if !left.is_zero() => left.min(SHUTDOWN_POLL),
+                    _ => return false,
+                },
+                None => SHUTDOWN_POLL,
+            };
+            if reader.get_ref().set_read_timeout(Some(timeout)).is_err() {
+                return false;
+            }
+            match reader.fill_buf() {
+                Ok(buf) => return !buf.is_empty(),
+                Err(err) if is_timeout(&err) || err.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => return false,
+            }
+        }
+    }
+
+    /// Returns a reader of the head of the request that the client has
+    /// started to send, which fails once the head takes longer than the
+    /// header read timeout.
+    pub fn head_reader<'a>(&self, reader: &'a mut BufReader<Box<dyn Transport>>) -> HeadReader<'a> {
+        HeadReader {
+            deadline: self
+                .header_read_timeout
+                .map(|timeout| Instant::now() + timeout),
+            reader,
+        }
+    }
+
+    /// Sets the timeout of the reads of the body of a request, whose head
+    /// has been read.
+    #[inline]
+    pub fn set_body_timeout(&self, reader: &BufReader<Box<dyn Transport>>) -> io::Result<()> {
+        reader.get_ref().set_read_timeout(self.header_read_timeout)
+    }
+}
+
+/// A reader of the head of a request, that fails with `TimedOut` once its
+/// deadline has passed, however slowly the client sends the bytes.
+pub(crate) struct HeadReader<'a> {
+    reader: &'a mut BufReader<Box<dyn Transport>>,
+    deadline: Option<Instant>,
+}
+
+impl Read for HeadReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for HeadReader<'_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if let Some(deadline) = self.deadline.filter(|_| self.reader.buffer().is_empty()) {
+            match deadline.checked_duration_since(Instant::now()) {
+                Some(left) if !left.is_zero() => {
+                    self.reader.get_ref().set_read_timeout(Some(left))?
+                }
+                _ => return Err(io::ErrorKind::TimedOut.into()),
+            }
+        }
+        self.reader.fill_buf()
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt)
+    }
+}
+
+/// Reports a read of a request that timed out, which some platforms report
+/// as `WouldBlock`, with `RequestTimeout`, i.e. `408 Request Timeout`.
+pub(crate) fn timed_out(err: Error) -> Error {
+    match err.kind() {
+        ErrorKind::Io(io) if is_timeout(io) => RequestTimeout::new().with_source(err).into(),
+        _ => err,
+    }
+}
+
+/// Checks whether the I/O error is that of a read that timed out.
+#[inline]
+pub(crate) fn is_timeout(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
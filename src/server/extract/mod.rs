@@ -0,0 +1,485 @@
+mod path;
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::result;
+
+use serde::de::DeserializeOwned;
+
+use crate::error::Result;
+use crate::http::{Body, HeaderMap, HeaderName, Method, Request, Response, StatusCode, Uri};
+use path::PathDeserializer;
+
+/// The shared application state of the server, keyed by type.
+pub(crate) type StateMap = HashMap<TypeId, Box<dyn Any + Send + Sync>>;
+
+/// The parameters captured from the request path by the matched route.
+///
+/// A route segment of the form `:name` captures the corresponding segment
+/// of the request path, percent-decoded.
+#[derive(Debug, Clone, Default)]
+pub struct Params {
+    inner: Vec<(String, String)>,
+}
+
+impl Params {
+    /// Creates an empty set of path parameters.
+    #[inline]
+    pub fn new() -> Self {
+        Params::default()
+    }
+
+    /// Appends a captured parameter.
+    #[inline]
+    pub(crate) fn push(&mut self, name: String, value: String) {
+        self.inner.push((name, value));
+    }
+
+    /// Returns the value of the parameter with the specified name.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.inner
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns an iterator over the name-value pairs, in route order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.inner.iter().map(|(n, v)| (n.as_str(), v.as_str()))
+    }
+}
+
+/// The information the router has gathered about a request, besides the
+/// request itself.
+pub struct RequestContext<'a> {
+    params: &'a Params,
+    state: &'a StateMap,
+}
+
+impl<'a> RequestContext<'a> {
+    /// Creates a new context from path parameters and application state.
+    #[inline]
+    pub(crate) fn new(params: &'a Params, state: &'a StateMap) -> Self {
+        RequestContext { params, state }
+    }
+
+    /// Returns the parameters captured from the request path.
+    #[inline]
+    pub fn params(&self) -> &Params {
+        self.params
+    }
+
+    /// Returns a reference to the application state of type `S`, if registered.
+    pub fn state<S: 'static>(&self) -> Option<&S> {
+        self.state
+            .get(&TypeId::of::<S>())
+            .and_then(|state| state.downcast_ref::<S>())
+    }
+}
+
+/// The reason a request could not be turned into a handler's arguments.
+///
+/// A rejection is answered with its status code and message, without calling
+/// the handler.
+#[derive(Debug)]
+pub struct Rejection {
+    status: StatusCode,
+    message: String,
+}
+
+impl Rejection {
+    /// Creates a new rejection with a status code and a message for the client.
+    pub fn new<M: Into<String>>(status: StatusCode, message: M) -> Self {
+        Rejection {
+            status,
+            message: message.into(),
+        }
+    }
+
+    /// Returns the status code of the response the rejection is answered with.
+    #[inline]
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Returns the message that is sent as the response body.
+    #[inline]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Consumes the rejection and builds the response that is sent to the client.
+    pub fn into_response<T>(self) -> Result<Response<T>>
+    where
+        T: Body + From<String>,
+    {
+        Response::builder()
+            .with_status(self.status)
+            .with_header("Content-Type", b"text/plain")
+            .with_body(T::from(self.message))
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.status, self.message)
+    }
+}
+
+/// Types that can be extracted from a request, to be passed as arguments
+/// to a handler.
+pub trait FromRequest<T>: Sized {
+    /// Extracts the value from the request and the context of the router.
+    fn from_request(
+        request: &Request<T>,
+        context: &RequestContext<'_>,
+    ) -> result::Result<Self, Rejection>;
+}
+
+impl<T> FromRequest<T> for Method {
+    fn from_request(
+        request: &Request<T>,
+        _: &RequestContext<'_>,
+    ) -> result::Result<Self, Rejection> {
+        Ok(request.method().clone())
+    }
+}
+
+impl<T> FromRequest<T> for Uri {
+    fn from_request(
+        request: &Request<T>,
+        _: &RequestContext<'_>,
+    ) -> result::Result<Self, Rejection> {
+        Ok(request.uri().clone())
+    }
+}
+
+impl<T> FromRequest<T> for HeaderMap {
+    fn from_request(
+        request: &Request<T>,
+        _: &RequestContext<'_>,
+    ) -> result::Result<Self, Rejection> {
+        Ok(request.headers().clone())
+    }
+}
+
+impl<T> FromRequest<T> for Params {
+    fn from_request(
+        _: &Request<T>,
+        context: &RequestContext<'_>,
+    ) -> result::Result<Self, Rejection> {
+        Ok(context.params().clone())
+    }
+}
+
+impl<T, E> FromRequest<T> for Option<E>
+where
+    E: FromRequest<T>,
+{
+    fn from_request(
+        request: &Request<T>,
+        context: &RequestContext<'_>,
+    ) -> result::Result<Self, Rejection> {
+        Ok(E::from_request(request, context).ok())
+    }
+}
+
+macro_rules! extractors {
+    (
+        $(
+            $(#[$docs:meta])*
+            $name:ident;
+        )+
+    ) => {
+        $(
+            $(#[$docs])*
+            #[derive(Debug, Clone, Copy, Default)]
+            pub struct $name<T>(pub T);
+
+            impl<T> $name<T> {
+                /// Consumes the extractor and returns the extracted value.
+                #[inline]
+                pub fn into_inner(self) -> T {
+                    self.0
+                }
+            }
+
+            impl<T> Deref for $name<T> {
+                type Target = T;
+
+                #[inline]
+                fn deref(&self) -> &T {
+                    &self.0
+                }
+            }
+        )+
+    }
+}
+
+extractors! {
+    /// Extracts the path parameters of the matched route.
+    ///
+    /// The parameters are deserialized into `T`, which can be a single value
+    /// when the route has one parameter, a tuple, or a struct with a field for
+    /// each parameter. Fails with `400 Bad Request`.
+    Path;
+
+    /// Extracts the query string of the request URI.
+    ///
+    /// Fails with `400 Bad Request`.
+    Query;
+
+    /// Extracts a JSON request body.
+    ///
+    /// Fails with `415 Unsupported Media Type` if the `Content-Type` is not JSON,
+    /// with `400 Bad Request` if the body is not valid JSON, and with
+    /// `422 Unprocessable Entity` if it does not match `T`.
+    Json;
+
+    /// Extracts a URL-encoded form request body.
+    ///
+    /// Fails with `415 Unsupported Media Type` if the `Content-Type` is not
+    /// `application/x-www-form-urlencoded`, and with `422 Unprocessable Entity`
+    /// if the body does not match `T`.
+    Form;
+
+    /// Extracts a clone of the application state registered on the server
+    /// builder with `state`.
+    ///
+    /// Fails with `500 Internal Server Error` if no state of type `T` is registered.
+    State;
+}
+
+impl<T, P> FromRequest<T> for Path<P>
+where
+    P: DeserializeOwned,
+{
+    fn from_request(
+        _: &Request<T>,
+        context: &RequestContext<'_>,
+    ) -> result::Result<Self, Rejection> {
+        P::deserialize(PathDeserializer::new(context.params()))
+            .map(Path)
+            .map_err(|err| {
+                Rejection::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid path parameters: {err}"),
+                )
+            })
+    }
+}
+
+impl<T, Q> FromRequest<T> for Query<Q>
+where
+    Q: DeserializeOwned,
+{
+    fn from_request(
+        request: &Request<T>,
+        _: &RequestContext<'_>,
+    ) -> result::Result<Self, Rejection> {
+        let query = request.uri().query().unwrap_or_default();
+        serde_urlencoded::from_str(query).map(Query).map_err(|err| {
+            Rejection::new(
+                StatusCode::BAD_REQUEST,
+                format!("Invalid query string: {err}"),
+            )
+        })
+    }
+}
+
+impl<T, J> FromRequest<T> for Json<J>
+where
+    T: AsRef<[u8]>,
+    J: DeserializeOwned,
+{
+    fn from_request(
+        request: &Request<T>,
+        _: &RequestContext<'_>,
+    ) -> result::Result<Self, Rejection> {
+        if !has_content_type(request, |mime| {
+            mime == "application/json" || mime.ends_with("+json")
+        }) {
+            return Err(Rejection::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "Expected request with `Content-Type: application/json`",
+            ));
+        }
+
+        serde_json::from_slice(request.body().as_ref())
+            .map(Json)
+            .map_err(|err| {
+                let status = match err.classify() {
+                    serde_json::error::Category::Data => StatusCode::UNPROCESSABLE_ENTITY,
+                    _ => StatusCode::BAD_REQUEST,
+                };
+                Rejection::new(status, format!("Invalid JSON body: {err}"))
+            })
+    }
+}
+
+impl<T, F> FromRequest<T> for Form<F>
+where
+    T: AsRef<[u8]>,
+    F: DeserializeOwned,
+{
+    fn from_request(
+        request: &Request<T>,
+        _: &RequestContext<'_>,
+    ) -> result::Result<Self, Rejection> {
+        if !has_content_type(request, |mime| mime == "application/x-www-form-urlencoded") {
+            return Err(Rejection::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "Expected request with `Content-Type: application/x-www-form-urlencoded`",
+            ));
+        }
+
+        serde_urlencoded::from_bytes(request.body().as_ref())
+            .map(Form)
+            .map_err(|err| {
+                Rejection::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    format!("Invalid form body: {err}"),
+                )
+            })
+    }
+}
+
+impl<T, S> FromRequest<T> for State<S>
+where
+    S: Clone + 'static,
+{
+    fn from_request(
+        _: &Request<T>,
+        context: &RequestContext<'_>,
+    ) -> result::Result<Self, Rejection> {
+        context.state::<S>().cloned().map(State).ok_or_else(|| {
+            Rejection::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Requested application state is not registered",
+            )
+        })
+    }
+}
+
+/// Checks whether the mime type of the request's `Content-Type` satisfies
+/// the predicate, ignoring its parameters and case.
+fn has_content_type<T, P>(request: &Request<T>, predicate: P) -> bool
+where
+    P: Fn(&str) -> bool,
+{
+    request
+        .headers()
+        .get(&HeaderName::CONTENT_TYPE)
+        .and_then(|value| std::str::from_utf8(value.as_bytes()).ok())
+        .map(|value| {
+            let mime = value.split(';').next().unwrap_or_default();
+            predicate(&mime.trim().to_ascii_lowercase())
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    fn request(uri: &str) -> Request<()> {
+        Request::builder().with_uri(uri).with_body(()).unwrap()
+    }
+
+    fn params(pairs: &[(&str, &str)]) -> Params {
+        let mut params = Params::new();
+        for (name, value) in pairs {
+            params.push(name.to_string(), value.to_string());
+        }
+        params
+    }
+
+    fn path<P: DeserializeOwned>(pairs: &[(&str, &str)]) -> result::Result<P, Rejection> {
+        let params = params(pairs);
+        let state = StateMap::new();
+        let context = RequestContext::new(&params, &state);
+        Path::<P>::from_request(&request("/"), &context).map(Path::into_inner)
+    }
+
+    fn query<Q: DeserializeOwned>(uri: &str) -> result::Result<Q, Rejection> {
+        let (params, state) = (Params::new(), StateMap::new());
+        let context = RequestContext::new(&params, &state);
+        Query::<Q>::from_request(&request(uri), &context).map(Query::into_inner)
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Item {
+        user: String,
+        id: u32,
+    }
+
+    #[test]
+    fn extracts_path_parameters() {
+        assert_eq!(path::<String>(&[("name", "a b")]).unwrap(), "a b");
+        assert_eq!(path::<u32>(&[("id", "42")]).unwrap(), 42);
+        assert_eq!(
+            path::<(String, u32)>(&[("user", "ann"), ("id", "7")]).unwrap(),
+            ("ann".to_string(), 7)
+        );
+        assert_eq!(
+            path::<Item>(&[("id", "7"), ("user", "ann")]).unwrap(),
+            Item {
+                user: "ann".to_string(),
+                id: 7
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_missing_path_parameters() {
+        let rejection = path::<String>(&[]).unwrap_err();
+        assert_eq!(rejection.status(), StatusCode::BAD_REQUEST);
+        assert!(rejection
+            .message()
+            .contains("expected 1 path parameter, found 0"));
+
+        let rejection = path::<Item>(&[("user", "ann")]).unwrap_err();
+        assert_eq!(rejection.status(), StatusCode::BAD_REQUEST);
+        assert!(rejection.message().contains("missing field `id`"));
+    }
+
+    #[test]
+    fn rejects_invalid_path_parameters() {
+        let rejection = path::<u32>(&[("id", "-1")]).unwrap_err();
+        assert_eq!(rejection.status(), StatusCode::BAD_REQUEST);
+        assert!(rejection.message().contains("cannot parse `-1`"));
+
+        let rejection = path::<Item>(&[("user", "ann"), ("id", "x")]).unwrap_err();
+        assert_eq!(rejection.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn extracts_and_decodes_the_query() {
+        assert_eq!(
+            query::<Item>("/items?user=a%20b+c%2F&id=7").unwrap(),
+            Item {
+                user: "a b c/".to_string(),
+                id: 7
+            }
+        );
+        assert_eq!(
+            query::<HashMap<String, String>>("/items").unwrap(),
+            HashMap::new()
+        );
+    }
+
+    #[test]
+    fn rejects_missing_and_invalid_queries() {
+        let rejection = query::<Item>("/items?user=ann").unwrap_err();
+        assert_eq!(rejection.status(), StatusCode::BAD_REQUEST);
+        assert!(rejection.message().contains("missing field `id`"));
+
+        let rejection = query::<Item>("/items?user=ann&id=x").unwrap_err();
+        assert_eq!(rejection.status(), StatusCode::BAD_REQUEST);
+        assert!(rejection.message().starts_with("Invalid query string"));
+    }
+}
@@ -0,0 +1,47 @@
+use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use bytes::Bytes;
+
+use crate::error::Result;
+use crate::server::HttpServer;
+
+/// A handle to an HTTP server that runs on a background thread.
+///
+/// It is returned by `HttpServer::spawn`. Dropping the handle does not stop
+/// the server; call `shutdown` to stop it.
+pub struct ServerHandle<Req = Bytes, Res = Req> {
+    server: Arc<HttpServer<Req, Res>>,
+    thread: JoinHandle<()>,
+}
+
+impl<Req: 'static, Res: 'static> ServerHandle<Req, Res> {
+    /// Creates a new handle from the server and the thread that runs it.
+    #[inline]
+    pub(crate) fn new(server: Arc<HttpServer<Req, Res>>, thread: JoinHandle<()>) -> Self {
+        ServerHandle { server, thread }
+    }
+
+    /// Returns the socket address of the first listener of the server.
+    #[inline]
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.server.local_addr()
+    }
+
+    /// Stops the server and waits until it has stopped.
+    ///
+    /// The listeners stop accepting connections, and the connections that
+    /// have already been accepted are closed once the requests that they have
+    /// started are answered, before the thread pool shuts down.
+    pub fn shutdown(self) {
+        self.server.options.shutdown.store(true, Ordering::SeqCst);
+        for (listener, _) in &self.server.listeners {
+            if let Err(err) = listener.unblock() {
+                (self.server.reporter)(&super::listener_error(listener.as_ref(), err));
+            }
+        }
+        self.thread.join().expect("Failed to join server thread");
+    }
+}
@@ -0,0 +1,84 @@
+use crate::error::Result;
+use crate::http::{Body, Request, Response};
+use crate::server::extract::{FromRequest, RequestContext};
+use crate::server::RequestHandler;
+
+/// Functions that can handle requests.
+///
+/// A handler is either a function that takes a reference to the `Request`,
+/// or a function whose arguments are all extractors, i.e. implement
+/// `FromRequest`. The arguments are extracted in order, and if one of them
+/// fails, the request is answered with the extractor's `Rejection` and the
+/// function is not called.
+///
+/// The `Args` type parameter only disambiguates between the implementations
+/// for functions of different arguments.
+pub trait Handler<Req, Res, Args>: Send + Sync + 'static {
+    /// Handles the request.
+    fn call(&self, request: &Request<Req>, context: &RequestContext<'_>) -> Result<Response<Res>>;
+}
+
+/// Marker for handlers that take a reference to the whole `Request`.
+pub struct WholeRequest;
+
+impl<Req, Res, F> Handler<Req, Res, WholeRequest> for F
+where
+    F: Fn(&Request<Req>) -> Result<Response<Res>> + Send + Sync + 'static,
+{
+    #[inline]
+    fn call(&self, request: &Request<Req>, _: &RequestContext<'_>) -> Result<Response<Res>> {
+        self(request)
+    }
+}
+
+macro_rules! extractor_handlers {
+    ($( ($($ty:ident),*); )+) => {
+        $(
+            #[allow(non_snake_case, unused_variables)]
+            impl<Req, Res, F, $($ty,)*> Handler<Req, Res, ($($ty,)*)> for F
+            where
+                F: Fn($($ty),*) -> Result<Response<Res>> + Send + Sync + 'static,
+                Res: Body + From<String>,
+                $($ty: FromRequest<Req>,)*
+            {
+                fn call(
+                    &self,
+                    request: &Request<Req>,
+                    context: &RequestContext<'_>,
+                ) -> Result<Response<Res>> {
+                    $(
+                        let $ty = match $ty::from_request(request, context) {
+                            Ok(value) => value,
+                            Err(rejection) => return rejection.into_response(),
+                        };
+                    )*
+                    self($($ty),*)
+                }
+            }
+        )+
+    }
+}
+
+extractor_handlers! {
+    ();
+    (A1);
+    (A1, A2);
+    (A1, A2, A3);
+    (A1, A2, A3, A4);
+    (A1, A2, A3, A4, A5);
+    (A1, A2, A3, A4, A5, A6);
+    (A1, A2, A3, A4, A5, A6, A7);
+    (A1, A2, A3, A4, A5, A6, A7, A8);
+}
+
+/// Erases the argument types of a handler, so that handlers of different
+/// arguments can be stored together.
+pub(crate) fn boxed<Req, Res, H, Args>(handler: H) -> RequestHandler<Req, Res>
+where
+    Req: 'static,
+    Res: 'static,
+    H: Handler<Req, Res, Args>,
+    Args: 'static,
+{
+    Box::new(move |request, context| handler.call(request, context))
+}
@@ -0,0 +1,426 @@
+use std::collections::VecDeque;
+use std::io::{BufReader, Write};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use super::connection::{self, ConnectionOptions};
+use super::extract::RequestContext;
+use super::pool::Spawner;
+use super::router::Router;
+use super::websocket::{self, WebSocket};
+use super::Transport;
+use crate::error::{Error, PayloadTooLarge, Result};
+use crate::http::decode;
+use crate::http::upgrade::{OnUpgrade, Upgraded};
+use crate::http::{
+    Body, ConnectionInfo, Encode, FromBody, HeaderName, Method, Request, Response, Version,
+};
+
+/// The maximum number of pipelined requests that are read ahead of the
+/// response that is written next.
+const MAX_PIPELINED: usize = 16;
+
+/// Handles a request and encodes its response.
+type Task = Box<dyn FnOnce() -> Outcome + Send + 'static>;
+
+/// A response that has been encoded, ready to be written.
+struct Outcome {
+    bytes: Vec<u8>,
+    persistent: bool,
+    upgrade: Option<OnUpgrade>,
+}
+
+/// A request whose response has not been written yet.
+enum Exchange {
+    /// The response is known, e.g. for a request that could not be read.
+    Ready(Outcome),
+    /// The request is handled by a worker thread of the pool, unless the
+    /// connection needs the response before a worker has taken the request.
+    Pending {
+        task: Arc<Mutex<Option<Task>>>,
+        result: Receiver<Outcome>,
+    },
+}
+
+impl Exchange {
+    /// Returns the response, handling the request on the current thread if
+    /// no worker has taken it yet.
+    ///
+    /// Returns `None` if the worker that took the request failed to respond.
+    fn wait(self) -> Option<Outcome> {
+        match self {
+            Exchange::Ready(outcome) => Some(outcome),
+            Exchange::Pending { task, result } => match take(&task) {
+                Some(task) => Some(task()),
+                None => result.recv().ok(),
+            },
+        }
+    }
+
+    /// Drops the request, unless a worker has already taken it.
+    fn cancel(self) {
+        if let Exchange::Pending { task, .. } = self {
+            drop(take(&task));
+        }
+    }
+}
+
+/// Serves an HTTP/1 connection, until either side closes it or a response
+/// hands it over to a handler.
+///
+/// The connection is closed once it has been idle for the keep-alive timeout,
+/// or between requests once the server shuts down. A request whose head or
+/// body is too slow to arrive is answered with `408 Request Timeout`.
+///
+/// Requests are read one after the other from the same `BufReader`, so the
+/// bytes of the requests that the client pipelined are never lost. While a
+/// response is pending, the requests that have already arrived are read
+/// ahead and handled by the other workers of the pool, but the responses are
+/// written in the order of the requests.
+///
+/// A request that changes how the connection is read, because it expects
+/// `100 Continue`, asks for an upgrade, tunnels with `CONNECT` or goes to a
+/// WebSocket route, is handled on its own once the responses before it have
+/// been written.
+pub(crate) fn serve<Req, Res>(
+    router: &Arc<Router<Req, Res>>,
+    spawner: &Spawner,
+    options: &ConnectionOptions,
+    mut reader: BufReader<Box<dyn Transport>>,
+    connection: ConnectionInfo,
+) where
+    Req: FromBody + 'static,
+    Res: Body + 'static,
+{
+    let mut pending = VecDeque::new();
+    let mut exclusive = None;
+    let mut reading = true;
+    loop {
+        while reading && pending.len() < MAX_PIPELINED {
+            if !pending.is_empty() && (exclusive.is_some() || reader.buffer().is_empty()) {
+                // Only the requests that have already arrived are read ahead
+                break;
+            }
+            let head = match exclusive.take() {
+                Some(head) => head,
+                None => match next_head(&mut reader, options) {
+                    Some(Ok(head)) => head,
+                    Some(Err((err, version))) => {
+                        let outcome = respond_error(router, &err, version);
+                        pending.push_back(Exchange::Ready(outcome));
+                        reading = false;
+                        break;
+                    }
+                    None => {
+                        reading = false;
+                        break;
+                    }
+                },
+            };
+            if is_exclusive(router, &head) {
+                if !pending.is_empty() {
+                    exclusive = Some(head);
+                    break;
+                }
+                match serve_exclusive(router, options, head, reader, &connection) {
+                    Some(next) => reader = next,
+                    None => return,
+                }
+                continue;
+            }
+            reading = wants_keep_alive(&head) && !options.is_shutting_down();
+            let exchange = match head.read_body_bytes(&mut reader, options.max_body_size) {
+                Ok(body) => {
+                    let router = Arc::clone(router);
+                    let connection = connection.clone();
+                    let keep_alive = reading;
+                    let task: Task = Box::new(move || {
+                        let version = *head.version();
+                        match head.with_body_bytes::<Req>(body) {
+                            Ok(mut request) => {
+                                request.set_connection(connection);
+                                respond(
+                                    &router,
+                                    router.handle_request(&request),
+                                    request.method(),
+                                    version,
+                                    keep_alive,
+                                )
+                            }
+                            Err(err) => respond_error(&router, &err, version),
+                        }
+                    });
+                    dispatch(spawner, task, !pending.is_empty())
+                }
+                Err(err) => {
+                    reading = false;
+                    let err = connection::timed_out(err);
+                    Exchange::Ready(respond_error(router, &err, *head.version()))
+                }
+            };
+            pending.push_back(exchange);
+        }
+
+        let Some(exchange) = pending.pop_front() else {
+            return;
+        };
+        let next = exchange.wait().and_then(|outcome| write(reader, outcome));
+        match next {
+            Some(next) => reader = next,
+            None => {
+                pending.into_iter().for_each(Exchange::cancel);
+                return;
+            }
+        }
+    }
+}
+
+/// Reads the head of the next request, or returns `None` once the client
+/// has closed the connection or left it idle for the keep-alive timeout, or
+/// the server shuts down.
+///
+/// A head that cannot be read fails with the version to answer the error
+/// with, which is that of the request line, or HTTP/1.0, that every client
+/// understands, if it could not be parsed.
+fn next_head(
+    reader: &mut BufReader<Box<dyn Transport>>,
+    options: &ConnectionOptions,
+) -> Option<std::result::Result<Request<()>, (Error, Version)>> {
+    if !options.wait_for_request(reader) {
+        return None;
+    }
+    let mut version = None;
+    let head = Request::read_head(
+        &mut options.head_reader(reader),
+        options.max_head_size,
+        &mut version,
+    );
+    let head = head.and_then(|head| {
+        options.set_body_timeout(reader)?;
+        Ok(head)
+    });
+    let version = version.unwrap_or(Version::HTTP_10);
+    Some(head.map_err(|err| (connection::timed_out(err), version)))
+}
+
+/// Returns a pending exchange for the request that the task handles, which
+/// a worker of the pool handles in parallel if `parallel` is set.
+fn dispatch(spawner: &Spawner, task: Task, parallel: bool) -> Exchange {
+    let task = Arc::new(Mutex::new(Some(task)));
+    let (sender, result) = mpsc::channel();
+    if parallel {
+        let task = Arc::clone(&task);
+        spawner.execute(move || {
+            if let Some(task) = take(&task) {
+                let _ = sender.send(task());
+            }
+        });
+    }
+    Exchange::Pending { task, result }
+}
+
+/// Takes the task, unless another thread has taken it.
+fn take(task: &Mutex<Option<Task>>) -> Option<Task> {
+    task.lock().unwrap_or_else(PoisonError::into_inner).take()
+}
+
+/// Checks whether the request changes how the connection is read, so that
+/// it cannot be handled while other responses are pending.
+fn is_exclusive<Req, Res>(router: &Router<Req, Res>, head: &Request<()>) -> bool {
+    *head.method() == Method::CONNECT
+        || head.headers().get(&HeaderName::UPGRADE).is_some()
+        || head.headers().get(&HeaderName::EXPECT).is_some()
+        || router.is_websocket_path(head.uri().path())
+}
+
+/// Checks whether the client asks to keep the connection open after the
+/// response, which is the default of HTTP/1.1 only.
+fn wants_keep_alive<T>(request: &Request<T>) -> bool {
+    let headers = request.headers();
+    match *request.version() {
+        Version::HTTP_11 => !headers.has_token(&HeaderName::CONNECTION, "close"),
+        Version::HTTP_10 => headers.has_token(&HeaderName::CONNECTION, "keep-alive"),
+        _ => false,
+    }
+}
+
+/// Serves a request on the current thread, with nothing else pending on
+/// the connection, and returns the connection if it serves further requests.
+fn serve_exclusive<Req, Res>(
+    router: &Router<Req, Res>,
+    options: &ConnectionOptions,
+    head: Request<()>,
+    mut reader: BufReader<Box<dyn Transport>>,
+    connection: &ConnectionInfo,
+) -> Option<BufReader<Box<dyn Transport>>>
+where
+    Req: FromBody,
+    Res: Body,
+{
+    let method = head.method().clone();
+    let version = *head.version();
+    let keep_alive = wants_keep_alive(&head) && !options.is_shutting_down();
+    let response = match read_request(router, options, head, &mut reader) {
+        Ok(mut request) => {
+            request.set_connection(connection.clone());
+            match router.get_websocket_handler(&request) {
+                Some(Ok((handler, params))) => match websocket::handshake::accept(&request) {
+                    Ok(mut response) => {
+                        router.add_default_headers(&mut response);
+                        if let Err(err) = response
+                            .write_to(reader.get_mut())
+                            .and_then(|()| reader.get_ref().set_read_timeout(None))
+                        {
+                            eprintln!("Failed to write response: {err}");
+                            return None;
+                        }
+                        let context = RequestContext::new(&params, router.state());
+                        handler(&request, &context, WebSocket::new(Upgraded::new(reader)));
+                        return None;
+                    }
+                    Err(err) => router.handle_error(&request, &err).map(|mut response| {
+                        websocket::handshake::advertise_version(&mut response, &err);
+                        response
+                    }),
+                },
+                Some(Err(err)) => router.handle_error(&request, &err),
+                None => router.handle_request(&request),
+            }
+        }
+        Err(err) => Err(connection::timed_out(err)),
+    };
+    write(
+        reader,
+        respond(router, response, &method, version, keep_alive),
+    )
+}
+
+/// Reads the body of a request whose head has been read, and sends
+/// `100 Continue` before if the client expects it and the router accepts
+/// the request.
+///
+/// HTTP/1.0 clients do not understand interim responses, so their `Expect`
+/// header is ignored, and a body that is known to be too large is rejected
+/// before `100 Continue` is sent.
+fn read_request<Req, Res>(
+    router: &Router<Req, Res>,
+    options: &ConnectionOptions,
+    head: Request<()>,
+    reader: &mut BufReader<Box<dyn Transport>>,
+) -> Result<Request<Req>>
+where
+    Req: FromBody,
+{
+    if let Some(len) = decode::content_length(head.headers())? {
+        if len > options.max_body_size as u64 {
+            return Err(PayloadTooLarge::with_context(len.to_string()).into());
+        }
+    }
+    if *head.version() == Version::HTTP_11 && router.check_expectation(&head)? {
+        Response::builder()
+            .with_status(100)
+            .with_body(())?
+            .write_to(reader.get_mut())?;
+    }
+    head.read_body(reader, options.max_body_size)
+}
+
+/// Adds the default headers of the router to the response, adapts it to
+/// the request and encodes it.
+///
+/// If the request cannot be handled, the error is answered instead.
+fn respond<Req, Res: Body>(
+    router: &Router<Req, Res>,
+    response: Result<Response<Res>>,
+    method: &Method,
+    version: Version,
+    keep_alive: bool,
+) -> Outcome {
+    match response {
+        Ok(mut response) => {
+            router.add_default_headers(&mut response);
+            encode(response, method, version, keep_alive)
+        }
+        Err(err) => respond_error(router, &err, version),
+    }
+}
+
+/// Encodes a response with the status code that corresponds to the error
+/// and no body, after which the connection is closed.
+///
+/// The response has the version of the request, since the request may not
+/// have been read far enough to tell whether the client understands a later
+/// one.
+fn respond_error<Req, Res>(router: &Router<Req, Res>, err: &Error, version: Version) -> Outcome {
+    let mut response = Response::builder()
+        .with_version(version)
+        .with_status(err.status())
+        .with_body(())
+        .expect("Guaranteed by the status code of the error.");
+    router.add_default_headers(&mut response);
+    // The response has no body, whatever the method of the request.
+    encode(response, &Method::GET, version, false)
+}
+
+/// Encodes the response for the method and the version of the request,
+/// leaving out the body that the response must not have.
+fn encode<T: Body>(
+    mut response: Response<T>,
+    method: &Method,
+    version: Version,
+    keep_alive: bool,
+) -> Outcome {
+    let persistent = response.conform_to(method, version, keep_alive);
+    let upgrade = response.take_upgrade();
+    let mut bytes = Vec::new();
+    let encoded = match response.has_body(method) {
+        true => response.encode(&mut bytes),
+        false => response.encode_head(&mut bytes),
+    };
+    match encoded {
+        Ok(()) => Outcome {
+            bytes,
+            persistent,
+            upgrade,
+        },
+        Err(err) => {
+            eprintln!("Failed to write response: {err}");
+            Outcome {
+                bytes: Vec::new(),
+                persistent: false,
+                upgrade: None,
+            }
+        }
+    }
+}
+
+/// Writes the response, and returns the connection if it serves further
+/// requests. A response with an upgrade hands the connection over to its
+/// function instead.
+fn write(
+    mut reader: BufReader<Box<dyn Transport>>,
+    outcome: Outcome,
+) -> Option<BufReader<Box<dyn Transport>>> {
+    let stream = reader.get_mut();
+    if let Err(err) = stream
+        .write_all(&outcome.bytes)
+        .and_then(|()| stream.flush())
+    {
+        eprintln!("Failed to write response: {err}");
+        return None;
+    }
+    match outcome.upgrade {
+        Some(on_upgrade) => {
+            // The connection is no longer read by the server, so reads may
+            // block as long as the protocol that takes it over needs.
+            if let Err(err) = reader.get_ref().set_read_timeout(None) {
+                eprintln!("Failed to upgrade connection: {err}");
+                return None;
+            }
+            on_upgrade(Upgraded::new(reader));
+            None
+        }
+        None if outcome.persistent => Some(reader),
+        None => None,
+    }
+}
@@ -0,0 +1,243 @@
+use std::io::{self, Read};
+
+/// The length of the header of every frame.
+pub(crate) const HEADER_LEN: usize = 9;
+
+/// The type of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FrameType {
+    Data,
+    Headers,
+    Priority,
+    RstStream,
+    Settings,
+    PushPromise,
+    Ping,
+    GoAway,
+    WindowUpdate,
+    Continuation,
+    Unknown(u8),
+}
+
+impl FrameType {
+    /// Returns the type of the value, which may be an unknown extension type.
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            0x0 => FrameType::Data,
+            0x1 => FrameType::Headers,
+            0x2 => FrameType::Priority,
+            0x3 => FrameType::RstStream,
+            0x4 => FrameType::Settings,
+            0x5 => FrameType::PushPromise,
+            0x6 => FrameType::Ping,
+            0x7 => FrameType::GoAway,
+            0x8 => FrameType::WindowUpdate,
+            0x9 => FrameType::Continuation,
+            value => FrameType::Unknown(value),
+        }
+    }
+
+    /// Returns the value of the type on the wire.
+    pub fn as_u8(self) -> u8 {
+        match self {
+            FrameType::Data => 0x0,
+            FrameType::Headers => 0x1,
+            FrameType::Priority => 0x2,
+            FrameType::RstStream => 0x3,
+            FrameType::Settings => 0x4,
+            FrameType::PushPromise => 0x5,
+            FrameType::Ping => 0x6,
+            FrameType::GoAway => 0x7,
+            FrameType::WindowUpdate => 0x8,
+            FrameType::Continuation => 0x9,
+            FrameType::Unknown(value) => value,
+        }
+    }
+}
+
+/// The flags of the frames.
+pub(crate) mod flags {
+    pub const END_STREAM: u8 = 0x1;
+    pub const ACK: u8 = 0x1;
+    pub const END_HEADERS: u8 = 0x4;
+    pub const PADDED: u8 = 0x8;
+    pub const PRIORITY: u8 = 0x20;
+}
+
+/// The error codes of `RST_STREAM` and `GOAWAY` frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ErrorCode {
+    NoError = 0x0,
+    ProtocolError = 0x1,
+    FlowControlError = 0x3,
+    StreamClosed = 0x5,
+    FrameSizeError = 0x6,
+    RefusedStream = 0x7,
+    CompressionError = 0x9,
+}
+
+/// The identifiers of the settings of a `SETTINGS` frame.
+pub(crate) mod settings {
+    pub const ENABLE_PUSH: u16 = 0x2;
+    pub const MAX_CONCURRENT_STREAMS: u16 = 0x3;
+    pub const INITIAL_WINDOW_SIZE: u16 = 0x4;
+    pub const MAX_FRAME_SIZE: u16 = 0x5;
+    pub const MAX_HEADER_LIST_SIZE: u16 = 0x6;
+}
+
+/// A frame, whose payload has been read whole.
+#[derive(Debug)]
+pub(crate) struct Frame {
+    pub kind: FrameType,
+    pub flags: u8,
+    pub stream_id: u32,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    /// Returns whether the flag is set.
+    #[inline]
+    pub fn has_flag(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+}
+
+/// The header of a frame, read before its payload so that its length can be
+/// checked.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FrameHeader {
+    pub len: usize,
+    pub kind: FrameType,
+    pub flags: u8,
+    pub stream_id: u32,
+}
+
+/// Reads the header of a frame, ignoring the reserved bit of the stream
+/// identifier.
+pub(crate) fn read_header<R: Read + ?Sized>(reader: &mut R) -> io::Result<FrameHeader> {
+    let mut head = [0_u8; HEADER_LEN];
+    reader.read_exact(&mut head)?;
+    Ok(FrameHeader {
+        len: u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize,
+        kind: FrameType::from_u8(head[3]),
+        flags: head[4],
+        stream_id: u32::from_be_bytes([head[5], head[6], head[7], head[8]]) & 0x7FFF_FFFF,
+    })
+}
+
+/// Reads the payload of the frame whose header has been read.
+pub(crate) fn read_payload<R: Read + ?Sized>(
+    reader: &mut R,
+    header: FrameHeader,
+) -> io::Result<Frame> {
+    let mut payload = vec![0_u8; header.len];
+    reader.read_exact(&mut payload)?;
+    Ok(Frame {
+        kind: header.kind,
+        flags: header.flags,
+        stream_id: header.stream_id,
+        payload,
+    })
+}
+
+/// Appends a frame to the buffer.
+pub(crate) fn encode(
+    kind: FrameType,
+    flags: u8,
+    stream_id: u32,
+    payload: &[u8],
+    buf: &mut Vec<u8>,
+) {
+    let len = (payload.len() as u32).to_be_bytes();
+    buf.extend_from_slice(&len[1..]);
+    buf.push(kind.as_u8());
+    buf.push(flags);
+    buf.extend_from_slice(&(stream_id & 0x7FFF_FFFF).to_be_bytes());
+    buf.extend_from_slice(payload);
+}
+
+/// Removes the padding of a `DATA` or `HEADERS` frame, and returns its
+/// payload without it, or `None` if the padding is longer than the payload.
+pub(crate) fn strip_padding(frame: &Frame) -> Option<&[u8]> {
+    if !frame.has_flag(flags::PADDED) {
+        return Some(&frame.payload);
+    }
+    let (&pad_len, rest) = frame.payload.split_first()?;
+    rest.len()
+        .checked_sub(pad_len as usize)
+        .map(|len| &rest[..len])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(mut bytes: &[u8]) -> Frame {
+        let header = read_header(&mut bytes).unwrap();
+        let frame = read_payload(&mut bytes, header).unwrap();
+        assert!(bytes.is_empty());
+        frame
+    }
+
+    #[test]
+    fn round_trips_frames() {
+        for kind in (0..=0xA).map(FrameType::from_u8) {
+            let mut buf = Vec::new();
+            encode(kind, flags::END_STREAM, 3, b"payload", &mut buf);
+            let frame = read(&buf);
+            assert_eq!(frame.kind, kind);
+            assert_eq!(frame.kind.as_u8(), buf[3]);
+            assert_eq!(frame.flags, flags::END_STREAM);
+            assert_eq!(frame.stream_id, 3);
+            assert_eq!(frame.payload, b"payload");
+        }
+        assert_eq!(FrameType::from_u8(0xA), FrameType::Unknown(0xA));
+    }
+
+    #[test]
+    fn encodes_the_header_on_the_wire() {
+        let mut buf = Vec::new();
+        let payload = vec![0; 0x01_02_03];
+        encode(FrameType::Data, 0, u32::MAX, &payload, &mut buf);
+        assert_eq!(buf[..HEADER_LEN], [1, 2, 3, 0, 0, 0x7F, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(buf.len(), HEADER_LEN + payload.len());
+    }
+
+    #[test]
+    fn ignores_the_reserved_bit() {
+        let frame = read(&[0, 0, 0, 0x4, 0, 0x80, 0, 0, 0]);
+        assert_eq!(frame.kind, FrameType::Settings);
+        assert_eq!(frame.stream_id, 0);
+    }
+
+    #[test]
+    fn fails_on_a_truncated_frame() {
+        let mut buf = Vec::new();
+        encode(FrameType::Ping, 0, 0, &[0; 8], &mut buf);
+        let mut truncated = &buf[..buf.len() - 1];
+        let header = read_header(&mut truncated).unwrap();
+        let err = read_payload(&mut truncated, header).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn strips_padding() {
+        let frame = |flags, payload: &[u8]| Frame {
+            kind: FrameType::Data,
+            flags,
+            stream_id: 1,
+            payload: payload.to_vec(),
+        };
+        assert_eq!(strip_padding(&frame(0, b"\x02ab")), Some(&b"\x02ab"[..]));
+        assert_eq!(
+            strip_padding(&frame(flags::PADDED, b"\x02abcd")),
+            Some(&b"ab"[..])
+        );
+        assert_eq!(
+            strip_padding(&frame(flags::PADDED, b"\x02ab")),
+            Some(&b""[..])
+        );
+        assert_eq!(strip_padding(&frame(flags::PADDED, b"\x03ab")), None);
+        assert_eq!(strip_padding(&frame(flags::PADDED, b"")), None);
+    }
+}
@@ -0,0 +1,600 @@
+use std::collections::VecDeque;
+
+use super::huffman;
+
+/// The static table of HPACK, whose indices start at 1.
+const STATIC_TABLE: [(&[u8], &[u8]); 61] = [
+    (b":authority", b""),
+    (b":method", b"GET"),
+    (b":method", b"POST"),
+    (b":path", b"/"),
+    (b":path", b"/index.html"),
+    (b":scheme", b"http"),
+    (b":scheme", b"https"),
+    (b":status", b"200"),
+    (b":status", b"204"),
+    (b":status", b"206"),
+    (b":status", b"304"),
+    (b":status", b"400"),
+    (b":status", b"404"),
+    (b":status", b"500"),
+    (b"accept-charset", b""),
+    (b"accept-encoding", b"gzip, deflate"),
+    (b"accept-language", b""),
+    (b"accept-ranges", b""),
+    (b"accept", b""),
+    (b"access-control-allow-origin", b""),
+    (b"age", b""),
+    (b"allow", b""),
+    (b"authorization", b""),
+    (b"cache-control", b""),
+    (b"content-disposition", b""),
+    (b"content-encoding", b""),
+    (b"content-language", b""),
+    (b"content-length", b""),
+    (b"content-location", b""),
+    (b"content-range", b""),
+    (b"content-type", b""),
+    (b"cookie", b""),
+    (b"date", b""),
+    (b"etag", b""),
+    (b"expect", b""),
+    (b"expires", b""),
+    (b"from", b""),
+    (b"host", b""),
+    (b"if-match", b""),
+    (b"if-modified-since", b""),
+    (b"if-none-match", b""),
+    (b"if-range", b""),
+    (b"if-unmodified-since", b""),
+    (b"last-modified", b""),
+    (b"link", b""),
+    (b"location", b""),
+    (b"max-forwards", b""),
+    (b"proxy-authenticate", b""),
+    (b"proxy-authorization", b""),
+    (b"range", b""),
+    (b"referer", b""),
+    (b"refresh", b""),
+    (b"retry-after", b""),
+    (b"server", b""),
+    (b"set-cookie", b""),
+    (b"strict-transport-security", b""),
+    (b"transfer-encoding", b""),
+    (b"user-agent", b""),
+    (b"vary", b""),
+    (b"via", b""),
+    (b"www-authenticate", b""),
+];
+
+/// The overhead of an entry of the dynamic table, that is added to the
+/// length of its name and value to compute its size.
+const ENTRY_OVERHEAD: usize = 32;
+
+/// A header field, as a name and a value.
+pub(crate) type Field = (Vec<u8>, Vec<u8>);
+
+/// The error of a header block that cannot be decoded, which is a
+/// connection error of type `COMPRESSION_ERROR`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct DecodeError;
+
+/// A decoder of header blocks, which keeps the dynamic table that the
+/// encoder of the peer fills.
+pub(crate) struct Decoder {
+    table: VecDeque<Field>,
+    size: usize,
+    max_size: usize,
+    limit: usize,
+}
+
+impl Decoder {
+    /// Creates a decoder whose dynamic table may grow up to the limit, that
+    /// is advertised with `SETTINGS_HEADER_TABLE_SIZE`.
+    pub fn new(limit: usize) -> Self {
+        Decoder {
+            table: VecDeque::new(),
+            size: 0,
+            max_size: limit,
+            limit,
+        }
+    }
+
+    /// Decodes a whole header block into its fields, in order.
+    ///
+    /// Fails if the total size of the fields, computed as for the dynamic
+    /// table, exceeds `max_list_size`.
+    pub fn decode(
+        &mut self,
+        mut block: &[u8],
+        max_list_size: usize,
+    ) -> Result<Vec<Field>, DecodeError> {
+        let mut fields = Vec::new();
+        let mut list_size = 0;
+        let mut fields_started = false;
+        while let Some(&first) = block.first() {
+            let field = if first & 0x80 != 0 {
+                let index = decode_integer(&mut block, 7)?;
+                self.get(index)?
+            } else if first & 0xC0 == 0x40 {
+                let field = self.decode_literal(&mut block, 6)?;
+                self.insert(field.clone());
+                field
+            } else if first & 0xE0 == 0x20 {
+                // Size updates are only allowed at the start of a block.
+                if fields_started {
+                    return Err(DecodeError);
+                }
+                let size = decode_integer(&mut block, 5)?;
+                if size > self.limit {
+                    return Err(DecodeError);
+                }
+                self.max_size = size;
+                self.evict(0);
+                continue;
+            } else {
+                self.decode_literal(&mut block, 4)?
+            };
+            fields_started = true;
+            list_size += field.0.len() + field.1.len() + ENTRY_OVERHEAD;
+            if list_size > max_list_size {
+                return Err(DecodeError);
+            }
+            fields.push(field);
+        }
+        Ok(fields)
+    }
+
+    /// Returns the field at the index of the static and dynamic tables.
+    fn get(&self, index: usize) -> Result<Field, DecodeError> {
+        match index {
+            0 => Err(DecodeError),
+            1..=61 => {
+                let (name, value) = STATIC_TABLE[index - 1];
+                Ok((name.to_vec(), value.to_vec()))
+            }
+            _ => self.table.get(index - 62).cloned().ok_or(DecodeError),
+        }
+    }
+
+    /// Decodes a literal field, whose name is either indexed or literal.
+    fn decode_literal(&self, block: &mut &[u8], prefix: u8) -> Result<Field, DecodeError> {
+        let name = match decode_integer(block, prefix)? {
+            0 => decode_string(block)?,
+            index => self.get(index)?.0,
+        };
+        let value = decode_string(block)?;
+        Ok((name, value))
+    }
+
+    /// Inserts a field at the front of the dynamic table, evicting the oldest
+    /// fields to make room for it.
+    fn insert(&mut self, field: Field) {
+        let size = field.0.len() + field.1.len() + ENTRY_OVERHEAD;
+        self.evict(size);
+        if size <= self.max_size {
+            self.size += size;
+            self.table.push_front(field);
+        }
+    }
+
+    /// Evicts the oldest fields until the size, plus the room that is needed,
+    /// fits in the maximum size.
+    fn evict(&mut self, room: usize) {
+        while self.size + room > self.max_size {
+            match self.table.pop_back() {
+                Some((name, value)) => self.size -= name.len() + value.len() + ENTRY_OVERHEAD,
+                None => break,
+            }
+        }
+    }
+}
+
+/// Encodes header blocks without the dynamic table, so that the decoder of
+/// the peer needs no state for them.
+///
+/// Names are looked up in the static table, and values are Huffman-encoded
+/// when that makes them shorter.
+pub(crate) fn encode<'a, I>(fields: I, buf: &mut Vec<u8>)
+where
+    I: IntoIterator<Item = (&'a [u8], &'a [u8])>,
+{
+    for (name, value) in fields {
+        let indexed = STATIC_TABLE
+            .iter()
+            .position(|&(n, v)| n == name && v == value && !v.is_empty());
+        if let Some(index) = indexed {
+            encode_integer(index + 1, 7, 0x80, buf);
+            continue;
+        }
+        match STATIC_TABLE.iter().position(|&(n, _)| n == name) {
+            // A literal field without indexing, with an indexed name.
+            Some(index) => encode_integer(index + 1, 4, 0x00, buf),
+            None => {
+                buf.push(0x00);
+                encode_string(name, buf);
+            }
+        }
+        encode_string(value, buf);
+    }
+}
+
+/// Decodes an integer whose first byte has the prefix of the number of bits.
+fn decode_integer(block: &mut &[u8], prefix: u8) -> Result<usize, DecodeError> {
+    let (&first, rest) = block.split_first().ok_or(DecodeError)?;
+    *block = rest;
+    let max = (1_usize << prefix) - 1;
+    let mut value = usize::from(first) & max;
+    if value < max {
+        return Ok(value);
+    }
+    let mut shift = 0;
+    loop {
+        let (&byte, rest) = block.split_first().ok_or(DecodeError)?;
+        *block = rest;
+        // Integers are limited to 28 bits, which is plenty for any length.
+        if shift > 21 {
+            return Err(DecodeError);
+        }
+        value += usize::from(byte & 0x7F) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+/// Decodes a string literal, that may be Huffman-encoded.
+fn decode_string(block: &mut &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let huffman = block.first().ok_or(DecodeError)? & 0x80 != 0;
+    let len = decode_integer(block, 7)?;
+    if len > block.len() {
+        return Err(DecodeError);
+    }
+    let (data, rest) = block.split_at(len);
+    *block = rest;
+    match huffman {
+        true => huffman::decode(data).ok_or(DecodeError),
+        false => Ok(data.to_vec()),
+    }
+}
+
+/// Encodes an integer with the prefix of the number of bits, after the flags
+/// of the first byte.
+fn encode_integer(value: usize, prefix: u8, flags: u8, buf: &mut Vec<u8>) {
+    let max = (1_usize << prefix) - 1;
+    if value < max {
+        buf.push(flags | value as u8);
+        return;
+    }
+    buf.push(flags | max as u8);
+    let mut value = value - max;
+    while value >= 0x80 {
+        buf.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Encodes a string literal, Huffman-encoded if that makes it shorter.
+fn encode_string(value: &[u8], buf: &mut Vec<u8>) {
+    let len = huffman::encoded_len(value);
+    if len < value.len() {
+        encode_integer(len, 7, 0x80, buf);
+        huffman::encode(value, buf);
+    } else {
+        encode_integer(value.len(), 7, 0x00, buf);
+        buf.extend_from_slice(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! The examples of RFC 7541, Appendix C.
+
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        let digits: Vec<u8> = s.bytes().filter(u8::is_ascii_hexdigit).collect();
+        digits
+            .chunks(2)
+            .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap())
+            .collect()
+    }
+
+    fn fields(fields: &[(&str, &str)]) -> Vec<Field> {
+        fields
+            .iter()
+            .map(|(name, value)| (name.as_bytes().to_vec(), value.as_bytes().to_vec()))
+            .collect()
+    }
+
+    /// Decodes the block, and checks its fields, then the dynamic table and
+    /// its size.
+    fn check(
+        decoder: &mut Decoder,
+        block: &str,
+        expected: &[(&str, &str)],
+        table: &[(&str, &str)],
+        size: usize,
+    ) {
+        assert_eq!(
+            decoder.decode(&hex(block), usize::MAX),
+            Ok(fields(expected))
+        );
+        assert_eq!(decoder.table, fields(table));
+        assert_eq!(decoder.size, size);
+    }
+
+    #[test]
+    fn integers() {
+        // C.1.1 to C.1.3
+        for (value, prefix, encoded) in [(10, 5, "0a"), (1337, 5, "1f9a0a"), (42, 8, "2a")] {
+            let mut buf = Vec::new();
+            encode_integer(value, prefix, 0, &mut buf);
+            assert_eq!(buf, hex(encoded));
+            assert_eq!(decode_integer(&mut &buf[..], prefix), Ok(value));
+        }
+        assert_eq!(decode_integer(&mut &hex("1fff")[..], 5), Err(DecodeError));
+        assert_eq!(
+            decode_integer(&mut &hex("1fffffffff0f")[..], 5),
+            Err(DecodeError)
+        );
+    }
+
+    #[test]
+    fn header_field_representations() {
+        // C.2.1
+        let mut decoder = Decoder::new(4_096);
+        check(
+            &mut decoder,
+            "400a 6375 7374 6f6d 2d6b 6579 0d63 7573 746f 6d2d 6865 6164 6572",
+            &[("custom-key", "custom-header")],
+            &[("custom-key", "custom-header")],
+            55,
+        );
+        // C.2.2
+        let mut decoder = Decoder::new(4_096);
+        check(
+            &mut decoder,
+            "040c 2f73 616d 706c 652f 7061 7468",
+            &[(":path", "/sample/path")],
+            &[],
+            0,
+        );
+        // C.2.3
+        let mut decoder = Decoder::new(4_096);
+        check(
+            &mut decoder,
+            "1008 7061 7373 776f 7264 0673 6563 7265 74",
+            &[("password", "secret")],
+            &[],
+            0,
+        );
+        // C.2.4
+        let mut decoder = Decoder::new(4_096);
+        check(&mut decoder, "82", &[(":method", "GET")], &[], 0);
+    }
+
+    /// Checks the requests of C.3 or C.4, which only differ by the encoding
+    /// of their strings.
+    fn requests(blocks: [&str; 3]) {
+        let mut decoder = Decoder::new(4_096);
+        let first = [
+            (":method", "GET"),
+            (":scheme", "http"),
+            (":path", "/"),
+            (":authority", "www.example.com"),
+        ];
+        check(
+            &mut decoder,
+            blocks[0],
+            &first,
+            &[(":authority", "www.example.com")],
+            57,
+        );
+        let second = [
+            (":method", "GET"),
+            (":scheme", "http"),
+            (":path", "/"),
+            (":authority", "www.example.com"),
+            ("cache-control", "no-cache"),
+        ];
+        check(
+            &mut decoder,
+            blocks[1],
+            &second,
+            &[
+                ("cache-control", "no-cache"),
+                (":authority", "www.example.com"),
+            ],
+            110,
+        );
+        let third = [
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":path", "/index.html"),
+            (":authority", "www.example.com"),
+            ("custom-key", "custom-value"),
+        ];
+        check(
+            &mut decoder,
+            blocks[2],
+            &third,
+            &[
+                ("custom-key", "custom-value"),
+                ("cache-control", "no-cache"),
+                (":authority", "www.example.com"),
+            ],
+            164,
+        );
+    }
+
+    #[test]
+    fn requests_without_huffman() {
+        // C.3
+        requests([
+            "8286 8441 0f77 7777 2e65 7861 6d70 6c65 2e63 6f6d",
+            "8286 84be 5808 6e6f 2d63 6163 6865",
+            "8287 85bf 400a 6375 7374 6f6d 2d6b 6579 0c63 7573 746f 6d2d 7661 6c75 65",
+        ]);
+    }
+
+    #[test]
+    fn requests_with_huffman() {
+        // C.4
+        requests([
+            "8286 8441 8cf1 e3c2 e5f2 3a6b a0ab 90f4 ff",
+            "8286 84be 5886 a8eb 1064 9cbf",
+            "8287 85bf 4088 25a8 49e9 5ba9 7d7f 8925 a849 e95b b8e8 b4bf",
+        ]);
+    }
+
+    /// Checks the responses of C.5 or C.6, which evict entries of a dynamic
+    /// table of 256 bytes.
+    fn responses(blocks: [&str; 3]) {
+        let mut decoder = Decoder::new(256);
+        let date = ("date", "Mon, 21 Oct 2013 20:13:21 GMT");
+        let location = ("location", "https://www.example.com");
+        check(
+            &mut decoder,
+            blocks[0],
+            &[
+                (":status", "302"),
+                ("cache-control", "private"),
+                date,
+                location,
+            ],
+            &[
+                location,
+                date,
+                ("cache-control", "private"),
+                (":status", "302"),
+            ],
+            222,
+        );
+        check(
+            &mut decoder,
+            blocks[1],
+            &[
+                (":status", "307"),
+                ("cache-control", "private"),
+                date,
+                location,
+            ],
+            &[
+                (":status", "307"),
+                location,
+                date,
+                ("cache-control", "private"),
+            ],
+            222,
+        );
+        let date = ("date", "Mon, 21 Oct 2013 20:13:22 GMT");
+        let cookie = (
+            "set-cookie",
+            "foo=ASDJKHQKBZXOQWEOPIUAXQWEOIU; max-age=3600; version=1",
+        );
+        check(
+            &mut decoder,
+            blocks[2],
+            &[
+                (":status", "200"),
+                ("cache-control", "private"),
+                date,
+                location,
+                ("content-encoding", "gzip"),
+                cookie,
+            ],
+            &[cookie, ("content-encoding", "gzip"), date],
+            215,
+        );
+    }
+
+    #[test]
+    fn responses_without_huffman() {
+        // C.5
+        responses([
+            "4803 3330 3258 0770 7269 7661 7465 611d 4d6f 6e2c 2032 3120 4f63 7420 3230 3133 \
+             2032 303a 3133 3a32 3120 474d 546e 1768 7474 7073 3a2f 2f77 7777 2e65 7861 6d70 \
+             6c65 2e63 6f6d",
+            "4803 3330 37c1 c0bf",
+            "88c1 611d 4d6f 6e2c 2032 3120 4f63 7420 3230 3133 2032 303a 3133 3a32 3220 474d \
+             54c0 5a04 677a 6970 7738 666f 6f3d 4153 444a 4b48 514b 425a 584f 5157 454f 5049 \
+             5541 5851 5745 4f49 553b 206d 6178 2d61 6765 3d33 3630 303b 2076 6572 7369 6f6e \
+             3d31",
+        ]);
+    }
+
+    #[test]
+    fn responses_with_huffman() {
+        // C.6
+        responses([
+            "4882 6402 5885 aec3 771a 4b61 96d0 7abe 9410 54d4 44a8 2005 9504 0b81 66e0 82a6 \
+             2d1b ff6e 919d 29ad 1718 63c7 8f0b 97c8 e9ae 82ae 43d3",
+            "4883 640e ffc1 c0bf",
+            "88c1 6196 d07a be94 1054 d444 a820 0595 040b 8166 e084 a62d 1bff c05a 839b d9ab \
+             77ad 94e7 821d d7f2 e6c7 b335 dfdf cd5b 3960 d5af 2708 7f36 72c1 ab27 0fb5 291f \
+             9587 3160 65c0 03ed 4ee5 b106 3d50 07",
+        ]);
+    }
+
+    #[test]
+    fn size_updates() {
+        let mut decoder = Decoder::new(4_096);
+        decoder
+            .decode(
+                &hex("400a 6375 7374 6f6d 2d6b 6579 0d63 7573 746f 6d2d 6865 6164 6572"),
+                usize::MAX,
+            )
+            .unwrap();
+        // A size of zero empties the table, and is only allowed at the start.
+        assert_eq!(
+            decoder.decode(&hex("20 82"), usize::MAX),
+            Ok(fields(&[(":method", "GET")]))
+        );
+        assert!(decoder.table.is_empty());
+        assert_eq!(decoder.decode(&hex("82 20"), usize::MAX), Err(DecodeError));
+        // Nor may it exceed the limit.
+        assert_eq!(decoder.decode(&hex("3fe21f"), usize::MAX), Err(DecodeError));
+    }
+
+    #[test]
+    fn rejects_invalid_blocks() {
+        let mut decoder = Decoder::new(4_096);
+        // Index zero, an index past the tables, and a truncated string.
+        for block in ["80", "be", "400a 6375 7374"] {
+            assert_eq!(
+                decoder.decode(&hex(block), usize::MAX),
+                Err(DecodeError),
+                "{block}"
+            );
+        }
+        // The list exceeds its maximum size.
+        assert_eq!(decoder.decode(&hex("8286"), 42 + 42), Err(DecodeError));
+        assert!(decoder.decode(&hex("8286"), 42 + 43).is_ok());
+    }
+
+    #[test]
+    fn encodes_without_the_dynamic_table() {
+        let fields = fields(&[
+            (":status", "200"),
+            (":status", "302"),
+            ("cache-control", "private"),
+            ("content-type", "aaaaaaaaaa"),
+            ("x-custom", "{}"),
+            ("date", ""),
+        ]);
+        let mut buf = Vec::new();
+        encode(
+            fields.iter().map(|(n, v)| (n.as_slice(), v.as_slice())),
+            &mut buf,
+        );
+        // An indexed field, then a name of the static table with a value,
+        // which is shorter when Huffman-encoded.
+        assert_eq!(buf[..5], hex("88 0882 6402"));
+        let mut decoder = Decoder::new(4_096);
+        assert_eq!(decoder.decode(&buf, usize::MAX), Ok(fields));
+        assert!(decoder.table.is_empty());
+    }
+}
@@ -0,0 +1,1492 @@
+pub(crate) mod frame;
+pub(crate) mod hpack;
+pub(crate) mod huffman;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use bytes::{Bytes, BytesMut};
+
+use frame::{flags, settings, ErrorCode, Frame, FrameType};
+use hpack::Field;
+
+use super::connection::{self, ConnectionOptions, SHUTDOWN_POLL};
+use super::pool::Spawner;
+use super::router::Router;
+use super::Transport;
+use crate::error::{Http2Error, InvalidMethod, Result};
+use crate::http::upgrade::{OnUpgrade, Upgraded};
+use crate::http::{
+    Body, ConnectionInfo, FromBody, HeaderName, Method, Request, Response, StatusCode, Version,
+};
+
+/// The connection preface, that a client sends before its first frame.
+pub(crate) const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// The size of the flow-control windows when a stream or the connection opens.
+const DEFAULT_WINDOW_SIZE: i64 = 65_535;
+
+/// The largest size of a flow-control window.
+const MAX_WINDOW_SIZE: i64 = (1 << 31) - 1;
+
+/// The size of the largest frame that either endpoint accepts by default.
+const DEFAULT_MAX_FRAME_SIZE: usize = 16_384;
+
+/// The size of the largest frame that the peer may advertise.
+const MAX_FRAME_SIZE_LIMIT: usize = (1 << 24) - 1;
+
+/// The number of streams that the client may open at once.
+const MAX_CONCURRENT_STREAMS: usize = 100;
+
+/// The size of the dynamic table of the HPACK decoder.
+const HEADER_TABLE_SIZE: usize = 4_096;
+
+/// How long a request may wait for a worker before the connection handles
+/// it itself.
+const STALLED_TASK: Duration = Duration::from_millis(10);
+
+/// The shortest and the longest time that the connection waits, in turn, for
+/// a message of the workers and for a frame of the client, while it expects
+/// both. The time doubles while neither arrives, so that a slow handler or a
+/// quiet stream does not keep the thread busy.
+const MIN_POLL: Duration = Duration::from_millis(1);
+const MAX_POLL: Duration = Duration::from_millis(50);
+
+/// The headers that are specific to an HTTP/1.1 connection, and make an
+/// HTTP/2 request malformed.
+const CONNECTION_HEADERS: [&[u8]; 5] = [
+    b"connection",
+    b"keep-alive",
+    b"proxy-connection",
+    b"transfer-encoding",
+    b"upgrade",
+];
+
+/// Returns whether the bytes that have been received start the connection
+/// preface, which means that the client speaks HTTP/2 with prior knowledge.
+///
+/// At least three bytes are needed to tell the preface from an HTTP/1.1
+/// request line.
+pub(crate) fn is_preface(buf: &[u8]) -> bool {
+    let len = buf.len().min(PREFACE.len());
+    len >= 3 && buf[..len] == PREFACE[..len]
+}
+
+/// Serves an HTTP/2 connection on the current worker thread, and dispatches
+/// the request of every stream to the other workers of the pool.
+///
+/// The connection is closed with a `GOAWAY` frame once it has no open stream
+/// and has been idle for the keep-alive timeout, or once the server shuts
+/// down and its open streams have been answered.
+///
+/// The reader may already hold the connection preface, that is read again
+/// through it.
+pub(crate) fn serve<Req, Res>(
+    router: &Arc<Router<Req, Res>>,
+    spawner: &Spawner,
+    options: &Arc<ConnectionOptions>,
+    reader: BufReader<Box<dyn Transport>>,
+    info: ConnectionInfo,
+) where
+    Req: FromBody + 'static,
+    Res: Body + 'static,
+{
+    Connection::new(router, spawner, options, reader, info).run();
+}
+
+/// The failure that ends a connection.
+enum Failure {
+    /// The connection failed, or the client closed it.
+    Io(io::Error),
+
+    /// The client violated the protocol, and the connection is closed with
+    /// a `GOAWAY` frame of the error code.
+    Connection(ErrorCode),
+}
+
+impl From<io::Error> for Failure {
+    #[inline]
+    fn from(err: io::Error) -> Self {
+        Failure::Io(err)
+    }
+}
+
+type Outcome<T = ()> = std::result::Result<T, Failure>;
+
+/// Handles the request of a stream and returns its response.
+type Task = Box<dyn FnOnce() -> StreamResponse + Send + 'static>;
+
+/// The response of a stream, whose fields and body are ready to be sent.
+///
+/// The function of an upgrade writes the rest of the body, once the fields
+/// have been sent.
+struct StreamResponse {
+    fields: Vec<Field>,
+    body: Bytes,
+    upgrade: Option<OnUpgrade>,
+}
+
+/// A message that the workers send to the connection.
+enum Message {
+    /// The response of a stream, whose body is written by the function of
+    /// an upgrade until the stream is closed if the flag is given.
+    Response(u32, StreamResponse, Option<Arc<AtomicBool>>),
+
+    /// A part of the body that the function of an upgrade has written.
+    Data(u32, Bytes),
+
+    /// The end of the body that the function of an upgrade has written.
+    End(u32),
+}
+
+/// A request that has been sent to the thread pool, and that the connection
+/// handles itself unless a worker takes it in time.
+struct PendingTask {
+    task: Arc<Mutex<Option<Task>>>,
+    queued: Instant,
+}
+
+/// A header block that continues in `CONTINUATION` frames.
+struct PartialHeaders {
+    stream_id: u32,
+    end_stream: bool,
+    block: Vec<u8>,
+}
+
+/// A stream that the client has opened, and that has not been closed yet.
+#[derive(Default)]
+struct Stream {
+    fields: Vec<Field>,
+    body: Vec<u8>,
+    remote_closed: bool,
+    send_window: i64,
+    pending: Option<BytesMut>,
+    streaming: Option<Streaming>,
+}
+
+/// The body of a response that the function of an upgrade is writing, which
+/// stops the writes once the stream has been closed.
+struct Streaming(Arc<AtomicBool>);
+
+impl Drop for Streaming {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// The connection that the function of an upgrade receives over HTTP/2, whose
+/// writes are sent as the `DATA` frames of its stream, which ends once it is
+/// dropped.
+///
+/// Reading from it returns nothing, since the request of the stream has been
+/// received whole.
+struct StreamWriter {
+    stream_id: u32,
+    sender: Sender<Message>,
+    closed: Arc<AtomicBool>,
+}
+
+impl StreamWriter {
+    fn new(stream_id: u32, sender: Sender<Message>) -> Self {
+        StreamWriter {
+            stream_id,
+            sender,
+            closed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Runs the function of an upgrade with the writer as its connection.
+    fn upgrade(self, on_upgrade: OnUpgrade) {
+        on_upgrade(Upgraded::new(BufReader::new(Box::new(self))));
+    }
+}
+
+impl Read for StreamWriter {
+    #[inline]
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Ok(0)
+    }
+}
+
+impl Write for StreamWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        if !buf.is_empty() {
+            let data = Message::Data(self.stream_id, Bytes::copy_from_slice(buf));
+            self.sender
+                .send(data)
+                .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        }
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for StreamWriter {}
+
+impl Drop for StreamWriter {
+    #[inline]
+    fn drop(&mut self) {
+        // The connection may have closed in the meantime.
+        let _ = self.sender.send(Message::End(self.stream_id));
+    }
+}
+
+/// The state of an HTTP/2 connection.
+///
+/// The connection runs a loop on a worker thread that reads the frames of
+/// the client, and writes the responses that the other workers send back
+/// through a channel. While requests are handled, it alternates between
+/// waiting for their responses and checking for frames, so that pings,
+/// resets and window updates are still answered meanwhile.
+struct Connection<Req, Res> {
+    reader: BufReader<Box<dyn Transport>>,
+    read_timeout: Option<Duration>,
+    out: Vec<u8>,
+    router: Arc<Router<Req, Res>>,
+    spawner: Spawner,
+    options: Arc<ConnectionOptions>,
+    info: ConnectionInfo,
+    decoder: hpack::Decoder,
+    streams: HashMap<u32, Stream>,
+    last_stream_id: u32,
+    partial: Option<PartialHeaders>,
+    settings_received: bool,
+    going_away: bool,
+    max_frame_size: usize,
+    initial_window_size: i64,
+    send_window: i64,
+    sender: Sender<Message>,
+    receiver: Receiver<Message>,
+    tasks: HashMap<u32, PendingTask>,
+    last_frame: Instant,
+    poll_interval: Duration,
+}
+
+impl<Req, Res> Connection<Req, Res>
+where
+    Req: FromBody + 'static,
+    Res: Body + 'static,
+{
+    fn new(
+        router: &Arc<Router<Req, Res>>,
+        spawner: &Spawner,
+        options: &Arc<ConnectionOptions>,
+        reader: BufReader<Box<dyn Transport>>,
+        info: ConnectionInfo,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Connection {
+            reader,
+            read_timeout: None,
+            out: Vec::new(),
+            router: Arc::clone(router),
+            spawner: spawner.clone(),
+            options: Arc::clone(options),
+            info,
+            decoder: hpack::Decoder::new(HEADER_TABLE_SIZE),
+            streams: HashMap::new(),
+            last_stream_id: 0,
+            partial: None,
+            settings_received: false,
+            going_away: false,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            initial_window_size: DEFAULT_WINDOW_SIZE,
+            send_window: DEFAULT_WINDOW_SIZE,
+            sender,
+            receiver,
+            tasks: HashMap::new(),
+            last_frame: Instant::now(),
+            poll_interval: MIN_POLL,
+        }
+    }
+
+    /// Serves the connection until the client closes it or violates the
+    /// protocol, or until it is closed for being idle or for the shutdown.
+    fn run(mut self) {
+        match self.serve() {
+            Ok(()) => {}
+            Err(Failure::Io(err)) if is_disconnect(&err) => {}
+            Err(Failure::Io(err)) => eprintln!("Failed to serve HTTP/2 connection: {err}"),
+            Err(Failure::Connection(code)) => {
+                let mut payload = self.last_stream_id.to_be_bytes().to_vec();
+                payload.extend_from_slice(&(code as u32).to_be_bytes());
+                frame::encode(FrameType::GoAway, 0, 0, &payload, &mut self.out);
+                let _ = self.flush();
+            }
+        }
+    }
+
+    fn serve(&mut self) -> Outcome {
+        self.set_read_timeout(self.options.header_read_timeout)?;
+        let mut preface = [0_u8; PREFACE.len()];
+        self.reader.read_exact(&mut preface)?;
+        if preface != PREFACE {
+            return Err(Failure::Connection(ErrorCode::ProtocolError));
+        }
+        let mut payload = Vec::new();
+        for (id, value) in [
+            (settings::MAX_CONCURRENT_STREAMS, MAX_CONCURRENT_STREAMS),
+            (settings::MAX_HEADER_LIST_SIZE, self.options.max_head_size),
+        ] {
+            payload.extend_from_slice(&id.to_be_bytes());
+            payload.extend_from_slice(&u32::try_from(value).unwrap_or(u32::MAX).to_be_bytes());
+        }
+        frame::encode(FrameType::Settings, 0, 0, &payload, &mut self.out);
+
+        loop {
+            while let Ok(message) = self.receiver.try_recv() {
+                self.handle_message(message);
+            }
+            if self.options.is_shutting_down() {
+                self.go_away();
+            }
+            self.send_pending_data();
+            if self.going_away && self.streams.is_empty() {
+                return Ok(self.flush()?);
+            }
+            if self.reader.buffer().is_empty() {
+                self.flush()?;
+                if !self.poll()? {
+                    continue;
+                }
+            }
+            // A frame that has started to arrive must arrive whole in time.
+            self.set_read_timeout(self.options.header_read_timeout)?;
+            let header = frame::read_header(&mut self.reader)?;
+            if header.len > DEFAULT_MAX_FRAME_SIZE {
+                return Err(Failure::Connection(ErrorCode::FrameSizeError));
+            }
+            let frame = frame::read_payload(&mut self.reader, header)?;
+            self.last_frame = Instant::now();
+            self.handle_frame(frame)?;
+        }
+    }
+
+    /// Waits a little for the client to send a frame, and returns whether it
+    /// has.
+    ///
+    /// A connection that expects nothing from the workers blocks on the
+    /// socket until its keep-alive timeout, waking up only to check whether
+    /// the server shuts down, and goes away once the timeout expires without
+    /// an open stream.
+    ///
+    /// While requests are handled or bodies written by the functions of
+    /// upgrades, the connection waits in turn for the messages of the workers
+    /// and for the socket, for a time that grows while neither arrives.
+    fn poll(&mut self) -> Outcome<bool> {
+        let busy = !self.tasks.is_empty() || self.streams.values().any(|s| s.streaming.is_some());
+        let timeout = if busy {
+            match self.receiver.recv_timeout(self.poll_interval) {
+                Ok(message) => {
+                    self.handle_message(message);
+                    self.poll_interval = MIN_POLL;
+                    return Ok(false);
+                }
+                Err(RecvTimeoutError::Timeout) => self.run_stalled_task(),
+                Err(RecvTimeoutError::Disconnected) => {
+                    unreachable!("The connection holds a sender.")
+                }
+            }
+            self.poll_interval
+        } else {
+            match self.options.keep_alive_timeout {
+                Some(timeout) if self.streams.is_empty() => {
+                    match timeout.checked_sub(self.last_frame.elapsed()) {
+                        Some(left) if !left.is_zero() => left.min(SHUTDOWN_POLL),
+                        _ => {
+                            self.go_away();
+                            return Ok(false);
+                        }
+                    }
+                }
+                _ => SHUTDOWN_POLL,
+            }
+        };
+        self.set_read_timeout(Some(timeout))?;
+        match self.reader.fill_buf() {
+            Ok([]) => Err(Failure::Io(io::ErrorKind::UnexpectedEof.into())),
+            Ok(_) => {
+                self.poll_interval = MIN_POLL;
+                Ok(true)
+            }
+            Err(err)
+                if connection::is_timeout(&err) || err.kind() == io::ErrorKind::Interrupted =>
+            {
+                if busy {
+                    self.poll_interval = (self.poll_interval * 2).min(MAX_POLL);
+                }
+                Ok(false)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Handles a message of the workers.
+    fn handle_message(&mut self, message: Message) {
+        match message {
+            Message::Response(stream_id, response, closed) => {
+                self.tasks.remove(&stream_id);
+                self.start_response(stream_id, response, closed);
+            }
+            Message::Data(stream_id, data) => {
+                if let Some(pending) = self
+                    .streams
+                    .get_mut(&stream_id)
+                    .and_then(|stream| stream.pending.as_mut())
+                {
+                    pending.extend_from_slice(&data);
+                }
+            }
+            Message::End(stream_id) => {
+                if let Some(stream) = self.streams.get_mut(&stream_id) {
+                    stream.streaming = None;
+                }
+            }
+        }
+    }
+
+    /// Handles a request that no worker has taken for a while on the current
+    /// thread, since the other workers are busy, e.g. serving connections.
+    ///
+    /// The function of its upgrade, if any, is sent back to the thread pool,
+    /// since its writes are sent by the connection.
+    fn run_stalled_task(&mut self) {
+        let stalled = self.tasks.iter().find_map(|(&stream_id, pending)| {
+            match pending.queued.elapsed() >= STALLED_TASK {
+                true => take(&pending.task).map(|task| (stream_id, task)),
+                false => None,
+            }
+        });
+        let Some((stream_id, task)) = stalled else {
+            return;
+        };
+        self.tasks.remove(&stream_id);
+        let mut response = task();
+        match response.upgrade.take() {
+            Some(on_upgrade) => {
+                let writer = StreamWriter::new(stream_id, self.sender.clone());
+                self.start_response(stream_id, response, Some(Arc::clone(&writer.closed)));
+                // If the pool has shut down, the writer is dropped with the
+                // job, which ends the stream.
+                self.spawner.execute(move || writer.upgrade(on_upgrade));
+            }
+            None => self.start_response(stream_id, response, None),
+        }
+    }
+
+    /// Sends a `GOAWAY` frame, so that the client opens no further streams,
+    /// and the connection is closed once the open streams have been answered.
+    fn go_away(&mut self) {
+        if self.going_away {
+            return;
+        }
+        self.going_away = true;
+        let mut payload = self.last_stream_id.to_be_bytes().to_vec();
+        payload.extend_from_slice(&(ErrorCode::NoError as u32).to_be_bytes());
+        frame::encode(FrameType::GoAway, 0, 0, &payload, &mut self.out);
+    }
+
+    /// Sets the read timeout of the connection, unless it is already set.
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        if self.read_timeout != timeout {
+            self.reader.get_ref().set_read_timeout(timeout)?;
+            self.read_timeout = timeout;
+        }
+        Ok(())
+    }
+
+    /// Handles a frame from the client.
+    fn handle_frame(&mut self, frame: Frame) -> Outcome {
+        if !self.settings_received && frame.kind != FrameType::Settings {
+            return Err(Failure::Connection(ErrorCode::ProtocolError));
+        }
+        if self.partial.is_some() && frame.kind != FrameType::Continuation {
+            return Err(Failure::Connection(ErrorCode::ProtocolError));
+        }
+        match frame.kind {
+            FrameType::Data => self.handle_data(frame),
+            FrameType::Headers => self.handle_headers(frame),
+            FrameType::Priority => match (frame.stream_id, frame.payload.len()) {
+                (0, _) => Err(Failure::Connection(ErrorCode::ProtocolError)),
+                (_, 5) => Ok(()),
+                (stream_id, _) => {
+                    self.reset(stream_id, ErrorCode::FrameSizeError);
+                    Ok(())
+                }
+            },
+            FrameType::RstStream => self.handle_reset(frame),
+            FrameType::Settings => self.handle_settings(frame),
+            FrameType::PushPromise => Err(Failure::Connection(ErrorCode::ProtocolError)),
+            FrameType::Ping => self.handle_ping(frame),
+            FrameType::GoAway => match frame.stream_id {
+                0 => {
+                    self.going_away = true;
+                    Ok(())
+                }
+                _ => Err(Failure::Connection(ErrorCode::ProtocolError)),
+            },
+            FrameType::WindowUpdate => self.handle_window_update(frame),
+            FrameType::Continuation => self.handle_continuation(frame),
+            FrameType::Unknown(_) => Ok(()),
+        }
+    }
+
+    fn handle_data(&mut self, frame: Frame) -> Outcome {
+        let stream_id = frame.stream_id;
+        if stream_id == 0 || stream_id > self.last_stream_id {
+            return Err(Failure::Connection(ErrorCode::ProtocolError));
+        }
+        let data =
+            frame::strip_padding(&frame).ok_or(Failure::Connection(ErrorCode::ProtocolError))?;
+        let end_stream = frame.has_flag(flags::END_STREAM);
+
+        // The whole frame counts against the windows, which are restored at
+        // once, since the data is buffered until the stream ends.
+        if !frame.payload.is_empty() {
+            self.restore_window(0, frame.payload.len());
+        }
+        match self.streams.get_mut(&stream_id) {
+            Some(stream) if !stream.remote_closed => {
+                // The body is buffered until the stream ends.
+                if data.len() + stream.body.len() > self.options.max_body_size {
+                    self.reset(stream_id, ErrorCode::RefusedStream);
+                    return Ok(());
+                }
+                stream.body.extend_from_slice(data);
+                if end_stream {
+                    stream.remote_closed = true;
+                    self.dispatch(stream_id);
+                } else if !frame.payload.is_empty() {
+                    self.restore_window(stream_id, frame.payload.len());
+                }
+            }
+            _ => self.reset(stream_id, ErrorCode::StreamClosed),
+        }
+        Ok(())
+    }
+
+    fn handle_headers(&mut self, frame: Frame) -> Outcome {
+        if frame.stream_id == 0 {
+            return Err(Failure::Connection(ErrorCode::ProtocolError));
+        }
+        let mut fragment =
+            frame::strip_padding(&frame).ok_or(Failure::Connection(ErrorCode::ProtocolError))?;
+        if frame.has_flag(flags::PRIORITY) {
+            fragment = fragment
+                .get(5..)
+                .ok_or(Failure::Connection(ErrorCode::FrameSizeError))?;
+        }
+        let partial = PartialHeaders {
+            stream_id: frame.stream_id,
+            end_stream: frame.has_flag(flags::END_STREAM),
+            block: fragment.to_vec(),
+        };
+        match frame.has_flag(flags::END_HEADERS) {
+            true => self.handle_header_block(partial),
+            false => {
+                self.partial = Some(partial);
+                Ok(())
+            }
+        }
+    }
+
+    fn handle_continuation(&mut self, frame: Frame) -> Outcome {
+        let mut partial = match self.partial.take() {
+            Some(partial) if partial.stream_id == frame.stream_id => partial,
+            _ => return Err(Failure::Connection(ErrorCode::ProtocolError)),
+        };
+        partial.block.extend_from_slice(&frame.payload);
+        if partial.block.len() > self.options.max_head_size {
+            return Err(Failure::Connection(ErrorCode::ProtocolError));
+        }
+        match frame.has_flag(flags::END_HEADERS) {
+            true => self.handle_header_block(partial),
+            false => {
+                self.partial = Some(partial);
+                Ok(())
+            }
+        }
+    }
+
+    /// Handles a whole header block, that either opens a stream or carries
+    /// the trailers of an open one.
+    fn handle_header_block(&mut self, partial: PartialHeaders) -> Outcome {
+        // The block is decoded even if the stream is refused, to keep the
+        // dynamic table in sync with the encoder of the client.
+        let fields = self
+            .decoder
+            .decode(&partial.block, self.options.max_head_size)
+            .map_err(|_| Failure::Connection(ErrorCode::CompressionError))?;
+        let stream_id = partial.stream_id;
+
+        if let Some(stream) = self.streams.get_mut(&stream_id) {
+            match (stream.remote_closed, partial.end_stream) {
+                (false, true) => {
+                    stream.remote_closed = true;
+                    self.dispatch(stream_id);
+                }
+                (false, false) => self.reset(stream_id, ErrorCode::ProtocolError),
+                (true, _) => self.reset(stream_id, ErrorCode::StreamClosed),
+            }
+            return Ok(());
+        }
+        if stream_id <= self.last_stream_id {
+            return Err(Failure::Connection(ErrorCode::StreamClosed));
+        }
+        if stream_id.is_multiple_of(2) {
+            return Err(Failure::Connection(ErrorCode::ProtocolError));
+        }
+        self.last_stream_id = stream_id;
+        if self.going_away {
+            return Ok(());
+        }
+        if self.streams.len() >= MAX_CONCURRENT_STREAMS {
+            self.reset(stream_id, ErrorCode::RefusedStream);
+            return Ok(());
+        }
+        if !has_scheme(&fields) {
+            self.reset(stream_id, ErrorCode::ProtocolError);
+            return Ok(());
+        }
+        self.streams.insert(
+            stream_id,
+            Stream {
+                fields,
+                send_window: self.initial_window_size,
+                remote_closed: partial.end_stream,
+                ..Stream::default()
+            },
+        );
+        match partial.end_stream {
+            true => self.dispatch(stream_id),
+            false => self.check_expectation(stream_id),
+        }
+        Ok(())
+    }
+
+    fn handle_reset(&mut self, frame: Frame) -> Outcome {
+        if frame.payload.len() != 4 {
+            return Err(Failure::Connection(ErrorCode::FrameSizeError));
+        }
+        if frame.stream_id == 0 || frame.stream_id > self.last_stream_id {
+            return Err(Failure::Connection(ErrorCode::ProtocolError));
+        }
+        self.streams.remove(&frame.stream_id);
+        Ok(())
+    }
+
+    fn handle_settings(&mut self, frame: Frame) -> Outcome {
+        if frame.stream_id != 0 {
+            return Err(Failure::Connection(ErrorCode::ProtocolError));
+        }
+        if frame.has_flag(flags::ACK) {
+            return match frame.payload.is_empty() {
+                true => Ok(()),
+                false => Err(Failure::Connection(ErrorCode::FrameSizeError)),
+            };
+        }
+        if !frame.payload.len().is_multiple_of(6) {
+            return Err(Failure::Connection(ErrorCode::FrameSizeError));
+        }
+        for setting in frame.payload.chunks_exact(6) {
+            let id = u16::from_be_bytes([setting[0], setting[1]]);
+            let value = u32::from_be_bytes([setting[2], setting[3], setting[4], setting[5]]);
+            match id {
+                settings::ENABLE_PUSH if value > 1 => {
+                    return Err(Failure::Connection(ErrorCode::ProtocolError));
+                }
+                settings::INITIAL_WINDOW_SIZE => {
+                    let size = i64::from(value);
+                    if size > MAX_WINDOW_SIZE {
+                        return Err(Failure::Connection(ErrorCode::FlowControlError));
+                    }
+                    let delta = size - self.initial_window_size;
+                    for stream in self.streams.values_mut() {
+                        stream.send_window += delta;
+                        if stream.send_window > MAX_WINDOW_SIZE {
+                            return Err(Failure::Connection(ErrorCode::FlowControlError));
+                        }
+                    }
+                    self.initial_window_size = size;
+                }
+                settings::MAX_FRAME_SIZE => {
+                    let size = value as usize;
+                    if !(DEFAULT_MAX_FRAME_SIZE..=MAX_FRAME_SIZE_LIMIT).contains(&size) {
+                        return Err(Failure::Connection(ErrorCode::ProtocolError));
+                    }
+                    self.max_frame_size = size;
+                }
+                // The encoder does not use the dynamic table, and the other
+                // settings do not constrain the server.
+                _ => {}
+            }
+        }
+        self.settings_received = true;
+        frame::encode(FrameType::Settings, flags::ACK, 0, &[], &mut self.out);
+        Ok(())
+    }
+
+    fn handle_ping(&mut self, frame: Frame) -> Outcome {
+        if frame.stream_id != 0 {
+            return Err(Failure::Connection(ErrorCode::ProtocolError));
+        }
+        if frame.payload.len() != 8 {
+            return Err(Failure::Connection(ErrorCode::FrameSizeError));
+        }
+        if !frame.has_flag(flags::ACK) {
+            frame::encode(
+                FrameType::Ping,
+                flags::ACK,
+                0,
+                &frame.payload,
+                &mut self.out,
+            );
+        }
+        Ok(())
+    }
+
+    fn handle_window_update(&mut self, frame: Frame) -> Outcome {
+        if frame.payload.len() != 4 {
+            return Err(Failure::Connection(ErrorCode::FrameSizeError));
+        }
+        let bytes = [
+            frame.payload[0],
+            frame.payload[1],
+            frame.payload[2],
+            frame.payload[3],
+        ];
+        let increment = i64::from(u32::from_be_bytes(bytes) & 0x7FFF_FFFF);
+        let stream_id = frame.stream_id;
+        if stream_id == 0 {
+            self.send_window += increment;
+            return match increment {
+                0 => Err(Failure::Connection(ErrorCode::ProtocolError)),
+                _ if self.send_window > MAX_WINDOW_SIZE => {
+                    Err(Failure::Connection(ErrorCode::FlowControlError))
+                }
+                _ => Ok(()),
+            };
+        }
+        if stream_id > self.last_stream_id {
+            return Err(Failure::Connection(ErrorCode::ProtocolError));
+        }
+        if let Some(stream) = self.streams.get_mut(&stream_id) {
+            stream.send_window += increment;
+            if increment == 0 {
+                self.reset(stream_id, ErrorCode::ProtocolError);
+            } else if stream.send_window > MAX_WINDOW_SIZE {
+                self.reset(stream_id, ErrorCode::FlowControlError);
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends the request of a stream whose request has been received whole
+    /// to the thread pool, to be handled with the router.
+    ///
+    /// The worker that takes it also runs the function of the upgrade of the
+    /// response, if any.
+    fn dispatch(&mut self, stream_id: u32) {
+        let stream = self
+            .streams
+            .get_mut(&stream_id)
+            .expect("The stream is open.");
+        let fields = std::mem::take(&mut stream.fields);
+        let body = Bytes::from(std::mem::take(&mut stream.body));
+        let router = Arc::clone(&self.router);
+        let info = self.info.clone();
+        let task: Task = Box::new(move || respond(&router, fields, body, info));
+        let task = Arc::new(Mutex::new(Some(task)));
+        let sender = self.sender.clone();
+        let queued = {
+            let task = Arc::clone(&task);
+            self.spawner.execute(move || {
+                let Some(task) = take(&task) else {
+                    return;
+                };
+                let mut response = task();
+                // The connection may have closed in the meantime.
+                match response.upgrade.take() {
+                    Some(on_upgrade) => {
+                        let writer = StreamWriter::new(stream_id, sender.clone());
+                        let closed = Some(Arc::clone(&writer.closed));
+                        let _ = sender.send(Message::Response(stream_id, response, closed));
+                        writer.upgrade(on_upgrade);
+                    }
+                    None => {
+                        let _ = sender.send(Message::Response(stream_id, response, None));
+                    }
+                }
+            })
+        };
+        match queued {
+            true => {
+                let queued = Instant::now();
+                self.tasks.insert(stream_id, PendingTask { task, queued });
+            }
+            false => self.reset(stream_id, ErrorCode::RefusedStream),
+        }
+    }
+
+    /// Sends the header block of a response, and queues its body to be sent
+    /// as the flow-control windows allow.
+    ///
+    /// The body that the function of an upgrade writes is queued as it
+    /// arrives, and the stream ends once the function has returned, or is
+    /// closed by the flag once the client resets it.
+    fn start_response(
+        &mut self,
+        stream_id: u32,
+        response: StreamResponse,
+        closed: Option<Arc<AtomicBool>>,
+    ) {
+        let streaming = closed.map(Streaming);
+        // The client may have reset the stream while the request was handled.
+        if !self.streams.contains_key(&stream_id) {
+            return;
+        }
+        let end_stream = response.body.is_empty() && streaming.is_none();
+        self.send_headers(stream_id, &response.fields, end_stream);
+        match end_stream {
+            true => {
+                self.streams.remove(&stream_id);
+            }
+            false => {
+                let stream = self.streams.get_mut(&stream_id).expect("Checked above.");
+                stream.pending = Some(BytesMut::from(&response.body[..]));
+                stream.streaming = streaming;
+            }
+        }
+    }
+
+    /// Sends a header block, in a `HEADERS` frame and as many `CONTINUATION`
+    /// frames as the maximum frame size of the client requires.
+    fn send_headers(&mut self, stream_id: u32, fields: &[Field], end_stream: bool) {
+        let mut block = Vec::new();
+        hpack::encode(fields.iter().map(|(n, v)| (&n[..], &v[..])), &mut block);
+        let mut chunks = block.chunks(self.max_frame_size).peekable();
+        let mut kind = FrameType::Headers;
+        while let Some(chunk) = chunks.next() {
+            let mut frame_flags = 0;
+            if kind == FrameType::Headers && end_stream {
+                frame_flags |= flags::END_STREAM;
+            }
+            if chunks.peek().is_none() {
+                frame_flags |= flags::END_HEADERS;
+            }
+            frame::encode(kind, frame_flags, stream_id, chunk, &mut self.out);
+            kind = FrameType::Continuation;
+        }
+    }
+
+    /// Answers `Expect: 100-continue` on a stream whose body has not been
+    /// received yet, with `100 Continue` if the router accepts the request.
+    ///
+    /// Otherwise the stream is answered with the status code of the error,
+    /// and reset so that the client stops sending the body.
+    fn check_expectation(&mut self, stream_id: u32) {
+        let stream = &self.streams[&stream_id];
+        if !stream.fields.iter().any(|(name, _)| name == b"expect") {
+            return;
+        }
+        // A malformed request is answered once it has been received.
+        let Ok(head) = build_head(&stream.fields) else {
+            return;
+        };
+        match self.router.check_expectation(&head) {
+            Ok(true) => self.send_headers(stream_id, &[status_field(StatusCode::CONTINUE)], false),
+            Ok(false) => {}
+            Err(err) => {
+                let response = error_response(&self.router, err.status());
+                self.send_headers(stream_id, &response.fields, true);
+                self.reset(stream_id, ErrorCode::NoError);
+            }
+        }
+    }
+
+    /// Sends as much of the pending bodies of the responses as the flow-control
+    /// windows allow, and closes the streams whose bodies have been sent.
+    fn send_pending_data(&mut self) {
+        let mut finished = Vec::new();
+        for (&stream_id, stream) in self.streams.iter_mut() {
+            let Some(pending) = stream.pending.as_mut() else {
+                continue;
+            };
+            let open = stream.streaming.is_some();
+            loop {
+                let window = self.send_window.min(stream.send_window);
+                // The end of a body that has been sent whole needs no window.
+                if pending.is_empty() && open || !pending.is_empty() && window <= 0 {
+                    break;
+                }
+                let len = pending.len().min(window.max(0) as usize);
+                let chunk = pending.split_to(len.min(self.max_frame_size));
+                let end_stream = pending.is_empty() && !open;
+                let frame_flags = if end_stream { flags::END_STREAM } else { 0 };
+                frame::encode(
+                    FrameType::Data,
+                    frame_flags,
+                    stream_id,
+                    &chunk,
+                    &mut self.out,
+                );
+                self.send_window -= chunk.len() as i64;
+                stream.send_window -= chunk.len() as i64;
+                if end_stream {
+                    finished.push(stream_id);
+                    break;
+                }
+            }
+        }
+        for stream_id in finished {
+            self.streams.remove(&stream_id);
+        }
+    }
+
+    /// Resets a stream with the error code.
+    fn reset(&mut self, stream_id: u32, code: ErrorCode) {
+        self.streams.remove(&stream_id);
+        let payload = (code as u32).to_be_bytes();
+        frame::encode(FrameType::RstStream, 0, stream_id, &payload, &mut self.out);
+    }
+
+    /// Restores the receive window of a stream, or of the connection for the
+    /// stream `0`.
+    fn restore_window(&mut self, stream_id: u32, increment: usize) {
+        let payload = (increment as u32).to_be_bytes();
+        frame::encode(
+            FrameType::WindowUpdate,
+            0,
+            stream_id,
+            &payload,
+            &mut self.out,
+        );
+    }
+
+    /// Writes the frames that have been queued to the connection.
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.out.is_empty() {
+            let stream = self.reader.get_mut();
+            stream.write_all(&self.out)?;
+            stream.flush()?;
+            self.out.clear();
+        }
+        Ok(())
+    }
+}
+
+/// Takes the task, unless another thread has taken it.
+fn take(task: &Mutex<Option<Task>>) -> Option<Task> {
+    task.lock().unwrap_or_else(PoisonError::into_inner).take()
+}
+
+/// Handles the request of a stream on a worker thread, and returns the
+/// response to send.
+///
+/// As in HTTP/1.1, a request that cannot be built is answered with the
+/// status code of the error and no body. Since an HTTP/2 connection is shared
+/// by its streams, the function that takes over the connection after a
+/// response only writes the body of the stream, and a response that would
+/// switch protocols is answered with `500 Internal Server Error`.
+fn respond<Req, Res>(
+    router: &Router<Req, Res>,
+    fields: Vec<Field>,
+    body: Bytes,
+    info: ConnectionInfo,
+) -> StreamResponse
+where
+    Req: FromBody,
+    Res: Body,
+{
+    let response = build_request(&fields, body).and_then(|mut request| {
+        request.set_connection(info);
+        let response = router.handle_request(&request)?;
+        Ok((request.method().clone(), response))
+    });
+    let status = match response {
+        Ok((method, mut response)) => {
+            router.add_default_headers(&mut response);
+            match encode_response(response, &method) {
+                Ok(response) => return response,
+                Err(err) => {
+                    eprintln!("Failed to write response: {err}");
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }
+            }
+        }
+        Err(err) => err.status(),
+    };
+    error_response(router, status)
+}
+
+/// Returns the response with the status code of an error and no body.
+fn error_response<Req, Res>(router: &Router<Req, Res>, status: StatusCode) -> StreamResponse {
+    let mut response = Response::builder()
+        .with_status(status)
+        .with_body(())
+        .expect("Guaranteed by the status code of the error.");
+    router.add_default_headers(&mut response);
+    // The response has no body, whatever the method of the request.
+    encode_response(response, &Method::GET).expect("An empty body is always written.")
+}
+
+/// Checks whether the header fields of a request have a `:scheme`, which
+/// every request but `CONNECT` must have.
+fn has_scheme(fields: &[Field]) -> bool {
+    let field = |name: &[u8]| fields.iter().find(|(n, _)| n == name).map(|(_, v)| v);
+    field(b":method").is_some_and(|method| method == b"CONNECT")
+        || field(b":scheme").is_some_and(|scheme| !scheme.is_empty())
+}
+
+/// Builds the request of a stream from its header fields and body.
+fn build_request<Req: FromBody>(fields: &[Field], body: Bytes) -> Result<Request<Req>> {
+    let head = build_head(fields)?;
+    if let Some(len) = head.headers().get(&HeaderName::CONTENT_LENGTH) {
+        if len.as_bytes().as_ref() != body.len().to_string().as_bytes() {
+            return Err(Http2Error::with_context("Content-Length does not match the body").into());
+        }
+    }
+    head.with_body_bytes(body)
+}
+
+/// Builds the request of a stream, without its body, from its header fields.
+///
+/// The pseudo-header fields give the method, the path and the authority,
+/// which becomes the `Host` header unless the client sent one.
+fn build_head(fields: &[Field]) -> Result<Request<()>> {
+    let mut method = None;
+    let mut path = None;
+    let mut authority = None;
+    let mut headers: Vec<(HeaderName, Vec<u8>)> = Vec::new();
+    for (name, value) in fields {
+        let value = value.clone();
+        if let Some(pseudo) = name.strip_prefix(b":") {
+            if !headers.is_empty() {
+                return Err(Http2Error::with_context("pseudo-header after header").into());
+            }
+            let slot = match pseudo {
+                b"method" => &mut method,
+                b"path" => &mut path,
+                b"authority" => &mut authority,
+                b"scheme" => continue,
+                _ => return Err(Http2Error::with_context("unknown pseudo-header").into()),
+            };
+            if slot.replace(value).is_some() {
+                return Err(Http2Error::with_context("duplicate pseudo-header").into());
+            }
+            continue;
+        }
+        if name.iter().any(u8::is_ascii_uppercase)
+            || CONNECTION_HEADERS.contains(&&name[..])
+            || name == b"te" && value != b"trailers"
+        {
+            let name = String::from_utf8_lossy(name).into_owned();
+            return Err(Http2Error::with_context(name).into());
+        }
+        let name = HeaderName::try_from(&name[..])?;
+        // Headers that are split into several fields are joined again, the
+        // cookies with the separator of the `Cookie` header.
+        match headers.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, joined)) => {
+                let separator: &[u8] = match name == HeaderName::COOKIE {
+                    true => b"; ",
+                    false => b", ",
+                };
+                joined.extend_from_slice(separator);
+                joined.extend_from_slice(&value);
+            }
+            None => headers.push((name, value)),
+        }
+    }
+
+    let method = method.ok_or_else(|| Http2Error::with_context("missing :method"))?;
+    let method = std::str::from_utf8(&method)
+        .map_err(|_| InvalidMethod::with_context(String::from_utf8_lossy(&method)))?;
+    let path = match (method, path, &authority) {
+        ("CONNECT", None, Some(authority)) => authority.clone(),
+        (_, Some(path), _) if !path.is_empty() => path,
+        _ => return Err(Http2Error::with_context("missing :path").into()),
+    };
+    let path = String::from_utf8(path).map_err(|_| Http2Error::with_context("invalid :path"))?;
+    if let Some(authority) = authority {
+        if !headers.iter().any(|(n, _)| *n == HeaderName::HOST) {
+            headers.push((HeaderName::HOST, authority));
+        }
+    }
+
+    let mut request = Request::builder()
+        .with_method(method)
+        .with_uri(path.as_str())
+        .with_version(Version::HTTP_2);
+    for (name, value) in headers {
+        request = request.with_header(name.as_str().as_bytes(), &value[..]);
+    }
+    request.with_unframed_body(())
+}
+
+/// Returns the header fields and the body of a response to a request of
+/// the method.
+///
+/// The header names are lowercase, and the headers that are specific to an
+/// HTTP/1.1 connection are left out, as is the body that the response must
+/// not have.
+///
+/// HTTP/2 has no `101 Switching Protocols`, so an informational response
+/// fails.
+fn encode_response<Res: Body>(
+    mut response: Response<Res>,
+    method: &Method,
+) -> io::Result<StreamResponse> {
+    if response.status().code().get() < 200 {
+        return Err(io::Error::other("informational response over HTTP/2"));
+    }
+    response.conform_to_method(method);
+    let upgrade = response.take_upgrade();
+    let mut body = Vec::new();
+    if response.has_body(method) {
+        body.reserve(response.body().content_len().unwrap_or_default());
+        response.body().write_to(&mut body)?;
+    }
+    let mut fields = vec![status_field(*response.status())];
+    for (name, value) in response.headers() {
+        if matches!(
+            *name,
+            HeaderName::CONNECTION | HeaderName::TRANSFER_ENCODING | HeaderName::UPGRADE
+        ) {
+            continue;
+        }
+        let name = name.as_str().to_ascii_lowercase().into_bytes();
+        fields.push((name, value.as_bytes().to_vec()));
+    }
+    Ok(StreamResponse {
+        fields,
+        body: Bytes::from(body),
+        upgrade,
+    })
+}
+
+/// Returns the `:status` pseudo-header field of the status code.
+fn status_field(status: StatusCode) -> Field {
+    (b":status".to_vec(), status.code().to_string().into_bytes())
+}
+
+/// Returns whether the error means that the client has gone away.
+fn is_disconnect(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpStream;
+
+    use super::*;
+    use crate::error::Error;
+    use crate::server::{HttpServer, ServerHandle};
+
+    /// Starts a server that answers `/big` with as many bytes as its query
+    /// asks for.
+    fn start() -> (ServerHandle<Bytes, Bytes>, TcpStream) {
+        let server = HttpServer::build()
+            .workers(2)
+            .route("/big", "GET", |request: &Request<Bytes>| {
+                let len = request.uri().query().unwrap_or_default().parse().unwrap();
+                Response::builder()
+                    .with_status(200)
+                    .with_body(Bytes::from(vec![b'x'; len]))
+            })
+            .route_err(|_: &Request<Bytes>, err: &Error| {
+                Response::builder()
+                    .with_status(err.status())
+                    .with_body(Bytes::new())
+            })
+            .bind("127.0.0.1:0")
+            .unwrap();
+        let stream = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        (server.spawn(), stream)
+    }
+
+    /// A client that speaks HTTP/2 with prior knowledge.
+    struct Client {
+        stream: TcpStream,
+        decoder: hpack::Decoder,
+    }
+
+    impl Client {
+        /// Sends the preface and the settings, and reads the settings of the
+        /// server and the acknowledgement of its own.
+        fn connect(stream: TcpStream, client_settings: &[(u16, u32)]) -> Self {
+            stream
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            let mut client = Client {
+                stream,
+                decoder: hpack::Decoder::new(HEADER_TABLE_SIZE),
+            };
+            client.stream.write_all(PREFACE).unwrap();
+            client.settings(client_settings);
+            let frame = client.recv();
+            assert_eq!((frame.kind, frame.flags), (FrameType::Settings, 0));
+            let frame = client.recv();
+            assert_eq!((frame.kind, frame.flags), (FrameType::Settings, flags::ACK));
+            client
+        }
+
+        fn send(&mut self, kind: FrameType, flags: u8, stream_id: u32, payload: &[u8]) {
+            let mut buf = Vec::new();
+            frame::encode(kind, flags, stream_id, payload, &mut buf);
+            self.stream.write_all(&buf).unwrap();
+        }
+
+        fn settings(&mut self, values: &[(u16, u32)]) {
+            let mut payload = Vec::new();
+            for (id, value) in values {
+                payload.extend_from_slice(&id.to_be_bytes());
+                payload.extend_from_slice(&value.to_be_bytes());
+            }
+            self.send(FrameType::Settings, 0, 0, &payload);
+        }
+
+        fn window_update(&mut self, stream_id: u32, increment: u32) {
+            self.send(
+                FrameType::WindowUpdate,
+                0,
+                stream_id,
+                &increment.to_be_bytes(),
+            );
+        }
+
+        /// Sends a request without a body, whose fields follow the method.
+        fn request(&mut self, stream_id: u32, method: &str, fields: &[(&str, &str)]) {
+            let mut block = Vec::new();
+            let method = [(":method", method)];
+            let fields = method.iter().chain(fields);
+            hpack::encode(
+                fields.map(|(n, v)| (n.as_bytes(), v.as_bytes())),
+                &mut block,
+            );
+            let flags = flags::END_HEADERS | flags::END_STREAM;
+            self.send(FrameType::Headers, flags, stream_id, &block);
+        }
+
+        fn get(&mut self, stream_id: u32, path: &str) {
+            let fields = [(":scheme", "http"), (":path", path), (":authority", "x")];
+            self.request(stream_id, "GET", &fields);
+        }
+
+        fn recv(&mut self) -> Frame {
+            let header = frame::read_header(&mut self.stream).unwrap();
+            frame::read_payload(&mut self.stream, header).unwrap()
+        }
+
+        /// Receives the next frame of the type, skipping the window updates
+        /// that restore the windows of the server.
+        fn expect(&mut self, kind: FrameType) -> Frame {
+            loop {
+                let frame = self.recv();
+                if frame.kind != FrameType::WindowUpdate || kind == FrameType::WindowUpdate {
+                    assert_eq!(frame.kind, kind, "{frame:?}");
+                    return frame;
+                }
+            }
+        }
+
+        /// Receives the header block of a response, and returns its status.
+        fn expect_status(&mut self, stream_id: u32) -> String {
+            let frame = self.expect(FrameType::Headers);
+            assert_eq!(frame.stream_id, stream_id);
+            let fields = self.decoder.decode(&frame.payload, usize::MAX).unwrap();
+            let (_, status) = fields.iter().find(|(n, _)| n == b":status").unwrap();
+            String::from_utf8(status.clone()).unwrap()
+        }
+
+        /// Receives a `DATA` frame, and returns its length and whether it
+        /// ends the stream.
+        fn expect_data(&mut self, stream_id: u32) -> (usize, bool) {
+            let frame = self.expect(FrameType::Data);
+            assert_eq!(frame.stream_id, stream_id);
+            (frame.payload.len(), frame.has_flag(flags::END_STREAM))
+        }
+
+        fn expect_reset(&mut self, stream_id: u32, code: ErrorCode) {
+            let frame = self.expect(FrameType::RstStream);
+            assert_eq!(frame.stream_id, stream_id);
+            assert_eq!(frame.payload, (code as u32).to_be_bytes());
+        }
+
+        fn expect_go_away(&mut self, code: ErrorCode) {
+            let frame = self.expect(FrameType::GoAway);
+            assert_eq!(frame.payload[4..], (code as u32).to_be_bytes());
+        }
+
+        /// Checks that the server sends nothing for a while.
+        fn expect_nothing(&mut self) {
+            self.stream
+                .set_read_timeout(Some(Duration::from_millis(100)))
+                .unwrap();
+            let err = frame::read_header(&mut self.stream).unwrap_err();
+            assert!(connection::is_timeout(&err), "{err}");
+            self.stream
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn answers_requests() {
+        let (server, stream) = start();
+        let mut client = Client::connect(stream, &[]);
+        client.get(1, "/big?5");
+        assert_eq!(client.expect_status(1), "200");
+        assert_eq!(client.expect_data(1), (5, true));
+        client.get(3, "/none");
+        assert_eq!(client.expect_status(3), "404");
+        server.shutdown();
+    }
+
+    #[test]
+    fn resets_requests_without_a_scheme() {
+        let (server, stream) = start();
+        let mut client = Client::connect(stream, &[]);
+        client.request(1, "GET", &[(":path", "/big?5"), (":authority", "x")]);
+        client.expect_reset(1, ErrorCode::ProtocolError);
+        client.request(3, "GET", &[(":scheme", ""), (":path", "/big?5")]);
+        client.expect_reset(3, ErrorCode::ProtocolError);
+        // The connection goes on.
+        client.get(5, "/big?5");
+        assert_eq!(client.expect_status(5), "200");
+        server.shutdown();
+    }
+
+    #[test]
+    fn sends_data_within_the_window_of_the_stream() {
+        let (server, stream) = start();
+        let mut client = Client::connect(stream, &[(settings::INITIAL_WINDOW_SIZE, 10)]);
+        client.get(1, "/big?30");
+        assert_eq!(client.expect_status(1), "200");
+        assert_eq!(client.expect_data(1), (10, false));
+        client.expect_nothing();
+        client.window_update(1, 5);
+        assert_eq!(client.expect_data(1), (5, false));
+        client.expect_nothing();
+        // A larger initial window enlarges the windows of the open streams.
+        client.settings(&[(settings::INITIAL_WINDOW_SIZE, 20)]);
+        let frame = client.expect(FrameType::Settings);
+        assert!(frame.has_flag(flags::ACK));
+        assert_eq!(client.expect_data(1), (10, false));
+        client.window_update(1, 100);
+        assert_eq!(client.expect_data(1), (5, true));
+        server.shutdown();
+    }
+
+    #[test]
+    fn sends_data_within_the_window_of_the_connection() {
+        let (server, stream) = start();
+        let mut client = Client::connect(stream, &[(settings::INITIAL_WINDOW_SIZE, 1 << 20)]);
+        client.get(1, "/big?70000");
+        assert_eq!(client.expect_status(1), "200");
+        let mut received = 0;
+        while received < DEFAULT_WINDOW_SIZE as usize {
+            let (len, end_stream) = client.expect_data(1);
+            assert!(len <= DEFAULT_MAX_FRAME_SIZE && !end_stream);
+            received += len;
+        }
+        assert_eq!(received, DEFAULT_WINDOW_SIZE as usize);
+        client.expect_nothing();
+        client.window_update(0, 10_000);
+        assert_eq!(client.expect_data(1), (70_000 - received, true));
+        server.shutdown();
+    }
+
+    #[test]
+    fn rejects_invalid_window_updates() {
+        let (server, stream) = start();
+        let mut client = Client::connect(stream, &[]);
+        client.window_update(0, 0x7FFF_FFFF);
+        client.expect_go_away(ErrorCode::FlowControlError);
+        server.shutdown();
+
+        let (server, stream) = start();
+        let mut client = Client::connect(stream, &[]);
+        client.window_update(0, 0);
+        client.expect_go_away(ErrorCode::ProtocolError);
+        server.shutdown();
+
+        let (server, stream) = start();
+        // The window of the stream is the largest, minus the data that the
+        // window of the connection has let through.
+        let mut client = Client::connect(stream, &[(settings::INITIAL_WINDOW_SIZE, 0x7FFF_FFFF)]);
+        client.get(1, "/big?70000");
+        assert_eq!(client.expect_status(1), "200");
+        let mut received = 0;
+        while received < DEFAULT_WINDOW_SIZE as usize {
+            received += client.expect_data(1).0;
+        }
+        client.window_update(1, DEFAULT_WINDOW_SIZE as u32 + 1);
+        client.expect_reset(1, ErrorCode::FlowControlError);
+        server.shutdown();
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        let (server, stream) = start();
+        let mut client = Client::connect(stream, &[]);
+        client.settings(&[(settings::INITIAL_WINDOW_SIZE, 1 << 31)]);
+        client.expect_go_away(ErrorCode::FlowControlError);
+        server.shutdown();
+
+        let (server, stream) = start();
+        let mut client = Client::connect(stream, &[]);
+        client.send(FrameType::Settings, 0, 0, &[0; 5]);
+        client.expect_go_away(ErrorCode::FrameSizeError);
+        server.shutdown();
+
+        let (server, stream) = start();
+        let mut client = Client::connect(stream, &[]);
+        client.settings(&[(settings::MAX_FRAME_SIZE, 100)]);
+        client.expect_go_away(ErrorCode::ProtocolError);
+        server.shutdown();
+    }
+
+    #[test]
+    fn rejects_data_on_an_idle_stream() {
+        let (server, stream) = start();
+        let mut client = Client::connect(stream, &[]);
+        client.send(FrameType::Data, flags::END_STREAM, 1, b"data");
+        client.expect_go_away(ErrorCode::ProtocolError);
+        server.shutdown();
+    }
+
+    #[test]
+    fn resets_closed_streams() {
+        let (server, stream) = start();
+        let mut client = Client::connect(stream, &[]);
+        client.get(1, "/big?5");
+        assert_eq!(client.expect_status(1), "200");
+        assert_eq!(client.expect_data(1), (5, true));
+        client.send(FrameType::Data, 0, 1, b"data");
+        client.expect_reset(1, ErrorCode::StreamClosed);
+
+        // A stream that cannot be opened anymore closes the connection.
+        client.get(1, "/big?5");
+        client.expect_go_away(ErrorCode::StreamClosed);
+        server.shutdown();
+    }
+}
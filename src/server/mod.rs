@@ -1,69 +1,316 @@
 pub mod build;
+pub(crate) mod connection;
+pub mod extract;
+pub mod handle;
+pub mod handler;
+pub mod http1;
+pub mod http2;
 pub mod pool;
 pub mod router;
+pub mod sse;
+pub mod tcp;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod transport;
+#[cfg(unix)]
+pub mod unix;
+pub mod websocket;
 pub mod worker;
 
-use std::io::Write;
-use std::net::TcpListener;
+use std::error::Error as _;
+use std::fmt;
+use std::io::{self, BufReader};
+use std::net::{SocketAddr, ToSocketAddrs};
 use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
 use bytes::Bytes;
 
 use build::Builder;
-use pool::ThreadPool;
+use connection::ConnectionOptions;
+use extract::RequestContext;
+use pool::{Spawner, ThreadPool};
 use router::Router;
+use tcp::{TcpOptions, TcpSocketListener};
+#[cfg(feature = "tls")]
+use tls::TlsListener;
+use transport::Listener;
 
-use crate::error::Result;
-use crate::http::{Request, Response};
+pub use crate::http::upgrade::Upgraded;
+pub use handle::ServerHandle;
+pub use handler::Handler;
+pub use sse::{Event, EventSender, Sse};
+pub use transport::Transport;
+pub use websocket::{Message, WebSocket};
 
-type RequestHandler<T> = Box<dyn Fn(&Request<T>) -> Result<Response<T>> + Send + Sync + 'static>;
+use crate::error::{Error, FailedConnection, Result};
+use crate::http::{Body, ConnectionInfo, FromBody, Request, Response};
 
-pub struct HttpServer<T> {
-    listener: TcpListener,
+type RequestHandler<Req, Res> = Box<
+    dyn Fn(&Request<Req>, &RequestContext<'_>) -> Result<Response<Res>> + Send + Sync + 'static,
+>;
+
+type ErrorHandler<Req, Res> =
+    Box<dyn Fn(&Request<Req>, &Error) -> Result<Response<Res>> + Send + Sync + 'static>;
+
+type ExpectHandler = Box<dyn Fn(&Request<()>) -> Result<()> + Send + Sync + 'static>;
+
+type ErrorReporter = Arc<dyn Fn(&Error) + Send + Sync + 'static>;
+
+type WebSocketHandler<Req> =
+    Box<dyn Fn(&Request<Req>, &RequestContext<'_>, WebSocket) + Send + Sync + 'static>;
+
+/// How long a listener waits before it accepts again, after a failure that
+/// is not caused by a single connection, e.g. once the process has run out
+/// of file descriptors.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// A listener and the router that serves its connections.
+type Binding<Req, Res> = (Arc<dyn Listener>, Arc<Router<Req, Res>>);
+
+/// An HTTP server.
+///
+/// `Req` is the type of the body of the requests that the handlers receive,
+/// and `Res` the type of the body of the responses that they return.
+///
+/// A server can accept connections on several listeners, that share the same
+/// thread pool. Every listener has its own `Router`, which by default is the
+/// `Router` of the server.
+pub struct HttpServer<Req = Bytes, Res = Req> {
+    listeners: Vec<Binding<Req, Res>>,
     pool: ThreadPool,
-    router: Arc<Router<T>>,
+    router: Arc<Router<Req, Res>>,
+    tcp: TcpOptions,
+    #[cfg(feature = "tls")]
+    tls: Option<Arc<rustls::ServerConfig>>,
+    options: Arc<ConnectionOptions>,
+    reporter: ErrorReporter,
 }
 
-impl<T> HttpServer<T> {
+impl<Req: 'static, Res: 'static> HttpServer<Req, Res> {
     /// Returns an HTTP server builder.
-    pub fn build() -> Builder<T> {
+    pub fn build() -> Builder<Req, Res> {
         Builder::default()
     }
+
+    /// Binds the server to another address, that is served with the routes
+    /// of the server.
+    ///
+    /// The socket is created with the TCP socket options of the builder of the
+    /// server, and serves TLS if the builder has a certificate.
+    pub fn also_bind<A>(self, addr: A) -> Result<Self>
+    where
+        A: ToSocketAddrs + fmt::Debug,
+    {
+        let listener = self.bind_tcp(addr)?;
+        Ok(self.also_listen(listener))
+    }
+
+    /// Binds the server to another address, that is served with the routes,
+    /// error handlers and state of the builder instead.
+    ///
+    /// The size of the thread pool of the builder is ignored, since the
+    /// connections are served by the thread pool of the server.
+    pub fn also_bind_with<A>(self, addr: A, routes: Builder<Req, Res>) -> Result<Self>
+    where
+        A: ToSocketAddrs + fmt::Debug,
+    {
+        let listener = self.bind_tcp(addr)?;
+        self.also_listen_with(listener, routes)
+    }
+
+    /// Returns the socket address of the first listener of the server.
+    ///
+    /// This is the way to learn the port that the OS assigned, when the server
+    /// is bound to port 0.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        let (listener, _) = &self.listeners[0];
+        Ok(listener.local_addr()?)
+    }
+
+    /// Binds a listener to the address with the TCP socket options of the
+    /// server, that serves TLS if the server has a certificate.
+    fn bind_tcp<A>(&self, addr: A) -> Result<Box<dyn Listener>>
+    where
+        A: ToSocketAddrs + fmt::Debug,
+    {
+        let listener = TcpSocketListener::bind(addr, &self.tcp)?;
+        #[cfg(feature = "tls")]
+        if let Some(config) = &self.tls {
+            return Ok(Box::new(TlsListener::new(listener, Arc::clone(config))));
+        }
+        Ok(Box::new(listener))
+    }
+
+    /// Adds a listener, whose connections are served with the routes of the server.
+    pub fn also_listen<L: Listener + 'static>(mut self, listener: L) -> Self {
+        let router = Arc::clone(&self.router);
+        self.listeners.push((Arc::new(listener), router));
+        self
+    }
+
+    /// Adds a listener, whose connections are served with the routes, error
+    /// handlers and state of the builder instead.
+    pub fn also_listen_with<L: Listener + 'static>(
+        mut self,
+        listener: L,
+        routes: Builder<Req, Res>,
+    ) -> Result<Self> {
+        let (_, router) = routes.into_router()?;
+        self.listeners.push((Arc::new(listener), Arc::new(router)));
+        Ok(self)
+    }
 }
 
-impl HttpServer<Bytes> {
+impl<Req, Res> HttpServer<Req, Res>
+where
+    Req: FromBody + 'static,
+    Res: Body + 'static,
+{
     /// Starts the HTTP server.
+    ///
+    /// Every listener accepts connections on its own thread, and every accepted
+    /// connection is served by a worker thread of the pool.
     pub fn run(&self) {
-        for stream in self.listener.incoming() {
-            let mut stream = stream.expect("Failed to get connection");
-            let router = Arc::clone(&self.router);
+        thread::scope(|scope| {
+            for (listener, router) in &self.listeners {
+                scope.spawn(move || self.accept(listener, router));
+            }
+        });
+    }
+
+    /// Starts the HTTP server on a background thread, and returns a handle
+    /// that can shut it down.
+    pub fn spawn(self) -> ServerHandle<Req, Res> {
+        let server = Arc::new(self);
+        let runner = Arc::clone(&server);
+        let thread = thread::spawn(move || runner.run());
+        ServerHandle::new(server, thread)
+    }
+
+    /// Accepts connections on the listener, and sends them to the thread pool
+    /// to be served with the router, until the server shuts down.
+    ///
+    /// A failure to accept that does not concern a single connection, such
+    /// as running out of file descriptors, would fail again at once, so the
+    /// listener backs off before it accepts again.
+    fn accept(&self, listener: &Arc<dyn Listener>, router: &Arc<Router<Req, Res>>) {
+        loop {
+            let accepted = listener.accept();
+            if self.options.is_shutting_down() {
+                break;
+            }
+            let stream = match accepted {
+                Ok(stream) => stream,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    let backoff = !is_connection_error(&err);
+                    (self.reporter)(&listener_error(listener.as_ref(), err));
+                    if backoff {
+                        thread::sleep(ACCEPT_BACKOFF);
+                    }
+                    continue;
+                }
+            };
+            let listener = Arc::clone(listener);
+            let router = Arc::clone(router);
+            let spawner = self.pool.spawner();
+            let options = Arc::clone(&self.options);
+            let reporter = Arc::clone(&self.reporter);
             self.pool.execute(move || {
-                let request = Request::from_stream(&mut stream)
-                    .expect("Failed to parse request from stream.");
-                let response = match router.handle_request(&request) {
-                    Ok(response) => response,
-                    _ => router
-                        .handle_error(&request)
-                        .expect("Guaranteed to get response"),
-                };
-
-                write!(
-                    stream,
-                    "{} {} {}\r\n",
-                    response.version(),
-                    response.status().code(),
-                    response.status().msg().expect("Guaranteed.")
-                )
-                .expect("Failed to write response");
-
-                for (name, val) in response.headers() {
-                    write!(stream, "{}: {}\r\n", name, val).expect("Failed to write headers");
+                // A client that is too slow to establish the connection, e.g. to
+                // complete a TLS handshake, does not hold the worker either.
+                let established = stream
+                    .set_read_timeout(options.header_read_timeout)
+                    .and_then(|()| listener.establish(stream));
+                match established {
+                    Ok((stream, connection)) => {
+                        serve_connection(&router, &spawner, &options, stream, connection)
+                    }
+                    Err(err) => reporter(&listener_error(listener.as_ref(), err)),
                 }
-                write!(stream, "\r\n").expect("Failed to write CRLF");
-                stream
-                    .write_all(response.body())
-                    .expect("Failed to write body");
             });
         }
     }
+
+    /// Serves a single connection on the current thread.
+    ///
+    /// This is the same code path that the worker threads use to serve the
+    /// connections of the listener, so any `Transport`, including in-memory
+    /// test doubles, can be served.
+    pub fn serve<S: Transport + 'static>(&self, stream: S) {
+        let spawner = self.pool.spawner();
+        serve_connection(
+            &self.router,
+            &spawner,
+            &self.options,
+            Box::new(stream),
+            ConnectionInfo::new(),
+        );
+    }
+}
+
+/// Returns the error of a listener, whose context is its address.
+fn listener_error(listener: &dyn Listener, err: io::Error) -> Error {
+    let addr = match listener.local_addr() {
+        Ok(addr) => addr.to_string(),
+        Err(_) => String::from("listener"),
+    };
+    FailedConnection::with_context(addr).with_source(err).into()
+}
+
+/// Checks whether the failure to accept only concerns the connection that
+/// was being accepted, so that the next one can be accepted at once.
+fn is_connection_error(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionRefused
+    )
+}
+
+/// Prints the error, followed by the errors that caused it, to the standard
+/// error. This is how the server reports its errors by default.
+fn print_error(err: &Error) {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        message.push_str(": ");
+        message.push_str(&err.to_string());
+        source = err.source();
+    }
+    eprintln!("{message}");
+}
+
+/// Serves a connection with the router.
+///
+/// A connection whose client negotiated `h2` with ALPN, or starts with the
+/// HTTP/2 connection preface, is served as an HTTP/2 connection. Any other
+/// connection is served as a persistent HTTP/1 connection, whose responses
+/// are adapted to the version of their request.
+///
+/// A connection whose client sends nothing within the keep-alive timeout is
+/// closed.
+fn serve_connection<Req, Res>(
+    router: &Arc<Router<Req, Res>>,
+    spawner: &Spawner,
+    options: &Arc<ConnectionOptions>,
+    stream: Box<dyn Transport>,
+    connection: ConnectionInfo,
+) where
+    Req: FromBody + 'static,
+    Res: Body + 'static,
+{
+    let mut reader = BufReader::new(stream);
+    let h2 = connection.alpn_protocol() == Some(b"h2");
+    if !h2 && !options.wait_for_request(&mut reader) {
+        return;
+    }
+    if h2 || http2::is_preface(reader.buffer()) {
+        http2::serve(router, spawner, options, reader, connection);
+    } else {
+        http1::serve(router, spawner, options, reader, connection);
+    }
 }
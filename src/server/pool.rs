@@ -1,5 +1,5 @@
 use std::sync::mpsc::{self, Sender};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
 
 use crate::server::worker::{Job, Worker};
 
@@ -9,7 +9,7 @@ use crate::server::worker::{Job, Worker};
 #[allow(dead_code)]
 pub struct ThreadPool {
     workers: Vec<Worker>,
-    sender: Option<Sender<Job>>,
+    sender: Option<Arc<Sender<Job>>>,
 }
 
 impl ThreadPool {
@@ -33,7 +33,7 @@ impl ThreadPool {
         }
         ThreadPool {
             workers,
-            sender: Some(sender),
+            sender: Some(Arc::new(sender)),
         }
     }
 
@@ -50,6 +50,39 @@ impl ThreadPool {
             .send(job)
             .expect("Execution of job failed.");
     }
+
+    /// Returns a handle that adds jobs to the queue of the pool, and can be
+    /// moved to other threads.
+    ///
+    /// The handle does not keep the pool alive, so that a thread that holds
+    /// one does not delay the shutdown of the pool.
+    #[inline]
+    pub fn spawner(&self) -> Spawner {
+        Spawner {
+            sender: Arc::downgrade(self.sender.as_ref().expect("There is no sender.")),
+        }
+    }
+}
+
+/// A handle that adds jobs to the queue of a `ThreadPool`.
+#[derive(Clone)]
+pub struct Spawner {
+    sender: Weak<Sender<Job>>,
+}
+
+impl Spawner {
+    /// Adds a closure to the queue of jobs of the pool.
+    ///
+    /// Returns `false` if the pool has shut down, so the job is not executed.
+    pub fn execute<F>(&self, f: F) -> bool
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        match self.sender.upgrade() {
+            Some(sender) => sender.send(Box::new(f)).is_ok(),
+            None => false,
+        }
+    }
 }
 
 impl Default for ThreadPool {
@@ -1,66 +1,217 @@
 use std::collections::HashMap;
+use std::result;
 
-use super::RequestHandler;
-use crate::error::Result;
-use crate::http::{Method, Request, Response, Uri};
+use super::extract::{Params, RequestContext, StateMap};
+use super::websocket::handshake;
+use super::{ErrorHandler, ExpectHandler, RequestHandler, WebSocketHandler};
+use crate::error::{
+    AmbiguousRoute, Error, ExpectationFailed, MethodNotAllowed, NoMatchingRoute, Result,
+};
+use crate::http::date;
+use crate::http::uri::percent_decode;
+use crate::http::{HeaderName, HeaderValue, Method, Request, Response, StatusCode, Uri};
 
-pub struct Router<T> {
-    routes: HashMap<(Uri, Method), RequestHandler<T>>,
-    error_handler: RequestHandler<T>,
+pub struct Router<Req, Res> {
+    routes: HashMap<(Uri, Method), RequestHandler<Req, Res>>,
+    websockets: HashMap<Uri, WebSocketHandler<Req>>,
+    error_handler: ErrorHandler<Req, Res>,
+    status_handlers: HashMap<StatusCode, ErrorHandler<Req, Res>>,
+    expect_handler: Option<ExpectHandler>,
+    server_name: Option<HeaderValue>,
+    state: StateMap,
 }
 
-impl<T> Router<T> {
-    /// Creates a new `Router` from a map between paths and request handles.
+impl<Req, Res> Router<Req, Res> {
+    /// Creates a new `Router` from a map between paths and request handles,
+    /// a map between paths and WebSocket handlers, the handlers of errors,
+    /// the handler of `Expect: 100-continue`, the value of the `Server` header
+    /// of the responses, and the application state that is available to the
+    /// handlers.
     pub fn from(
-        routes: HashMap<(Uri, Method), RequestHandler<T>>,
-        error_handler: RequestHandler<T>,
+        routes: HashMap<(Uri, Method), RequestHandler<Req, Res>>,
+        websockets: HashMap<Uri, WebSocketHandler<Req>>,
+        error_handler: ErrorHandler<Req, Res>,
+        status_handlers: HashMap<StatusCode, ErrorHandler<Req, Res>>,
+        expect_handler: Option<ExpectHandler>,
+        server_name: Option<HeaderValue>,
+        state: StateMap,
     ) -> Self {
         Router {
             routes,
+            websockets,
             error_handler,
+            status_handlers,
+            expect_handler,
+            server_name,
+            state,
         }
     }
 
     /// Handles the request.
     ///
-    /// The request is handled based on the specified routes. In case of an error
-    /// the request is handled with the `Router`'s specified error handler.
-    pub fn handle_request(&self, request: &Request<T>) -> Result<Response<T>> {
-        match self.get_handler(request.uri(), request.method()) {
-            Some(handler) => handler(request),
-            None => self.handle_error(request),
+    /// The request is handled based on the specified routes. If no route matches
+    /// the request, or the handler of the route returns an error, the request is
+    /// handled with the `Router`'s error handlers.
+    ///
+    /// Returns an error only if the error handler fails as well.
+    pub fn handle_request(&self, request: &Request<Req>) -> Result<Response<Res>> {
+        self.get_handler(request.uri(), request.method())
+            .and_then(|(handler, params)| {
+                handler(request, &RequestContext::new(&params, &self.state))
+            })
+            .or_else(|err| self.handle_error(request, &err))
+    }
+
+    /// Handles the request in case of error.
+    ///
+    /// If a handler is registered for the status code that corresponds to the
+    /// error, it handles the error, otherwise the general error handler does.
+    pub fn handle_error(&self, request: &Request<Req>, error: &Error) -> Result<Response<Res>> {
+        match self.status_handlers.get(&error.status()) {
+            Some(handler) => handler(request, error),
+            None => (self.error_handler)(request, error),
+        }
+    }
+
+    /// Checks the `Expect` header of a request whose body has not been read.
+    ///
+    /// `100-continue` is met unless the handler of the expectation rejects the
+    /// request, and any other expectation fails with `ExpectationFailed`.
+    /// Returns whether the client waits for `100 Continue` before it sends
+    /// the body.
+    pub(crate) fn check_expectation(&self, head: &Request<()>) -> Result<bool> {
+        let Some(expect) = head.headers().get(&HeaderName::EXPECT) else {
+            return Ok(false);
+        };
+        if !expect.as_bytes().eq_ignore_ascii_case(b"100-continue") {
+            return Err(ExpectationFailed::with_context(expect.to_string()).into());
+        }
+        if let Some(handler) = &self.expect_handler {
+            handler(head)?;
+        }
+        Ok(true)
+    }
+
+    /// Adds the `Date` header and the `Server` header of the router to the
+    /// response, unless its handler has set them.
+    pub(crate) fn add_default_headers<T>(&self, response: &mut Response<T>) {
+        let headers = &mut response.headers_mut().inner;
+        headers
+            .entry(HeaderName::DATE)
+            .or_insert_with(date::date_header);
+        if let Some(server_name) = &self.server_name {
+            headers
+                .entry(HeaderName::SERVER)
+                .or_insert_with(|| server_name.clone());
+        }
+    }
+
+    /// Returns the application state that is available to the handlers.
+    #[inline]
+    pub(crate) fn state(&self) -> &StateMap {
+        &self.state
+    }
+
+    /// Gets the WebSocket handler of the route that matches the request, together
+    /// with the parameters captured from the path.
+    ///
+    /// A WebSocket route handles the requests to its path that ask for an
+    /// upgrade, as well as the others that no regular route handles, so that
+    /// they fail the handshake. Returns `None` for requests that are left to
+    /// the regular routes.
+    pub(crate) fn get_websocket_handler(
+        &self,
+        request: &Request<Req>,
+    ) -> Option<Result<(&WebSocketHandler<Req>, Params)>> {
+        let path = request.uri().path();
+        let mut handlers: Vec<_> = self
+            .websockets
+            .iter()
+            .filter_map(|(p, handler)| match_path(p, path).map(|params| (handler, params)))
+            .collect();
+        if handlers.is_empty()
+            || !handshake::is_upgrade(request)
+                && self.get_handler(request.uri(), request.method()).is_ok()
+        {
+            return None;
+        }
+        match handlers.len() {
+            1 => Some(Ok(handlers.pop().expect("Guaranteed by length."))),
+            _ => Some(Err(AmbiguousRoute::with_context(path).into())),
         }
     }
 
-    /// Handle request in case of error.
-    pub fn handle_error(&self, request: &Request<T>) -> Result<Response<T>> {
-        (self.error_handler)(request)
+    /// Returns whether a WebSocket route matches the path.
+    pub(crate) fn is_websocket_path(&self, path: &str) -> bool {
+        self.websockets
+            .keys()
+            .any(|route| match_path(route, path).is_some())
     }
 
-    /// Gets the function that handles the request, for given path.
+    /// Gets the function that handles the request, for given path, together
+    /// with the parameters captured from the path.
     ///
-    /// The request handler
-    /// Paths are expected to start with the prefix '/'.
-    fn get_handler(&self, path: &Uri, method: &Method) -> Option<&RequestHandler<T>> {
+    /// A `HEAD` request without a route of its own goes to the `GET` route,
+    /// whose body the server leaves out. Paths are expected to start with the
+    /// prefix '/'.
+    fn get_handler(
+        &self,
+        uri: &Uri,
+        method: &Method,
+    ) -> result::Result<(&RequestHandler<Req, Res>, Params), Error> {
         let mut handlers = Vec::new();
+        let mut path_matched = false;
+        let path = uri.path();
 
-        if path == "/" {
-            if let Some(root_handler) = self.routes.get(&(path.clone(), method.clone())) {
-                handlers.push(root_handler);
-            }
-        } else {
-            for ((p, m), handler) in self.routes.iter() {
-                if path.inner.starts_with(&p.inner) && (p != "/") && method == m {
-                    handlers.push(handler);
+        for ((p, m), handler) in self.routes.iter() {
+            if let Some(params) = match_path(p, path) {
+                path_matched = true;
+                if method == m {
+                    handlers.push((handler, params));
                 }
             }
         }
 
         match handlers.len() {
-            0 => None,
-            1 => Some(handlers[0]),
-            // TODO: Maybe change the return type to Result with two reasons.
-            _ => panic!("There are more than one paths matching the pattern."),
+            0 if path_matched && *method == Method::HEAD => self.get_handler(uri, &Method::GET),
+            0 if path_matched => Err(MethodNotAllowed::with_context(method.as_str()).into()),
+            0 => Err(NoMatchingRoute::with_context(path).into()),
+            1 => Ok(handlers.pop().expect("Guaranteed by length.")),
+            _ => Err(AmbiguousRoute::with_context(path).into()),
+        }
+    }
+}
+
+/// Matches a request path against the path of a route, where the root path
+/// only matches itself.
+fn match_path(route: &Uri, path: &str) -> Option<Params> {
+    match path {
+        "/" if route == "/" => Some(Params::new()),
+        "/" => None,
+        _ if route == "/" => None,
+        _ => match_route(route.path(), path),
+    }
+}
+
+/// Matches a request path against the path of a route.
+///
+/// The route matches the paths that start with its segments, so that "/files"
+/// also handles "/files/btc.html". A route segment of the form `:name` matches
+/// any path segment, and captures it as a parameter.
+fn match_route(route: &str, path: &str) -> Option<Params> {
+    let mut params = Params::new();
+    let mut segments = path.split('/').filter(|s| !s.is_empty());
+
+    for pattern in route.split('/').filter(|s| !s.is_empty()) {
+        let segment = segments.next()?;
+        match pattern.strip_prefix(':') {
+            Some(name) => {
+                let value = percent_decode(segment).unwrap_or_else(|| segment.to_string());
+                params.push(name.to_string(), value);
+            }
+            None if pattern == segment => {}
+            None => return None,
         }
     }
+    Some(params)
 }
@@ -0,0 +1,214 @@
+use std::io::{self, Write};
+use std::result;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+use super::extract::{FromRequest, Rejection, RequestContext};
+use super::Upgraded;
+use crate::error::{FailedConnection, Result};
+use crate::http::{Body, HeaderName, Request, Response};
+
+/// The default interval of the keep-alive comments.
+const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// An event of a Server-Sent Events stream.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Event {
+    event: Option<String>,
+    data: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    /// Creates an empty event.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the data of the event, which is sent as one `data` field per line.
+    #[inline]
+    pub fn data<D: Into<String>>(mut self, data: D) -> Self {
+        self.data = Some(data.into());
+        self
+    }
+
+    /// Sets the type of the event, that the browser dispatches it as.
+    ///
+    /// Line breaks are removed, since the field must fit in a single line.
+    #[inline]
+    pub fn event<E: Into<String>>(mut self, event: E) -> Self {
+        self.event = Some(single_line(event.into()));
+        self
+    }
+
+    /// Sets the ID of the event, that the browser sends back in the
+    /// `Last-Event-ID` header when it reconnects.
+    ///
+    /// Line breaks are removed, since the field must fit in a single line.
+    #[inline]
+    pub fn id<I: Into<String>>(mut self, id: I) -> Self {
+        self.id = Some(single_line(id.into()));
+        self
+    }
+
+    /// Sets the time that the browser waits before it reconnects.
+    #[inline]
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Appends the wire format of the event to the buffer.
+    fn encode(&self, buf: &mut Vec<u8>) {
+        if let Some(event) = &self.event {
+            buf.extend_from_slice(format!("event: {event}\n").as_bytes());
+        }
+        if let Some(data) = &self.data {
+            for line in data.split('\n') {
+                let line = line.strip_suffix('\r').unwrap_or(line);
+                buf.extend_from_slice(format!("data: {line}\n").as_bytes());
+            }
+        }
+        if let Some(id) = &self.id {
+            buf.extend_from_slice(format!("id: {id}\n").as_bytes());
+        }
+        if let Some(retry) = self.retry {
+            buf.extend_from_slice(format!("retry: {}\n", retry.as_millis()).as_bytes());
+        }
+        buf.push(b'\n');
+    }
+}
+
+/// The sending half of a Server-Sent Events stream.
+///
+/// It can be cloned and moved to other threads. The stream ends when every
+/// sender has been dropped.
+#[derive(Debug, Clone)]
+pub struct EventSender {
+    sender: Sender<Event>,
+}
+
+impl EventSender {
+    /// Sends an event to the client.
+    ///
+    /// Fails with `FailedConnection` once the client has disconnected.
+    pub fn send(&self, event: Event) -> Result<()> {
+        self.sender
+            .send(event)
+            .map_err(|_| FailedConnection::with_context("event stream is closed").into())
+    }
+}
+
+/// A Server-Sent Events response.
+///
+/// The response has the `text/event-stream` content type and keeps the
+/// connection open, while the worker thread writes the events that are sent
+/// through the `EventSender` to it. A comment is sent when no event has been
+/// sent for the keep-alive interval, so that proxies do not time out the
+/// connection.
+#[derive(Debug, Clone)]
+pub struct Sse {
+    keep_alive: Duration,
+}
+
+impl Default for Sse {
+    #[inline]
+    fn default() -> Self {
+        Sse {
+            keep_alive: DEFAULT_KEEP_ALIVE,
+        }
+    }
+}
+
+impl Sse {
+    /// Creates a Server-Sent Events response, whose keep-alive interval is 15 seconds.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the interval of the keep-alive comments.
+    #[inline]
+    pub fn keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = interval;
+        self
+    }
+
+    /// Returns the sender of the events, and the response that the handler returns.
+    ///
+    /// The body of the response is delimited by closing the connection, which
+    /// happens once every sender has been dropped.
+    pub fn into_response<Res>(self) -> Result<(EventSender, Response<Res>)>
+    where
+        Res: Body + Default,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let keep_alive = self.keep_alive;
+        let response = Response::builder()
+            .with_status(200)
+            .with_header(b"Content-Type", b"text/event-stream")
+            .with_header(b"Cache-Control", b"no-cache")
+            .with_header(b"Connection", b"close")
+            .with_unframed_body(Res::default())?
+            .with_upgrade(move |stream| {
+                if let Err(err) = stream_events(stream, &receiver, keep_alive) {
+                    if err.kind() != io::ErrorKind::BrokenPipe {
+                        eprintln!("Failed to send event: {err}");
+                    }
+                }
+            });
+        Ok((EventSender { sender }, response))
+    }
+}
+
+/// Extracts the `Last-Event-ID` header, that a reconnecting browser sends
+/// with the ID of the last event that it received.
+///
+/// It never fails; the value is `None` when the header is missing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LastEventId(pub Option<String>);
+
+impl<T> FromRequest<T> for LastEventId {
+    fn from_request(
+        request: &Request<T>,
+        _: &RequestContext<'_>,
+    ) -> result::Result<Self, Rejection> {
+        let id = request
+            .headers()
+            .get(&HeaderName::LAST_EVENT_ID)
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+        Ok(LastEventId(id))
+    }
+}
+
+/// Writes the received events to the stream, and a keep-alive comment
+/// whenever no event is received for the interval, until every sender has
+/// been dropped.
+fn stream_events(
+    mut stream: Upgraded,
+    receiver: &Receiver<Event>,
+    keep_alive: Duration,
+) -> io::Result<()> {
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match receiver.recv_timeout(keep_alive) {
+            Ok(event) => event.encode(&mut buf),
+            Err(RecvTimeoutError::Timeout) => buf.extend_from_slice(b": keep-alive\n\n"),
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        stream.write_all(&buf)?;
+        stream.flush()?;
+    }
+}
+
+/// Removes the line breaks of a field value.
+fn single_line(value: String) -> String {
+    if value.contains(['\r', '\n']) {
+        value.replace(['\r', '\n'], "")
+    } else {
+        value
+    }
+}
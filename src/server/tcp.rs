@@ -0,0 +1,115 @@
+use std::fmt;
+use std::io;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+use super::transport::{Listener, Transport};
+use crate::error::{FailedConnection, Result};
+
+/// The options of the TCP sockets that the server listens to.
+#[derive(Debug, Clone)]
+pub(crate) struct TcpOptions {
+    /// Sets `SO_REUSEADDR` on the listening socket.
+    pub reuse_address: bool,
+
+    /// Sets `SO_REUSEPORT` on the listening socket.
+    pub reuse_port: bool,
+
+    /// Sets `IPV6_V6ONLY` on listening sockets of IPv6 addresses.
+    pub only_v6: bool,
+
+    /// The maximum number of pending connections.
+    pub backlog: i32,
+
+    /// Sets `TCP_NODELAY` on the accepted connections.
+    pub nodelay: bool,
+}
+
+impl Default for TcpOptions {
+    #[inline]
+    fn default() -> Self {
+        TcpOptions {
+            reuse_address: cfg!(unix),
+            reuse_port: false,
+            only_v6: false,
+            backlog: 128,
+            nodelay: false,
+        }
+    }
+}
+
+/// A TCP listener that applies the socket options of the server.
+pub struct TcpSocketListener {
+    listener: TcpListener,
+    nodelay: bool,
+}
+
+impl TcpSocketListener {
+    /// Binds a listener to the first of the addresses that succeeds.
+    ///
+    /// If none succeeds, the error reports the last address that was attempted
+    /// and the I/O error of the attempt.
+    pub(crate) fn bind<A>(addr: A, options: &TcpOptions) -> Result<Self>
+    where
+        A: ToSocketAddrs + fmt::Debug,
+    {
+        let addrs = addr.to_socket_addrs().map_err(|err| {
+            FailedConnection::with_context(format!("{:?}", addr)).with_source(err)
+        })?;
+
+        let mut last_err =
+            FailedConnection::with_context(format!("{:?}", addr)).with_source(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "could not resolve to any address",
+            ));
+        for addr in addrs {
+            match bind_socket(addr, options) {
+                Ok(listener) => {
+                    return Ok(TcpSocketListener {
+                        listener,
+                        nodelay: options.nodelay,
+                    })
+                }
+                Err(err) => {
+                    last_err = FailedConnection::with_context(addr.to_string()).with_source(err)
+                }
+            }
+        }
+        Err(last_err.into())
+    }
+}
+
+impl Listener for TcpSocketListener {
+    fn accept(&self) -> io::Result<Box<dyn Transport>> {
+        let (stream, _) = self.listener.accept()?;
+        if self.nodelay {
+            stream.set_nodelay(true)?;
+        }
+        Ok(Box::new(stream) as Box<dyn Transport>)
+    }
+
+    #[inline]
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    #[inline]
+    fn unblock(&self) -> io::Result<()> {
+        Listener::unblock(&self.listener)
+    }
+}
+
+/// Creates a listening socket with the options, bound to the address.
+fn bind_socket(addr: SocketAddr, options: &TcpOptions) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_reuse_address(options.reuse_address)?;
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    socket.set_reuse_port(options.reuse_port)?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(options.only_v6)?;
+    }
+    socket.bind(&addr.into())?;
+    socket.listen(options.backlog)?;
+    Ok(socket.into())
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rustls::crypto::ring::{self, sign::any_supported_type};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+
+use super::transport::{Listener, Transport};
+use crate::error::{InvalidCertificate, Result};
+use crate::http::ConnectionInfo;
+
+/// The certificates and the ALPN protocols that the server uses to serve TLS.
+#[derive(Debug, Clone)]
+pub(crate) struct TlsConfig {
+    default: Option<Arc<CertifiedKey>>,
+    by_name: HashMap<String, Arc<CertifiedKey>>,
+    alpn_protocols: Vec<Vec<u8>>,
+}
+
+impl Default for TlsConfig {
+    #[inline]
+    fn default() -> Self {
+        TlsConfig {
+            default: None,
+            by_name: HashMap::new(),
+            alpn_protocols: vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+        }
+    }
+}
+
+impl TlsConfig {
+    /// Sets the certificate that is used when no certificate matches the
+    /// server name that the client requested.
+    #[inline]
+    pub fn set_default(&mut self, key: CertifiedKey) {
+        self.default = Some(Arc::new(key));
+    }
+
+    /// Sets the certificate that is used when the client requests the server name.
+    #[inline]
+    pub fn insert(&mut self, server_name: &str, key: CertifiedKey) {
+        self.by_name
+            .insert(normalize_name(server_name), Arc::new(key));
+    }
+
+    /// Sets the protocols that the server offers through ALPN, in order of preference.
+    #[inline]
+    pub fn set_alpn_protocols(&mut self, protocols: Vec<Vec<u8>>) {
+        self.alpn_protocols = protocols;
+    }
+
+    /// Returns the configuration of the TLS connections, or `None` if no
+    /// certificate has been set.
+    pub fn server_config(&self) -> Result<Option<Arc<ServerConfig>>> {
+        if self.default.is_none() && self.by_name.is_empty() {
+            return Ok(None);
+        }
+        let resolver = CertResolver {
+            default: self.default.clone(),
+            by_name: self.by_name.clone(),
+        };
+        let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|err| InvalidCertificate::new().with_source(err))?
+            .with_no_client_auth()
+            .with_cert_resolver(Arc::new(resolver));
+        config.alpn_protocols = self.alpn_protocols.clone();
+        Ok(Some(Arc::new(config)))
+    }
+}
+
+/// Loads a certificate chain and its private key from PEM files.
+pub(crate) fn load_certified_key<C, K>(cert_path: C, key_path: K) -> Result<CertifiedKey>
+where
+    C: AsRef<Path>,
+    K: AsRef<Path>,
+{
+    let cert_path = cert_path.as_ref();
+    let key_path = key_path.as_ref();
+    let invalid_cert = || InvalidCertificate::with_context(cert_path.display().to_string());
+    let invalid_key = || InvalidCertificate::with_context(key_path.display().to_string());
+
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<std::result::Result<Vec<_>, _>>())
+        .map_err(|err| invalid_cert().with_source(err))?;
+    if certs.is_empty() {
+        return Err(invalid_cert().into());
+    }
+    let key =
+        PrivateKeyDer::from_pem_file(key_path).map_err(|err| invalid_key().with_source(err))?;
+    let key = any_supported_type(&key).map_err(|err| invalid_key().with_source(err))?;
+
+    let certified = CertifiedKey::new(certs, key);
+    match certified.keys_match() {
+        Ok(()) | Err(rustls::Error::InconsistentKeys(rustls::InconsistentKeys::Unknown)) => {
+            Ok(certified)
+        }
+        Err(err) => Err(invalid_key().with_source(err).into()),
+    }
+}
+
+/// Selects the certificate that matches the server name that the client
+/// requested through SNI.
+#[derive(Debug)]
+struct CertResolver {
+    default: Option<Arc<CertifiedKey>>,
+    by_name: HashMap<String, Arc<CertifiedKey>>,
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        client_hello
+            .server_name()
+            .and_then(|name| self.by_name.get(&normalize_name(name)))
+            .or(self.default.as_ref())
+            .cloned()
+    }
+}
+
+/// A listener that serves TLS over the connections of another listener.
+///
+/// The TLS handshake is performed on the worker thread that serves the
+/// connection, and the protocol negotiated through ALPN and the server name
+/// requested through SNI are recorded in the `ConnectionInfo` of the requests.
+pub struct TlsListener<L> {
+    listener: L,
+    config: Arc<ServerConfig>,
+}
+
+impl<L: Listener> TlsListener<L> {
+    /// Creates a listener that serves TLS with the configuration over the
+    /// connections of the listener.
+    #[inline]
+    pub fn new(listener: L, config: Arc<ServerConfig>) -> Self {
+        TlsListener { listener, config }
+    }
+}
+
+impl<L: Listener> Listener for TlsListener<L> {
+    #[inline]
+    fn accept(&self) -> io::Result<Box<dyn Transport>> {
+        self.listener.accept()
+    }
+
+    #[inline]
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    fn establish(
+        &self,
+        stream: Box<dyn Transport>,
+    ) -> io::Result<(Box<dyn Transport>, ConnectionInfo)> {
+        let (stream, mut connection) = self.listener.establish(stream)?;
+        let conn = ServerConnection::new(Arc::clone(&self.config)).map_err(io::Error::other)?;
+        let mut tls = StreamOwned::new(conn, stream);
+        while tls.conn.is_handshaking() {
+            if tls.conn.complete_io(&mut tls.sock)? == (0, 0) {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+
+        if let Some(protocol) = tls.conn.alpn_protocol() {
+            connection = connection.with_alpn_protocol(protocol);
+        }
+        if let Some(name) = tls.conn.server_name() {
+            connection = connection.with_server_name(name);
+        }
+        Ok((Box::new(tls), connection))
+    }
+
+    #[inline]
+    fn unblock(&self) -> io::Result<()> {
+        self.listener.unblock()
+    }
+}
+
+impl<T: Transport> Transport for StreamOwned<ServerConnection, T> {
+    #[inline]
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.sock.set_read_timeout(timeout)
+    }
+}
+
+/// Returns the server name in the form that certificates are looked up by.
+fn normalize_name(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
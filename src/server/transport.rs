@@ -0,0 +1,85 @@
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+
+pub use crate::http::transport::Transport;
+use crate::http::ConnectionInfo;
+
+/// A source of connections that the server accepts and serves.
+pub trait Listener: Send + Sync {
+    /// Blocks until a new connection is established, and returns it.
+    fn accept(&self) -> io::Result<Box<dyn Transport>>;
+
+    /// Returns the socket address that the listener is bound to.
+    ///
+    /// Fails for listeners that are not bound to a socket address.
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Establishes an accepted connection, e.g. by performing a TLS handshake,
+    /// and returns the stream to serve with the information of the connection.
+    ///
+    /// It is called on the worker thread that serves the connection, so that a
+    /// slow client does not hold up `accept`. By default, the stream is served
+    /// as it is.
+    fn establish(
+        &self,
+        stream: Box<dyn Transport>,
+    ) -> io::Result<(Box<dyn Transport>, ConnectionInfo)> {
+        Ok((stream, ConnectionInfo::new()))
+    }
+
+    /// Makes a blocked call to `accept` return, e.g. by connecting to the listener.
+    ///
+    /// The server calls it on shutdown, after which the accepted connection
+    /// is dropped.
+    fn unblock(&self) -> io::Result<()>;
+}
+
+impl Listener for TcpListener {
+    fn accept(&self) -> io::Result<Box<dyn Transport>> {
+        let (stream, _) = TcpListener::accept(self)?;
+        Ok(Box::new(stream) as Box<dyn Transport>)
+    }
+
+    #[inline]
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        TcpListener::local_addr(self)
+    }
+
+    fn unblock(&self) -> io::Result<()> {
+        let mut addr = TcpListener::local_addr(self)?;
+        if addr.ip().is_unspecified() {
+            match addr {
+                SocketAddr::V4(_) => addr.set_ip(Ipv4Addr::LOCALHOST.into()),
+                SocketAddr::V6(_) => addr.set_ip(Ipv6Addr::LOCALHOST.into()),
+            }
+        }
+        TcpStream::connect(addr).map(|_| ())
+    }
+}
+
+impl<L: Listener + ?Sized> Listener for Box<L> {
+    #[inline]
+    fn accept(&self) -> io::Result<Box<dyn Transport>> {
+        (**self).accept()
+    }
+
+    #[inline]
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        (**self).local_addr()
+    }
+
+    #[inline]
+    fn establish(
+        &self,
+        stream: Box<dyn Transport>,
+    ) -> io::Result<(Box<dyn Transport>, ConnectionInfo)> {
+        (**self).establish(stream)
+    }
+
+    #[inline]
+    fn unblock(&self) -> io::Result<()> {
+        (**self).unblock()
+    }
+}
@@ -0,0 +1,95 @@
+use std::fs;
+use std::io;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+
+use super::transport::{Listener, Transport};
+
+/// A listener on a Unix domain socket, that removes the socket file when dropped.
+pub struct UnixSocketListener {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl UnixSocketListener {
+    /// Binds a listener to the socket at the specified path.
+    ///
+    /// A stale socket file, left behind by a server that did not shut down
+    /// cleanly, is removed first. A socket that still accepts connections, or
+    /// a path that is not a socket, is left untouched and the bind fails. If a
+    /// mode is given, the permissions of the socket file are set to it.
+    pub fn bind<P: AsRef<Path>>(path: P, mode: Option<u32>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        remove_stale_socket(&path)?;
+
+        let listener = UnixListener::bind(&path)?;
+        let listener = UnixSocketListener { listener, path };
+        if let Some(mode) = mode {
+            fs::set_permissions(&listener.path, fs::Permissions::from_mode(mode))?;
+        }
+        Ok(listener)
+    }
+
+    /// Returns the path of the socket file.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Listener for UnixSocketListener {
+    #[inline]
+    fn accept(&self) -> io::Result<Box<dyn Transport>> {
+        Listener::accept(&self.listener)
+    }
+
+    #[inline]
+    fn unblock(&self) -> io::Result<()> {
+        Listener::unblock(&self.listener)
+    }
+}
+
+impl Drop for UnixSocketListener {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl Listener for UnixListener {
+    fn accept(&self) -> io::Result<Box<dyn Transport>> {
+        let (stream, _) = UnixListener::accept(self)?;
+        Ok(Box::new(stream) as Box<dyn Transport>)
+    }
+
+    fn unblock(&self) -> io::Result<()> {
+        let addr = UnixListener::local_addr(self)?;
+        let path = addr
+            .as_pathname()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::Unsupported))?;
+        UnixStream::connect(path).map(|_| ())
+    }
+}
+
+/// Removes the socket file at the path, if no server is listening on it.
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "path exists and is not a socket",
+        ));
+    }
+    match UnixStream::connect(path) {
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            "socket is in use by another server",
+        )),
+        Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => fs::remove_file(path),
+        Err(err) => Err(err),
+    }
+}
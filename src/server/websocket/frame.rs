@@ -0,0 +1,76 @@
+use std::io::{self, Write};
+
+/// The opcode of a WebSocket frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OpCode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl OpCode {
+    /// Returns the opcode of the value, or `None` if it is reserved.
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x0 => Some(OpCode::Continuation),
+            0x1 => Some(OpCode::Text),
+            0x2 => Some(OpCode::Binary),
+            0x8 => Some(OpCode::Close),
+            0x9 => Some(OpCode::Ping),
+            0xA => Some(OpCode::Pong),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the opcode on the wire.
+    pub fn as_u8(self) -> u8 {
+        match self {
+            OpCode::Continuation => 0x0,
+            OpCode::Text => 0x1,
+            OpCode::Binary => 0x2,
+            OpCode::Close => 0x8,
+            OpCode::Ping => 0x9,
+            OpCode::Pong => 0xA,
+        }
+    }
+
+    /// Returns whether frames of the opcode are control frames, which cannot
+    /// be fragmented and carry at most 125 bytes.
+    #[inline]
+    pub fn is_control(self) -> bool {
+        self.as_u8() & 0x8 != 0
+    }
+}
+
+/// Writes a final, unmasked frame, as frames from the server are never masked.
+pub(crate) fn write_frame<W>(writer: &mut W, opcode: OpCode, payload: &[u8]) -> io::Result<()>
+where
+    W: Write + ?Sized,
+{
+    let mut head = Vec::with_capacity(10 + payload.len());
+    head.push(0x80 | opcode.as_u8());
+    match payload.len() {
+        len @ 0..=125 => head.push(len as u8),
+        len @ 126..=0xFFFF => {
+            head.push(126);
+            head.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            head.push(127);
+            head.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    head.extend_from_slice(payload);
+    writer.write_all(&head)?;
+    writer.flush()
+}
+
+/// Unmasks the payload of a frame from the client with the masking key.
+pub(crate) fn unmask(payload: &mut [u8], key: [u8; 4]) {
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= key[i % 4];
+    }
+}
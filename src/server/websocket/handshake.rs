@@ -0,0 +1,126 @@
+use crate::error::{Error, ErrorKind, Result, UnsupportedWebSocketVersion, WebSocketError};
+use crate::http::{base64, HeaderName, HeaderValue, Method, Request, Response, Version};
+
+/// The GUID that the client key is concatenated with, as defined by RFC 6455.
+const GUID: &[u8] = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The only version of the protocol that the server speaks.
+const VERSION: &str = "13";
+
+/// Returns whether the request asks to upgrade the connection to the WebSocket protocol.
+pub(crate) fn is_upgrade<T>(request: &Request<T>) -> bool {
+    request
+        .headers()
+        .has_token(&HeaderName::UPGRADE, "websocket")
+}
+
+/// Validates the opening handshake of the request, and returns the
+/// `101 Switching Protocols` response that completes it.
+pub(crate) fn accept<T>(request: &Request<T>) -> Result<Response<()>> {
+    if *request.method() != Method::GET {
+        return Err(WebSocketError::with_context("handshake method is not GET").into());
+    }
+    if *request.version() != Version::HTTP_11 {
+        return Err(WebSocketError::with_context("handshake version is not HTTP/1.1").into());
+    }
+    if !is_upgrade(request) {
+        return Err(WebSocketError::with_context("missing Upgrade: websocket").into());
+    }
+    if !request
+        .headers()
+        .has_token(&HeaderName::CONNECTION, "upgrade")
+    {
+        return Err(WebSocketError::with_context("missing Connection: Upgrade").into());
+    }
+    match request.headers().get(&HeaderName::SEC_WEBSOCKET_VERSION) {
+        Some(version) if version.as_bytes().as_ref() == VERSION.as_bytes() => {}
+        Some(version) => {
+            return Err(UnsupportedWebSocketVersion::with_context(version.to_string()).into())
+        }
+        None => return Err(WebSocketError::with_context("missing Sec-WebSocket-Version").into()),
+    }
+    let key = request
+        .headers()
+        .get(&HeaderName::SEC_WEBSOCKET_KEY)
+        .map(|key| key.as_bytes().trim_ascii())
+        .filter(|key| base64::decode(key).is_some_and(|nonce| nonce.len() == 16))
+        .ok_or_else(|| WebSocketError::with_context("invalid Sec-WebSocket-Key"))?;
+
+    Response::builder()
+        .with_status(101)
+        .with_header(b"Upgrade", b"websocket")
+        .with_header(b"Connection", b"Upgrade")
+        .with_header(b"Sec-WebSocket-Accept", accept_key(key).as_bytes())
+        .with_body(())
+}
+
+/// Adds the version that the server speaks to the response of a handshake
+/// that failed with `UnsupportedWebSocketVersion`, so that the client can
+/// retry with it.
+pub(crate) fn advertise_version<T>(response: &mut Response<T>, err: &Error) {
+    if let ErrorKind::WebSocketVersion(_) = err.kind() {
+        response.headers_mut().inner.insert(
+            HeaderName::SEC_WEBSOCKET_VERSION,
+            HeaderValue::from_static(VERSION),
+        );
+    }
+}
+
+/// Returns the `Sec-WebSocket-Accept` value that corresponds to the client key.
+fn accept_key(key: &[u8]) -> String {
+    let mut input = key.to_vec();
+    input.extend_from_slice(GUID);
+    base64::encode(&sha1(&input))
+}
+
+/// Computes the SHA-1 digest of the input.
+fn sha1(input: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut message = input.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((input.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0_u32; 80];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0_u8; 20];
+    for (chunk, s) in digest.chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&s.to_be_bytes());
+    }
+    digest
+}
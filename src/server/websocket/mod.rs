@@ -0,0 +1,520 @@
+pub(crate) mod frame;
+pub(crate) mod handshake;
+
+use std::io::Read;
+
+use frame::OpCode;
+
+use super::Upgraded;
+use crate::error::{Result, WebSocketError};
+
+/// The default limit of the size of a message, 16 MiB.
+const DEFAULT_MAX_MESSAGE_SIZE: usize = 16 << 20;
+
+/// A message of the WebSocket protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// A UTF-8 text message.
+    Text(String),
+
+    /// A binary message.
+    Binary(Vec<u8>),
+
+    /// A ping, that is answered with a pong automatically when read.
+    Ping(Vec<u8>),
+
+    /// A pong, in answer to a ping or unsolicited.
+    Pong(Vec<u8>),
+
+    /// A close message, with an optional status code and reason.
+    Close(Option<CloseFrame>),
+}
+
+impl From<String> for Message {
+    #[inline]
+    fn from(text: String) -> Self {
+        Message::Text(text)
+    }
+}
+
+impl From<&str> for Message {
+    #[inline]
+    fn from(text: &str) -> Self {
+        Message::Text(text.to_string())
+    }
+}
+
+impl From<Vec<u8>> for Message {
+    #[inline]
+    fn from(data: Vec<u8>) -> Self {
+        Message::Binary(data)
+    }
+}
+
+impl From<&[u8]> for Message {
+    #[inline]
+    fn from(data: &[u8]) -> Self {
+        Message::Binary(data.to_vec())
+    }
+}
+
+/// The status code and the reason of a close message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloseFrame {
+    /// The status code, e.g. `1000` for a normal closure.
+    pub code: u16,
+
+    /// The reason of the closure, that is meant for humans.
+    pub reason: String,
+}
+
+impl CloseFrame {
+    /// `1000`: the purpose of the connection has been fulfilled.
+    pub const NORMAL: u16 = 1000;
+
+    /// `1001`: the endpoint is going away.
+    pub const GOING_AWAY: u16 = 1001;
+
+    /// `1002`: the peer violated the protocol.
+    pub const PROTOCOL_ERROR: u16 = 1002;
+
+    /// `1007`: a message had data that is inconsistent with its type.
+    pub const INVALID_DATA: u16 = 1007;
+
+    /// `1009`: a message was too big to process.
+    pub const TOO_BIG: u16 = 1009;
+
+    /// Creates a close frame of the status code and the reason.
+    #[inline]
+    pub fn new<R: Into<String>>(code: u16, reason: R) -> Self {
+        CloseFrame {
+            code,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// A WebSocket connection, that sends and receives whole messages.
+///
+/// It is given to the handlers of the routes that are registered with
+/// `Builder::websocket`, after the opening handshake has completed. Fragmented
+/// messages are reassembled, pings are answered with pongs and a close message
+/// from the client is answered before it is returned. If the socket is dropped
+/// before it is closed, it closes the connection with a normal closure.
+pub struct WebSocket {
+    stream: Upgraded,
+    fragments: Option<(OpCode, Vec<u8>)>,
+    max_message_size: usize,
+    close_sent: bool,
+    close_received: bool,
+}
+
+impl WebSocket {
+    /// Creates a WebSocket over a connection whose handshake has completed.
+    ///
+    /// The connection may already hold frames that the client has sent.
+    pub(crate) fn new(stream: Upgraded) -> Self {
+        WebSocket {
+            stream,
+            fragments: None,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            close_sent: false,
+            close_received: false,
+        }
+    }
+
+    /// Sets the limit of the size of the messages that are received.
+    ///
+    /// A bigger message closes the connection with status code `1009`.
+    #[inline]
+    pub fn set_max_message_size(&mut self, size: usize) {
+        self.max_message_size = size;
+    }
+
+    /// Returns whether a close message has been both sent and received.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.close_sent && self.close_received
+    }
+
+    /// Blocks until a whole message is received, and returns it.
+    ///
+    /// If the client violates the protocol, the connection is closed with the
+    /// corresponding status code, and an error is returned.
+    pub fn read(&mut self) -> Result<Message> {
+        if self.close_received {
+            return Err(WebSocketError::with_context("connection is closed").into());
+        }
+        loop {
+            let (fin, opcode, payload) = self.read_frame()?;
+            match opcode {
+                OpCode::Ping => {
+                    if !self.close_sent {
+                        frame::write_frame(&mut self.stream, OpCode::Pong, &payload)?;
+                    }
+                    return Ok(Message::Ping(payload));
+                }
+                OpCode::Pong => return Ok(Message::Pong(payload)),
+                OpCode::Close => return self.receive_close(&payload),
+                OpCode::Text | OpCode::Binary if self.fragments.is_some() => {
+                    return Err(self.fail(CloseFrame::PROTOCOL_ERROR, "expected continuation"));
+                }
+                OpCode::Text | OpCode::Binary if fin => return self.message(opcode, payload),
+                OpCode::Text | OpCode::Binary => self.fragments = Some((opcode, payload)),
+                OpCode::Continuation => {
+                    let Some((_, data)) = self.fragments.as_mut() else {
+                        return Err(
+                            self.fail(CloseFrame::PROTOCOL_ERROR, "unexpected continuation")
+                        );
+                    };
+                    if data.len() + payload.len() > self.max_message_size {
+                        return Err(self.fail(CloseFrame::TOO_BIG, "message is too big"));
+                    }
+                    data.extend_from_slice(&payload);
+                    if fin {
+                        let (opcode, data) = self.fragments.take().expect("Checked above.");
+                        return self.message(opcode, data);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sends a message.
+    ///
+    /// Sending a close message starts the closing handshake; no other message
+    /// can be sent after it.
+    pub fn send<M: Into<Message>>(&mut self, message: M) -> Result<()> {
+        if self.close_sent {
+            return Err(WebSocketError::with_context("connection is closed").into());
+        }
+        let stream = &mut self.stream;
+        match message.into() {
+            Message::Text(text) => frame::write_frame(stream, OpCode::Text, text.as_bytes())?,
+            Message::Binary(data) => frame::write_frame(stream, OpCode::Binary, &data)?,
+            Message::Ping(data) | Message::Pong(data) if data.len() > 125 => {
+                return Err(WebSocketError::with_context("control message is too big").into());
+            }
+            Message::Ping(data) => frame::write_frame(stream, OpCode::Ping, &data)?,
+            Message::Pong(data) => frame::write_frame(stream, OpCode::Pong, &data)?,
+            Message::Close(close) => {
+                let payload = close_payload(close.as_ref());
+                if payload.len() > 125 {
+                    return Err(WebSocketError::with_context("control message is too big").into());
+                }
+                self.close_sent = true;
+                frame::write_frame(stream, OpCode::Close, &payload)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts the closing handshake with an optional status code and reason.
+    ///
+    /// The client answers with a close message, which `read` returns.
+    #[inline]
+    pub fn close(&mut self, close: Option<CloseFrame>) -> Result<()> {
+        self.send(Message::Close(close))
+    }
+
+    /// Reads a frame, and returns whether it is final, its opcode and its
+    /// unmasked payload.
+    fn read_frame(&mut self) -> Result<(bool, OpCode, Vec<u8>)> {
+        let mut head = [0_u8; 2];
+        self.stream.read_exact(&mut head)?;
+        let fin = head[0] & 0x80 != 0;
+        if head[0] & 0x70 != 0 {
+            return Err(self.fail(CloseFrame::PROTOCOL_ERROR, "reserved bits are set"));
+        }
+        let Some(opcode) = OpCode::from_u8(head[0] & 0x0F) else {
+            return Err(self.fail(CloseFrame::PROTOCOL_ERROR, "reserved opcode"));
+        };
+        if head[1] & 0x80 == 0 {
+            return Err(self.fail(CloseFrame::PROTOCOL_ERROR, "frame is not masked"));
+        }
+
+        let len = match head[1] & 0x7F {
+            126 => {
+                let mut len = [0_u8; 2];
+                self.stream.read_exact(&mut len)?;
+                u64::from(u16::from_be_bytes(len))
+            }
+            127 => {
+                let mut len = [0_u8; 8];
+                self.stream.read_exact(&mut len)?;
+                u64::from_be_bytes(len)
+            }
+            len => u64::from(len),
+        };
+        if opcode.is_control() && (!fin || len > 125) {
+            return Err(self.fail(CloseFrame::PROTOCOL_ERROR, "invalid control frame"));
+        }
+        let len = match usize::try_from(len) {
+            Ok(len) if len <= self.max_message_size => len,
+            _ => return Err(self.fail(CloseFrame::TOO_BIG, "message is too big")),
+        };
+
+        let mut key = [0_u8; 4];
+        self.stream.read_exact(&mut key)?;
+        let mut payload = vec![0_u8; len];
+        self.stream.read_exact(&mut payload)?;
+        frame::unmask(&mut payload, key);
+        Ok((fin, opcode, payload))
+    }
+
+    /// Returns the message of the opcode and the data, that have been reassembled.
+    fn message(&mut self, opcode: OpCode, data: Vec<u8>) -> Result<Message> {
+        match opcode {
+            OpCode::Text => match String::from_utf8(data) {
+                Ok(text) => Ok(Message::Text(text)),
+                Err(_) => Err(self.fail(CloseFrame::INVALID_DATA, "text is not UTF-8")),
+            },
+            _ => Ok(Message::Binary(data)),
+        }
+    }
+
+    /// Handles a close message from the client, and answers it if the server
+    /// has not started the closing handshake.
+    fn receive_close(&mut self, payload: &[u8]) -> Result<Message> {
+        let close = match payload {
+            [] => None,
+            [_] => return Err(self.fail(CloseFrame::PROTOCOL_ERROR, "invalid close frame")),
+            [high, low, reason @ ..] => {
+                let code = u16::from_be_bytes([*high, *low]);
+                if !is_valid_close_code(code) {
+                    return Err(self.fail(CloseFrame::PROTOCOL_ERROR, "invalid close code"));
+                }
+                let Ok(reason) = std::str::from_utf8(reason) else {
+                    return Err(self.fail(CloseFrame::INVALID_DATA, "reason is not UTF-8"));
+                };
+                Some(CloseFrame::new(code, reason))
+            }
+        };
+        self.close_received = true;
+        if !self.close_sent {
+            self.close_sent = true;
+            let answer = close.as_ref().map(|close| CloseFrame::new(close.code, ""));
+            frame::write_frame(
+                &mut self.stream,
+                OpCode::Close,
+                &close_payload(answer.as_ref()),
+            )?;
+        }
+        Ok(Message::Close(close))
+    }
+
+    /// Closes the connection because the client violated the protocol, and
+    /// returns the error that describes the violation.
+    fn fail(&mut self, code: u16, reason: &'static str) -> crate::error::Error {
+        if !self.close_sent {
+            self.close_sent = true;
+            let close = CloseFrame::new(code, reason);
+            let _ = frame::write_frame(
+                &mut self.stream,
+                OpCode::Close,
+                &close_payload(Some(&close)),
+            );
+        }
+        self.close_received = true;
+        WebSocketError::with_context(reason).into()
+    }
+}
+
+impl Drop for WebSocket {
+    fn drop(&mut self) {
+        if !self.close_sent {
+            let _ = self.close(Some(CloseFrame::new(CloseFrame::NORMAL, "")));
+        }
+    }
+}
+
+/// Returns the payload of a close frame.
+fn close_payload(close: Option<&CloseFrame>) -> Vec<u8> {
+    match close {
+        Some(close) => {
+            let mut payload = close.code.to_be_bytes().to_vec();
+            payload.extend_from_slice(close.reason.as_bytes());
+            payload
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Returns whether a close frame from a peer may carry the status code.
+fn is_valid_close_code(code: u16) -> bool {
+    matches!(code, 1000..=1003 | 1007..=1014 | 3000..=4999)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, BufReader, Cursor, Write};
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::http::transport::Transport;
+
+    /// A connection that reads the frames of the client from memory, and
+    /// keeps what the server writes.
+    struct Memory {
+        input: Cursor<Vec<u8>>,
+        output: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl Read for Memory {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Memory {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Transport for Memory {}
+
+    /// Returns a socket that reads the frames, and the bytes it writes.
+    fn websocket(frames: &[Vec<u8>]) -> (WebSocket, Arc<Mutex<Vec<u8>>>) {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let memory = Memory {
+            input: Cursor::new(frames.concat()),
+            output: Arc::clone(&output),
+        };
+        let stream: Box<dyn Transport> = Box::new(memory);
+        (
+            WebSocket::new(Upgraded::new(BufReader::new(stream))),
+            output,
+        )
+    }
+
+    /// Returns a frame from the client, masked with the key if there is one.
+    fn client_frame(fin: bool, opcode: OpCode, payload: &[u8], key: Option<[u8; 4]>) -> Vec<u8> {
+        let mut frame = vec![u8::from(fin) << 7 | opcode.as_u8()];
+        let mask = if key.is_some() { 0x80 } else { 0 };
+        match payload.len() {
+            len @ 0..=125 => frame.push(mask | len as u8),
+            len => {
+                frame.push(mask | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+        }
+        let mut payload = payload.to_vec();
+        if let Some(key) = key {
+            frame.extend_from_slice(&key);
+            frame::unmask(&mut payload, key);
+        }
+        frame.extend_from_slice(&payload);
+        frame
+    }
+
+    fn masked(fin: bool, opcode: OpCode, payload: &[u8]) -> Vec<u8> {
+        client_frame(fin, opcode, payload, Some([0x37, 0xFA, 0x21, 0x3D]))
+    }
+
+    /// Returns the opcodes and payloads of the unmasked frames of the server.
+    fn server_frames(output: &Mutex<Vec<u8>>) -> Vec<(OpCode, Vec<u8>)> {
+        let output = output.lock().unwrap();
+        let mut frames = Vec::new();
+        let mut rest = &output[..];
+        while let [first, second, tail @ ..] = rest {
+            assert_eq!(first & 0x80, 0x80, "frames of the server are final");
+            assert_eq!(second & 0x80, 0, "frames of the server are not masked");
+            let (len, tail) = match second & 0x7F {
+                126 => (
+                    usize::from(u16::from_be_bytes([tail[0], tail[1]])),
+                    &tail[2..],
+                ),
+                len => (usize::from(len), tail),
+            };
+            let opcode = OpCode::from_u8(first & 0x0F).unwrap();
+            frames.push((opcode, tail[..len].to_vec()));
+            rest = &tail[len..];
+        }
+        frames
+    }
+
+    /// Returns the status code of the last frame of the server, which must
+    /// be a close frame.
+    fn close_code(output: &Mutex<Vec<u8>>) -> u16 {
+        let (opcode, payload) = server_frames(output).pop().unwrap();
+        assert_eq!(opcode, OpCode::Close);
+        u16::from_be_bytes([payload[0], payload[1]])
+    }
+
+    #[test]
+    fn round_trips_masked_frames() {
+        let long = vec![7_u8; 300];
+        let (mut socket, output) = websocket(&[
+            masked(true, OpCode::Text, "héllo".as_bytes()),
+            masked(true, OpCode::Ping, b"ping"),
+            masked(false, OpCode::Binary, &long[..100]),
+            masked(true, OpCode::Continuation, &long[100..]),
+        ]);
+
+        let text = socket.read().unwrap();
+        assert_eq!(text, Message::Text("héllo".to_string()));
+        socket.send(text).unwrap();
+        assert_eq!(socket.read().unwrap(), Message::Ping(b"ping".to_vec()));
+        let binary = socket.read().unwrap();
+        assert_eq!(binary, Message::Binary(long.clone()));
+        socket.send(binary).unwrap();
+
+        assert_eq!(
+            server_frames(&output),
+            [
+                (OpCode::Text, "héllo".as_bytes().to_vec()),
+                (OpCode::Pong, b"ping".to_vec()),
+                (OpCode::Binary, long),
+            ]
+        );
+    }
+
+    #[test]
+    fn closes_with_protocol_error_on_unmasked_frames() {
+        let (mut socket, output) = websocket(&[client_frame(true, OpCode::Text, b"hello", None)]);
+        assert!(socket.read().is_err());
+        assert_eq!(close_code(&output), CloseFrame::PROTOCOL_ERROR);
+        assert!(socket.is_closed());
+        assert!(socket.read().is_err());
+        assert!(socket.send("hello").is_err());
+    }
+
+    #[test]
+    fn closes_with_invalid_data_on_fragmented_text_that_is_not_utf8() {
+        // A character may be split across fragments.
+        let (mut socket, _) = websocket(&[
+            masked(false, OpCode::Text, &[b'a', 0xC3]),
+            masked(true, OpCode::Continuation, &[0xA9]),
+        ]);
+        assert_eq!(socket.read().unwrap(), Message::Text("aé".to_string()));
+
+        let (mut socket, output) = websocket(&[
+            masked(false, OpCode::Text, &[b'a', 0xC3]),
+            masked(true, OpCode::Continuation, &[0xFF]),
+        ]);
+        assert!(socket.read().is_err());
+        assert_eq!(close_code(&output), CloseFrame::INVALID_DATA);
+    }
+
+    #[test]
+    fn closes_with_too_big_on_oversized_messages() {
+        let (mut socket, output) = websocket(&[masked(true, OpCode::Binary, b"hello")]);
+        socket.set_max_message_size(4);
+        assert!(socket.read().is_err());
+        assert_eq!(close_code(&output), CloseFrame::TOO_BIG);
+
+        // Nor can the fragments of a message add up to more than the limit.
+        let (mut socket, output) = websocket(&[
+            masked(false, OpCode::Binary, b"hel"),
+            masked(true, OpCode::Continuation, b"lo"),
+        ]);
+        socket.set_max_message_size(4);
+        assert!(socket.read().is_err());
+        assert_eq!(close_code(&output), CloseFrame::TOO_BIG);
+    }
+}
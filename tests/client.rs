@@ -0,0 +1,322 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use bytes::Bytes;
+
+use tiny_http::client::Client;
+use tiny_http::error::{Error, ErrorKind, Result};
+use tiny_http::http::{Request, Response};
+use tiny_http::server::build::Builder;
+use tiny_http::server::transport::Listener;
+use tiny_http::server::{HttpServer, ServerHandle, Transport};
+
+type Server = ServerHandle<Bytes, Bytes>;
+
+/// A listener that counts the connections that it accepts.
+struct Counting {
+    inner: TcpListener,
+    accepted: Arc<AtomicUsize>,
+}
+
+impl Listener for Counting {
+    fn accept(&self) -> io::Result<Box<dyn Transport>> {
+        let stream = Listener::accept(&self.inner)?;
+        self.accepted.fetch_add(1, Ordering::SeqCst);
+        Ok(stream)
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    fn unblock(&self) -> io::Result<()> {
+        self.inner.unblock()
+    }
+}
+
+fn builder() -> Builder<Bytes, Bytes> {
+    HttpServer::build()
+        .workers(4)
+        .route("/echo", "GET", echo)
+        .route("/echo", "POST", echo)
+        .route("/auth", "GET", |request: &Request<Bytes>| {
+            let auth = match request.get_header("Authorization") {
+                Some(value) => value.as_bytes().to_vec(),
+                None => b"none".to_vec(),
+            };
+            ok(auth)
+        })
+        .route("/slow", "GET", |_: &Request<Bytes>| {
+            thread::sleep(Duration::from_millis(500));
+            ok("slow")
+        })
+        .route("/loop", "GET", |_: &Request<Bytes>| redirect(302, "/loop"))
+        .route("/https", "GET", |_: &Request<Bytes>| {
+            redirect(301, "https://localhost/echo")
+        })
+        .route_err(|_: &Request<Bytes>, err: &Error| {
+            Response::builder()
+                .with_status(err.status())
+                .with_body(Bytes::new())
+        })
+}
+
+/// Starts the server on an ephemeral port, and returns its handle and the
+/// URI of its root.
+fn start(builder: Builder<Bytes, Bytes>) -> (Server, String) {
+    let server = builder.bind("127.0.0.1:0").unwrap();
+    let uri = format!("http://{}", server.local_addr().unwrap());
+    (server.spawn(), uri)
+}
+
+/// Starts the server on an ephemeral port, and returns its handle, the URI of
+/// its root and the number of connections that it has accepted.
+fn start_counting(builder: Builder<Bytes, Bytes>) -> (Server, String, Arc<AtomicUsize>) {
+    let accepted = Arc::new(AtomicUsize::new(0));
+    let listener = Counting {
+        inner: TcpListener::bind("127.0.0.1:0").unwrap(),
+        accepted: Arc::clone(&accepted),
+    };
+    let server = builder.listen(listener).unwrap();
+    let uri = format!("http://{}", server.local_addr().unwrap());
+    (server.spawn(), uri, accepted)
+}
+
+fn echo(request: &Request<Bytes>) -> Result<Response<Bytes>> {
+    let mut body = request.method().as_str().as_bytes().to_vec();
+    body.push(b' ');
+    body.extend_from_slice(request.body());
+    ok(body)
+}
+
+fn ok(body: impl Into<Bytes>) -> Result<Response<Bytes>> {
+    Response::builder().with_status(200).with_body(body.into())
+}
+
+fn redirect(status: u16, location: &str) -> Result<Response<Bytes>> {
+    Response::builder()
+        .with_status(status)
+        .with_header(b"Location", location.as_bytes())
+        .with_body(Bytes::new())
+}
+
+fn request(method: &str, uri: &str, body: &'static str) -> Request<Bytes> {
+    Request::builder()
+        .with_method(method)
+        .with_uri(uri)
+        .with_body(Bytes::from(body))
+        .unwrap()
+}
+
+fn body(response: &Response<Bytes>) -> &str {
+    std::str::from_utf8(response.body()).unwrap()
+}
+
+#[test]
+fn reuses_kept_alive_connections() {
+    let (server, uri, accepted) = start_counting(builder());
+    let client = Client::new();
+    for _ in 0..3 {
+        let response = client
+            .send(request("GET", &format!("{uri}/echo"), ""))
+            .unwrap();
+        assert_eq!(body(&response), "GET ");
+    }
+    assert_eq!(accepted.load(Ordering::SeqCst), 1);
+    server.shutdown();
+}
+
+#[test]
+fn reconnects_once_the_server_closes_an_idle_connection() {
+    let builder = builder().keep_alive_timeout(Some(Duration::from_millis(100)));
+    let (server, uri, accepted) = start_counting(builder);
+    let client = Client::new();
+    let response = client
+        .send(request("GET", &format!("{uri}/echo"), ""))
+        .unwrap();
+    assert_eq!(body(&response), "GET ");
+    thread::sleep(Duration::from_millis(400));
+
+    // A request with a body is not sent again, so the closed connection must
+    // not be used at all.
+    let response = client
+        .send(request("POST", &format!("{uri}/echo"), "data"))
+        .unwrap();
+    assert_eq!(body(&response), "POST data");
+    assert_eq!(accepted.load(Ordering::SeqCst), 2);
+    server.shutdown();
+}
+
+/// Starts a server that answers the first request of every connection, and
+/// closes it without a response once the next request arrives. Returns its
+/// URI and the number of requests that it has received.
+fn start_closing() -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let uri = format!("http://{}", listener.local_addr().unwrap());
+    let received = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&received);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = BufReader::new(stream.unwrap());
+            let mut answered = false;
+            loop {
+                let mut line = String::new();
+                // The requests have no body.
+                while line != "\r\n" {
+                    line.clear();
+                    if stream.read_line(&mut line).unwrap_or(0) == 0 {
+                        break;
+                    }
+                }
+                if line != "\r\n" {
+                    break;
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+                if answered {
+                    break;
+                }
+                let response = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+                stream.get_mut().write_all(response.as_bytes()).unwrap();
+                answered = true;
+            }
+        }
+    });
+    (uri, received)
+}
+
+#[test]
+fn resends_only_safe_requests_on_a_closed_connection() {
+    let (uri, received) = start_closing();
+    let client = Client::new();
+    client.send(request("GET", &uri, "")).unwrap();
+    let response = client.send(request("GET", &uri, "")).unwrap();
+    assert_eq!(response.body().as_ref(), b"ok");
+    assert_eq!(received.load(Ordering::SeqCst), 3);
+
+    // The server may have handled the request before closing the connection.
+    let err = client
+        .send(request("POST", &uri, "data"))
+        .err()
+        .expect("the request fails");
+    assert!(matches!(err.kind(), ErrorKind::Io(_)), "{err}");
+    assert_eq!(received.load(Ordering::SeqCst), 4);
+
+    // Nor does a request without a body make it safe.
+    client.send(request("GET", &uri, "")).unwrap();
+    assert_eq!(received.load(Ordering::SeqCst), 5);
+    let err = client
+        .send(request("POST", &uri, ""))
+        .err()
+        .expect("the request fails");
+    assert!(matches!(err.kind(), ErrorKind::Io(_)), "{err}");
+    assert_eq!(received.load(Ordering::SeqCst), 6);
+}
+
+#[test]
+fn rewrites_the_method_of_redirects() {
+    let builder = builder()
+        .route("/301", "POST", |_: &Request<Bytes>| redirect(301, "/echo"))
+        .route("/302", "POST", |_: &Request<Bytes>| redirect(302, "/echo"))
+        .route("/303", "POST", |_: &Request<Bytes>| redirect(303, "/echo"))
+        .route("/307", "POST", |_: &Request<Bytes>| redirect(307, "/echo"));
+    let (server, uri) = start(builder);
+    let client = Client::new();
+    for (path, expected) in [
+        ("/301", "GET "),
+        ("/302", "GET "),
+        ("/303", "GET "),
+        ("/307", "POST data"),
+    ] {
+        let response = client
+            .send(request("POST", &format!("{uri}{path}"), "data"))
+            .unwrap();
+        assert_eq!(body(&response), expected, "{path}");
+    }
+    server.shutdown();
+}
+
+#[test]
+fn follows_relative_redirects_with_a_uri_in_the_query() {
+    let builder = builder().route("/login", "GET", |_: &Request<Bytes>| {
+        redirect(302, "/echo?next=http://example.com/")
+    });
+    let (server, uri) = start(builder);
+    let response = Client::new()
+        .send(request("GET", &format!("{uri}/login"), ""))
+        .unwrap();
+    assert_eq!(response.status().code().get(), 200);
+    assert_eq!(body(&response), "GET ");
+    server.shutdown();
+}
+
+#[test]
+fn drops_credentials_on_cross_origin_redirects() {
+    let (other, other_uri) = start(builder());
+    let location = format!("{other_uri}/auth");
+    let builder = builder()
+        .route("/same", "GET", |_: &Request<Bytes>| redirect(302, "/auth"))
+        .route("/other", "GET", move |_: &Request<Bytes>| {
+            redirect(302, &location)
+        });
+    let (server, uri) = start(builder);
+    let client = Client::new();
+    let authorized = |path: &str| {
+        Request::builder()
+            .with_method("GET")
+            .with_uri(format!("{uri}{path}").as_str())
+            .with_header(b"Authorization", b"Bearer secret")
+            .with_body(Bytes::new())
+            .unwrap()
+    };
+    let response = client.send(authorized("/same")).unwrap();
+    assert_eq!(body(&response), "Bearer secret");
+    let response = client.send(authorized("/other")).unwrap();
+    assert_eq!(body(&response), "none");
+    server.shutdown();
+    other.shutdown();
+}
+
+#[test]
+fn fails_with_too_many_redirects() {
+    let (server, uri) = start(builder());
+    let client = Client::builder().max_redirects(3).build().unwrap();
+    let err = client
+        .send(request("GET", &format!("{uri}/loop"), ""))
+        .err()
+        .expect("the request fails");
+    assert!(matches!(err.kind(), ErrorKind::Redirect(_)), "{err}");
+    server.shutdown();
+}
+
+#[test]
+fn returns_redirects_to_other_schemes() {
+    let (server, uri) = start(builder());
+    let response = Client::new()
+        .send(request("GET", &format!("{uri}/https"), ""))
+        .unwrap();
+    assert_eq!(response.status().code().get(), 301);
+    server.shutdown();
+}
+
+#[test]
+fn times_out_without_resending() {
+    let (server, uri, accepted) = start_counting(builder());
+    let client = Client::builder()
+        .timeout(Some(Duration::from_millis(100)))
+        .build()
+        .unwrap();
+    let err = client
+        .send(request("GET", &format!("{uri}/slow"), ""))
+        .err()
+        .expect("the request fails");
+    match err.kind() {
+        ErrorKind::Io(err) => assert_eq!(err.kind(), io::ErrorKind::TimedOut),
+        _ => panic!("{err}"),
+    }
+    assert_eq!(accepted.load(Ordering::SeqCst), 1);
+    server.shutdown();
+}
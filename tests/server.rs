@@ -0,0 +1,259 @@
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+
+use tiny_http::error::{Error, Result};
+use tiny_http::http::{Request, Response};
+use tiny_http::server::build::Builder;
+use tiny_http::server::extract::Path;
+use tiny_http::server::transport::Listener;
+use tiny_http::server::{HttpServer, ServerHandle, Transport};
+
+type Server = ServerHandle<Bytes, Bytes>;
+
+fn builder() -> Builder<Bytes, Bytes> {
+    HttpServer::build()
+        .workers(2)
+        .route("/echo", "POST", |request: &Request<Bytes>| {
+            Response::builder()
+                .with_status(200)
+                .with_body(request.body().clone())
+        })
+        .route_err(
+            |_: &Request<Bytes>, err: &Error| -> Result<Response<Bytes>> {
+                Response::builder()
+                    .with_status(err.status())
+                    .with_body(Bytes::new())
+            },
+        )
+}
+
+/// Starts the server on an ephemeral port, and returns its handle and its
+/// address.
+fn start(builder: Builder<Bytes, Bytes>) -> (Server, String) {
+    let server = builder.bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap().to_string();
+    (server.spawn(), addr)
+}
+
+/// Sends the bytes on a new connection, and returns everything that the
+/// server sends back until it closes the connection.
+fn exchange(addr: &str, request: &[u8]) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    stream.write_all(request).unwrap();
+    stream.shutdown(Shutdown::Write).unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    String::from_utf8_lossy(&response).into_owned()
+}
+
+/// Returns the status lines of the responses.
+fn statuses(responses: &str) -> Vec<&str> {
+    responses
+        .split("\r\n")
+        .filter(|line| line.starts_with("HTTP/"))
+        .collect()
+}
+
+#[test]
+fn rejects_different_content_lengths() {
+    let (server, addr) = start(builder());
+    let responses = exchange(
+        &addr,
+        b"POST /echo HTTP/1.1\r\nHost: x\r\nContent-Length: 0\r\nContent-Length: 5\r\n\r\nhello",
+    );
+    assert_eq!(statuses(&responses), ["HTTP/1.1 400 Bad Request"]);
+
+    // Equal values are the same length.
+    let responses = exchange(
+        &addr,
+        b"POST /echo HTTP/1.1\r\nHost: x\r\nContent-Length: 5\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+    );
+    assert_eq!(statuses(&responses), ["HTTP/1.1 200 OK"]);
+    assert!(responses.ends_with("\r\n\r\nhello"));
+    server.shutdown();
+}
+
+#[test]
+fn rejects_transfer_encoding_with_content_length() {
+    let (server, addr) = start(builder());
+    let responses = exchange(
+        &addr,
+        b"POST /echo HTTP/1.1\r\nHost: x\r\nContent-Length: 30\r\nTransfer-Encoding: chunked\r\n\r\n\
+          0\r\n\r\nGET /nope HTTP/1.1\r\nHost: x\r\n\r\n",
+    );
+    assert_eq!(statuses(&responses), ["HTTP/1.1 400 Bad Request"]);
+    server.shutdown();
+}
+
+#[test]
+fn decodes_path_parameters() {
+    let (server, addr) = start(builder().route(
+        "/files/:name",
+        "GET",
+        |Path(name): Path<String>| -> Result<Response<Bytes>> {
+            Response::builder()
+                .with_status(200)
+                .with_body(Bytes::from(name))
+        },
+    ));
+    let responses = exchange(
+        &addr,
+        b"GET /files/a%20b HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\r\n",
+    );
+    assert_eq!(statuses(&responses), ["HTTP/1.1 200 OK"]);
+    assert!(responses.ends_with("\r\n\r\na b"));
+
+    // An encoded slash is part of the parameter, which handlers must check.
+    let responses = exchange(
+        &addr,
+        b"GET /files/..%2Fsecret HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\r\n",
+    );
+    assert_eq!(statuses(&responses), ["HTTP/1.1 200 OK"]);
+    assert!(responses.ends_with("\r\n\r\n../secret"));
+    server.shutdown();
+}
+
+#[test]
+fn rejects_heads_that_are_too_large() {
+    let (server, addr) = start(builder().max_head_size(300));
+    let header = format!("Accept: {}\r\n", "a".repeat(200));
+    let request = format!("POST /echo HTTP/1.1\r\nHost: x\r\n{header}{header}\r\n");
+    let responses = exchange(&addr, request.as_bytes());
+    assert_eq!(
+        statuses(&responses),
+        ["HTTP/1.1 431 Request Header Fields Too Large"]
+    );
+
+    // Nor is a line that does not end read whole, whose version is unknown.
+    let request = format!("GET /{} HTTP/1.1\r\n", "a".repeat(4 << 10));
+    let responses = exchange(&addr, request.as_bytes());
+    assert_eq!(
+        statuses(&responses),
+        ["HTTP/1.0 431 Request Header Fields Too Large"]
+    );
+
+    let request = format!("POST /echo HTTP/1.1\r\nHost: x\r\nConnection: close\r\n{header}\r\n");
+    let responses = exchange(&addr, request.as_bytes());
+    assert_eq!(statuses(&responses), ["HTTP/1.1 200 OK"]);
+    server.shutdown();
+}
+
+#[test]
+fn rejects_bodies_that_are_too_large() {
+    let (server, addr) = start(builder().max_body_size(5));
+    let responses = exchange(
+        &addr,
+        b"POST /echo HTTP/1.1\r\nHost: x\r\nContent-Length: 6\r\n\r\nhello!",
+    );
+    assert_eq!(statuses(&responses), ["HTTP/1.1 413 Payload Too Large"]);
+
+    let responses = exchange(
+        &addr,
+        b"POST /echo HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: chunked\r\n\r\n\
+          3\r\nhel\r\n3\r\nlo!\r\n0\r\n\r\n",
+    );
+    assert_eq!(statuses(&responses), ["HTTP/1.1 413 Payload Too Large"]);
+
+    // The length is checked before the client is asked for the body.
+    let responses = exchange(
+        &addr,
+        b"POST /echo HTTP/1.1\r\nHost: x\r\nContent-Length: 6\r\nExpect: 100-continue\r\n\r\n",
+    );
+    assert_eq!(statuses(&responses), ["HTTP/1.1 413 Payload Too Large"]);
+
+    let responses = exchange(
+        &addr,
+        b"POST /echo HTTP/1.1\r\nHost: x\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+    );
+    assert_eq!(statuses(&responses), ["HTTP/1.1 200 OK"]);
+    server.shutdown();
+}
+
+/// A listener whose first calls to `accept` fail as if the process had run
+/// out of file descriptors.
+struct Exhausted {
+    inner: TcpListener,
+    failures: AtomicUsize,
+}
+
+impl Listener for Exhausted {
+    fn accept(&self) -> io::Result<Box<dyn Transport>> {
+        let failed = self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                left.checked_sub(1)
+            });
+        match failed {
+            Ok(_) => Err(io::Error::other("too many open files")),
+            Err(_) => Listener::accept(&self.inner),
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    fn unblock(&self) -> io::Result<()> {
+        self.inner.unblock()
+    }
+}
+
+#[test]
+fn backs_off_and_reports_failures_to_accept() {
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let errors = Arc::clone(&reported);
+    let builder = builder().report_err(move |err: &Error| {
+        let source = std::error::Error::source(err).map(ToString::to_string);
+        errors.lock().unwrap().push((Instant::now(), source));
+    });
+    let listener = Exhausted {
+        inner: TcpListener::bind("127.0.0.1:0").unwrap(),
+        failures: AtomicUsize::new(3),
+    };
+    let server = builder.listen(listener).unwrap();
+    let addr = server.local_addr().unwrap().to_string();
+    let server = server.spawn();
+
+    // The connection waits in the backlog until the failures are over.
+    let responses = exchange(
+        &addr,
+        b"POST /echo HTTP/1.1\r\nHost: x\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+    );
+    assert_eq!(statuses(&responses), ["HTTP/1.1 200 OK"]);
+    let reported = reported.lock().unwrap();
+    assert_eq!(reported.len(), 3);
+    assert!(reported[2].0 - reported[0].0 >= Duration::from_millis(150));
+    assert_eq!(reported[0].1.as_deref(), Some("too many open files"));
+    drop(reported);
+    server.shutdown();
+}
+
+#[test]
+fn answers_errors_with_the_version_of_the_request() {
+    let (server, addr) = start(builder());
+    let responses = exchange(
+        &addr,
+        b"POST /echo HTTP/1.0\r\nContent-Length: 0\r\nContent-Length: 5\r\n\r\nhello",
+    );
+    assert_eq!(statuses(&responses), ["HTTP/1.0 400 Bad Request"]);
+
+    // A version that cannot be parsed is answered with HTTP/1.0.
+    let responses = exchange(&addr, b"GET /echo HTTP/x\r\n\r\n");
+    assert_eq!(statuses(&responses), ["HTTP/1.0 400 Bad Request"]);
+    let responses = exchange(&addr, b"GET /echo HTTP/3.0\r\n\r\n");
+    assert_eq!(
+        statuses(&responses),
+        ["HTTP/1.0 505 HTTP Version Not Supported"]
+    );
+    let responses = exchange(&addr, b"POST /echo\r\n\r\n");
+    assert_eq!(statuses(&responses), ["HTTP/1.0 400 Bad Request"]);
+    server.shutdown();
+}